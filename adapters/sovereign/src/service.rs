@@ -27,12 +27,20 @@ pub struct DaServiceConfig {
     pub ikura_rpc: String,
     #[serde(default = "default_rpc_timeout_seconds")]
     pub rpc_timeout_seconds: u64,
+    /// The token presented to the shim when submitting blobs, if the shim requires one for the
+    /// namespace of the rollup.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// The API token presented to the shim in the `x-api-token` header, if the shim requires one.
+    #[serde(default)]
+    pub api_token: Option<String>,
 }
 
 /// Implementation of the DA provider that uses ikura.
 #[derive(Clone)]
 pub struct DaProvider {
     namespace: ikura_nmt::Namespace,
+    auth_token: Option<String>,
     client: Client,
 }
 
@@ -40,9 +48,10 @@ impl DaProvider {
     /// Creates new instance of the service.
     pub fn new(config: DaServiceConfig, chain_params: ChainParams) -> Self {
         let request_timeout = Duration::from_secs(config.rpc_timeout_seconds);
-        let client = Client::new(config.ikura_rpc, request_timeout);
+        let client = Client::new(config.ikura_rpc, request_timeout, config.api_token);
         Self {
             namespace: ikura_nmt::Namespace::from_raw_bytes(chain_params.namespace_id),
+            auth_token: config.auth_token,
             client,
         }
     }
@@ -111,7 +120,9 @@ impl sov_rollup_interface::services::da::DaService for DaProvider {
     // Send the blob to the DA layer, using the submit_blob extrinsic
     async fn send_transaction(&self, blob: &[u8]) -> Result<(), Self::Error> {
         let client = self.client.ensure_connected().await?;
-        client.submit_blob(blob.to_vec(), self.namespace).await?;
+        client
+            .submit_blob(blob.to_vec(), self.namespace, self.auth_token.clone())
+            .await?;
         Ok(())
     }
 }
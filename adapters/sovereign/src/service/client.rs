@@ -12,6 +12,7 @@ pub struct Client {
 struct Inner {
     url: String,
     request_timeout: Duration,
+    api_token: Option<String>,
     client: Option<ClientRef>,
 }
 
@@ -29,11 +30,12 @@ impl std::ops::Deref for ClientRef {
 }
 
 impl Client {
-    pub fn new(url: String, request_timeout: Duration) -> Self {
+    pub fn new(url: String, request_timeout: Duration, api_token: Option<String>) -> Self {
         Self {
             inner: Arc::new(Mutex::new(Inner {
                 url,
                 request_timeout,
+                api_token,
                 client: None,
             })),
         }
@@ -45,8 +47,13 @@ impl Client {
             return Ok(client.clone());
         }
 
+        let mut headers = jsonrpsee::ws_client::HeaderMap::new();
+        if let Some(api_token) = &inner.api_token {
+            headers.insert("x-api-token", api_token.parse()?);
+        }
         let client = jsonrpsee::ws_client::WsClientBuilder::new()
             .request_timeout(inner.request_timeout)
+            .set_headers(headers)
             .build(inner.url.clone())
             .await?;
         let client = ClientRef {
@@ -3,24 +3,84 @@ use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use sov_rollup_interface::{da::CountedBufReader, Bytes};
 
+/// A blob relevant to the rollup.
+///
+/// The shim may submit a blob in an envelope (see `ikura-shim-common-envelope`), e.g. compressed.
+/// The hash commits to the blob as stored on chain, while the rollup reads the contents taken
+/// out of the envelope. The contents are always derived from the raw blob, including after
+/// deserialization, so checking the hash against the raw blob verifies the contents as well.
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "RawBlobTransaction", into = "RawBlobTransaction")]
 pub struct BlobTransaction {
     pub sender: Address,
-    /// Sha2 hash of the blob
+    /// Sha2 hash of the blob as stored on chain
     pub hash: Hash,
+    /// The blob as stored on chain.
+    pub raw: Bytes,
+    /// The contents of the blob.
     pub blob: CountedBufReader<Bytes>,
 }
 
+/// The serialized form of [`BlobTransaction`].
+#[derive(Serialize, Deserialize)]
+struct RawBlobTransaction {
+    sender: Address,
+    hash: Hash,
+    raw: Bytes,
+}
+
+impl From<RawBlobTransaction> for BlobTransaction {
+    fn from(raw_tx: RawBlobTransaction) -> Self {
+        Self {
+            blob: CountedBufReader::new(open_envelope(&raw_tx.raw)),
+            sender: raw_tx.sender,
+            hash: raw_tx.hash,
+            raw: raw_tx.raw,
+        }
+    }
+}
+
+impl From<BlobTransaction> for RawBlobTransaction {
+    fn from(tx: BlobTransaction) -> Self {
+        Self {
+            sender: tx.sender,
+            hash: tx.hash,
+            raw: tx.raw,
+        }
+    }
+}
+
 impl BlobTransaction {
     pub fn new(sender: Address, blob: Vec<u8>) -> Self {
         use sha2::Digest;
         let hash: [u8; 32] = sha2::Sha256::digest(&blob).into();
         let hash = Hash(hash);
-        Self {
+        RawBlobTransaction {
             sender,
             hash,
-            blob: CountedBufReader::new(Bytes::from(blob)),
+            raw: Bytes::from(blob),
         }
+        .into()
+    }
+
+    /// Returns true if the hash matches the raw blob.
+    pub fn is_hash_valid(&self) -> bool {
+        use sha2::Digest;
+        let hash: [u8; 32] = sha2::Sha256::digest(&self.raw).into();
+        hash == self.hash.0
+    }
+}
+
+/// Takes the raw blob out of its envelope.
+///
+/// A blob with a malformed envelope is passed to the rollup as is, the same way as any other blob
+/// the rollup cannot make sense of. The adapter has no decryption keys, so an encrypted blob is
+/// passed as is as well.
+fn open_envelope(raw: &Bytes) -> Bytes {
+    match ikura_shim_common_envelope::open(raw) {
+        Ok(alloc::borrow::Cow::Owned(contents)) => Bytes::from(contents),
+        Ok(alloc::borrow::Cow::Borrowed(contents)) => raw.slice_ref(contents),
+        Err(_) => raw.clone(),
     }
 }
 
@@ -77,6 +77,11 @@ impl DaVerifier for IkuraVerifier {
             block_hash: block_header.hash().0,
         };
 
+        // The contents of the blobs are derived from the raw blobs, so checking the hashes of the
+        // latter verifies the former.
+        if !txs.iter().all(|tx| tx.is_hash_valid()) {
+            anyhow::bail!("blob does not match its hash");
+        }
         let blob_hashes: Vec<[u8; 32]> = txs.iter().map(|tx| tx.hash.0).collect();
         let ip = inclusion_proof.verify(
             blob_hashes.as_slice(),
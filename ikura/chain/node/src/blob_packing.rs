@@ -0,0 +1,506 @@
+//! Packing of the blob submissions into blocks.
+//!
+//! The block builder includes the ready transactions in order of priority, and the blob
+//! submissions which exceed the blob limits of the block are rejected by the runtime with
+//! `ExhaustsResources`. Since the block builder gives up after a few of those, the remaining blobs
+//! are skipped block after block, while smaller ones could have filled the block.
+//!
+//! [`BlobPackingPool`] wraps the transaction pool used by the block builder, so that the ready
+//! blob submissions are narrowed down beforehand to the ones maximizing the fees within the blob
+//! limits, knapsack-style. The other blob submissions are deferred to a later block, together with
+//! the transactions depending on them.
+//!
+//! The blob submissions and the blob limits are found out through the `BlobsApi` of the runtime,
+//! so the packing does not depend on the extrinsic format of any runtime.
+
+use sc_transaction_pool_api::{
+    ImportNotificationStream, InPoolTransaction, PoolFuture, PoolStatus, ReadyTransactions,
+    TransactionFor, TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{traits::NumberFor, transaction_validity::TransactionTag, OpaqueExtrinsic};
+
+use ikura_primitives::{
+    opaque::{Block, Hash},
+    runtime_api::BlobsApi,
+};
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::service::ParachainClient;
+
+/// The maximum capacity of the knapsack, in units of blob size. The blob sizes are rounded up to
+/// `max_size / MAX_CAPACITY` bytes, so that the cost of the packing does not depend on the blob
+/// limits.
+const MAX_CAPACITY: usize = 4096;
+
+/// The maximum number of blob submissions packed knapsack-style, the most valuable per byte. The
+/// room left is filled greedily.
+const MAX_CANDIDATES: usize = 1024;
+
+/// The blob sizes of the ready transactions, by transaction hash, so that the runtime is asked
+/// about each transaction only once.
+#[derive(Default)]
+pub(crate) struct BlobSizes(HashMap<Hash, Option<u32>>);
+
+impl BlobSizes {
+    /// The sizes of the blobs submitted by the given ready transactions, `None` for the ones which
+    /// are not blob submissions.
+    ///
+    /// The sizes of the transactions no longer ready are forgotten.
+    pub(crate) fn of<T>(
+        &mut self,
+        client: &ParachainClient,
+        at: Hash,
+        ready: &[Arc<T>],
+    ) -> Vec<Option<u32>>
+    where
+        T: InPoolTransaction<Transaction = OpaqueExtrinsic, Hash = Hash>,
+    {
+        let runtime_api = client.runtime_api();
+        let mut sizes = HashMap::with_capacity(ready.len());
+        let ready_sizes = ready
+            .iter()
+            .map(|tx| {
+                let size = self.0.remove(tx.hash()).unwrap_or_else(|| {
+                    // If the runtime does not know the API, no transaction is a blob submission.
+                    runtime_api.blob_size(at, tx.data().clone()).ok().flatten()
+                });
+                sizes.insert(*tx.hash(), size);
+                size
+            })
+            .collect();
+        self.0 = sizes;
+        ready_sizes
+    }
+}
+
+/// Compares the values per byte of two items, given as `(size, value)`.
+fn cmp_value_per_byte(a: &(usize, u128), b: &(usize, u128)) -> Ordering {
+    let a_value = a.1.saturating_mul(b.0.max(1) as u128);
+    let b_value = b.1.saturating_mul(a.0.max(1) as u128);
+    a_value.cmp(&b_value)
+}
+
+/// Selects the items, given as `(size, value)`, maximizing the total value with at most
+/// `max_count` items of total size at most `max_size`. Returns whether each item is selected.
+///
+/// If all the items fit, they are all selected. Otherwise, the [`MAX_CANDIDATES`] items most
+/// valuable per byte are selected by the dynamic programming solution of the 0/1 knapsack problem
+/// over the sizes rounded up, within [`MAX_CAPACITY`]. The room left by the rounding is then
+/// filled greedily by value per byte, with the exact sizes. The count limit is seldom the binding
+/// one, so it is enforced afterwards by dropping the least valuable items per byte.
+fn knapsack(items: &[(usize, u128)], max_count: usize, max_size: usize) -> Vec<bool> {
+    let total_size = items
+        .iter()
+        .fold(0usize, |total, &(size, _)| total.saturating_add(size));
+    if items.len() <= max_count && total_size <= max_size {
+        return vec![true; items.len()];
+    }
+
+    // The items fitting on their own, the most valuable per byte first.
+    let mut order: Vec<usize> = (0..items.len())
+        .filter(|&i| items[i].0 <= max_size)
+        .collect();
+    order.sort_by(|&a, &b| cmp_value_per_byte(&items[b], &items[a]));
+    let candidates = &order[..order.len().min(MAX_CANDIDATES)];
+
+    let granularity = max_size.div_ceil(MAX_CAPACITY).max(1);
+    let capacity = max_size / granularity;
+    let weight = |i: usize| items[i].0.div_ceil(granularity);
+
+    // best[w] is the maximum value within the weight w, considering the candidates seen so far.
+    // The bit w of the k-th row of taken is whether the k-th candidate was taken for best[w].
+    let row_len = (capacity + 1).div_ceil(64);
+    let mut best = vec![0u128; capacity + 1];
+    let mut taken = vec![0u64; candidates.len() * row_len];
+    for (k, &i) in candidates.iter().enumerate() {
+        let weight = weight(i);
+        if weight > capacity {
+            continue;
+        }
+        for w in (weight..=capacity).rev() {
+            let with_item = best[w - weight].saturating_add(items[i].1);
+            // Ties are broken in favor of including the item.
+            if with_item >= best[w] {
+                best[w] = with_item;
+                taken[k * row_len + w / 64] |= 1 << (w % 64);
+            }
+        }
+    }
+
+    let mut selected = vec![false; items.len()];
+    let mut size = 0;
+    let mut w = capacity;
+    for (k, &i) in candidates.iter().enumerate().rev() {
+        if taken[k * row_len + w / 64] & (1 << (w % 64)) != 0 {
+            selected[i] = true;
+            size += items[i].0;
+            w -= weight(i);
+        }
+    }
+
+    // The rounding of the sizes may have left room for more.
+    for &i in &order {
+        if !selected[i] && size + items[i].0 <= max_size {
+            selected[i] = true;
+            size += items[i].0;
+        }
+    }
+
+    for &i in order.iter().filter(|&&i| selected[i]).skip(max_count) {
+        selected[i] = false;
+    }
+    selected
+}
+
+/// Narrows down the ready transactions to the blob submissions fitting in a block, given the
+/// blob size of each transaction if it is a blob submission. The order of the transactions is
+/// kept.
+fn pack<T: InPoolTransaction>(
+    ready: Vec<Arc<T>>,
+    blob_sizes: &[Option<u32>],
+    max_blobs: usize,
+    max_total_blob_size: usize,
+) -> Vec<Arc<T>> {
+    // The priority of a blob submission is proportional to its tip per byte.
+    let (blob_indices, blobs): (Vec<usize>, Vec<(usize, u128)>) = ready
+        .iter()
+        .zip(blob_sizes)
+        .enumerate()
+        .filter_map(|(i, (tx, size))| {
+            let size = (*size)? as usize;
+            Some((i, (size, *tx.priority() as u128 * size.max(1) as u128)))
+        })
+        .unzip();
+
+    let selected = knapsack(&blobs, max_blobs, max_total_blob_size);
+    let mut deferred = vec![false; ready.len()];
+    for (i, selected) in blob_indices.into_iter().zip(selected) {
+        deferred[i] = !selected;
+    }
+
+    let mut deferred_tags = HashSet::new();
+    ready
+        .into_iter()
+        .zip(deferred)
+        .filter_map(|(tx, deferred)| {
+            if deferred || requires_any(&*tx, &deferred_tags) {
+                deferred_tags.extend(tx.provides().iter().cloned());
+                None
+            } else {
+                Some(tx)
+            }
+        })
+        .collect()
+}
+
+fn requires_any<T: InPoolTransaction>(tx: &T, tags: &HashSet<TransactionTag>) -> bool {
+    tx.requires().iter().any(|tag| tags.contains(tag))
+}
+
+/// The packed ready transactions.
+struct PackedReadyTransactions<T> {
+    ready: std::vec::IntoIter<Arc<T>>,
+    /// The tags provided by the transactions reported as invalid.
+    invalid_tags: HashSet<TransactionTag>,
+}
+
+impl<T> PackedReadyTransactions<T>
+where
+    T: InPoolTransaction<Transaction = OpaqueExtrinsic, Hash = Hash>,
+{
+    /// Packs the ready transactions for a block built on top of the block `at`.
+    fn new(
+        client: &ParachainClient,
+        blob_sizes: &Mutex<BlobSizes>,
+        at: Hash,
+        ready: impl Iterator<Item = Arc<T>>,
+    ) -> Self {
+        let ready: Vec<Arc<T>> = ready.collect();
+        let ready = match client.runtime_api().blob_limits(at) {
+            Ok((max_blobs, max_total_blob_size)) => {
+                let sizes = blob_sizes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .of(client, at, &ready);
+                pack(
+                    ready,
+                    &sizes,
+                    max_blobs as usize,
+                    max_total_blob_size as usize,
+                )
+            }
+            // The runtime does not know the API, the blob limits are left to it to enforce.
+            Err(_) => ready,
+        };
+        PackedReadyTransactions {
+            ready: ready.into_iter(),
+            invalid_tags: HashSet::new(),
+        }
+    }
+}
+
+impl<T: InPoolTransaction> Iterator for PackedReadyTransactions<T> {
+    type Item = Arc<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let tx = self.ready.next()?;
+            // Transactions depending on an invalid one are invalid as well.
+            if requires_any(&*tx, &self.invalid_tags) {
+                self.invalid_tags.extend(tx.provides().iter().cloned());
+                continue;
+            }
+            return Some(tx);
+        }
+    }
+}
+
+impl<T: InPoolTransaction> ReadyTransactions for PackedReadyTransactions<T> {
+    fn report_invalid(&mut self, tx: &Self::Item) {
+        self.invalid_tags.extend(tx.provides().iter().cloned());
+    }
+}
+
+/// A transaction pool packing the ready blob submissions. See module docs.
+pub struct BlobPackingPool<P> {
+    inner: Arc<P>,
+    client: Arc<ParachainClient>,
+    blob_sizes: Arc<Mutex<BlobSizes>>,
+}
+
+impl<P> BlobPackingPool<P> {
+    /// Wrap the given transaction pool.
+    pub fn new(inner: Arc<P>, client: Arc<ParachainClient>) -> Self {
+        BlobPackingPool {
+            inner,
+            client,
+            blob_sizes: Arc::new(Mutex::new(BlobSizes::default())),
+        }
+    }
+}
+
+impl<P> TransactionPool for BlobPackingPool<P>
+where
+    P: TransactionPool<Block = Block, Hash = Hash>,
+    P::InPoolTransaction: Send + Sync + 'static,
+{
+    type Block = Block;
+    type Hash = P::Hash;
+    type InPoolTransaction = P::InPoolTransaction;
+    type Error = P::Error;
+
+    fn submit_at(
+        &self,
+        at: Hash,
+        source: TransactionSource,
+        xts: Vec<TransactionFor<Self>>,
+    ) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+        self.inner.submit_at(at, source, xts)
+    }
+
+    fn submit_one(
+        &self,
+        at: Hash,
+        source: TransactionSource,
+        xt: TransactionFor<Self>,
+    ) -> PoolFuture<TxHash<Self>, Self::Error> {
+        self.inner.submit_one(at, source, xt)
+    }
+
+    fn submit_and_watch(
+        &self,
+        at: Hash,
+        source: TransactionSource,
+        xt: TransactionFor<Self>,
+    ) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+        self.inner.submit_and_watch(at, source, xt)
+    }
+
+    fn ready_at(
+        &self,
+        at: NumberFor<Block>,
+    ) -> Pin<
+        Box<
+            dyn Future<
+                    Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+                > + Send,
+        >,
+    > {
+        let ready = self.inner.ready_at(at);
+        let client = self.client.clone();
+        let blob_sizes = self.blob_sizes.clone();
+        Box::pin(async move {
+            let ready = ready.await;
+            let at = client
+                .hash(at)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| client.info().best_hash);
+            Box::new(PackedReadyTransactions::new(
+                &client,
+                &blob_sizes,
+                at,
+                ready,
+            )) as Box<dyn ReadyTransactions<Item = _> + Send>
+        })
+    }
+
+    fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
+        let at = self.client.info().best_hash;
+        Box::new(PackedReadyTransactions::new(
+            &self.client,
+            &self.blob_sizes,
+            at,
+            self.inner.ready(),
+        ))
+    }
+
+    fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+        self.inner.remove_invalid(hashes)
+    }
+
+    fn futures(&self) -> Vec<Self::InPoolTransaction> {
+        self.inner.futures()
+    }
+
+    fn status(&self) -> PoolStatus {
+        self.inner.status()
+    }
+
+    fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+        self.inner.import_notification_stream()
+    }
+
+    fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+        self.inner.on_broadcasted(propagations)
+    }
+
+    fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+        self.inner.hash_of(xt)
+    }
+
+    fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+        self.inner.ready_transaction(hash)
+    }
+}
+
+#[cfg(test)]
+struct TestTransaction {
+    hash: Hash,
+    priority: sp_runtime::transaction_validity::TransactionPriority,
+    longevity: sp_runtime::transaction_validity::TransactionLongevity,
+    requires: Vec<TransactionTag>,
+    provides: Vec<TransactionTag>,
+}
+
+#[cfg(test)]
+impl TestTransaction {
+    fn new(id: u8, priority: u64) -> Self {
+        TestTransaction {
+            hash: Hash::repeat_byte(id),
+            priority,
+            longevity: 64,
+            requires: vec![],
+            provides: vec![vec![id]],
+        }
+    }
+}
+
+#[cfg(test)]
+impl InPoolTransaction for TestTransaction {
+    type Transaction = ();
+    type Hash = Hash;
+
+    fn data(&self) -> &() {
+        &()
+    }
+
+    fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    fn priority(&self) -> &sp_runtime::transaction_validity::TransactionPriority {
+        &self.priority
+    }
+
+    fn longevity(&self) -> &sp_runtime::transaction_validity::TransactionLongevity {
+        &self.longevity
+    }
+
+    fn requires(&self) -> &[TransactionTag] {
+        &self.requires
+    }
+
+    fn provides(&self) -> &[TransactionTag] {
+        &self.provides
+    }
+
+    fn is_propagable(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn knapsack_selects_all_if_they_fit() {
+    let items = [(100, 1), (200, 5), (300, 2)];
+    assert_eq!(knapsack(&items, 3, 600), vec![true, true, true]);
+}
+
+#[test]
+fn knapsack_maximizes_value() {
+    // The first item is the most valuable per byte, but the two others are worth more together.
+    let items = [(600, 700), (500, 500), (500, 500)];
+    assert_eq!(knapsack(&items, 10, 1000), vec![false, true, true]);
+}
+
+#[test]
+fn knapsack_skips_items_too_large() {
+    let items = [(2000, 1_000_000), (500, 1), (600, 2)];
+    assert_eq!(knapsack(&items, 10, 1000), vec![false, false, true]);
+}
+
+#[test]
+fn knapsack_enforces_max_count() {
+    let items = [(100, 100), (100, 300), (100, 200)];
+    assert_eq!(knapsack(&items, 2, 1000), vec![false, true, true]);
+}
+
+#[test]
+fn knapsack_does_not_round_small_items_up() {
+    // Far more small blobs than the knapsack capacity allows, only the blob limits bind.
+    let max_size = 2 * 1024 * 1024;
+    let items = vec![(300, 300); 10_000];
+    let selected = knapsack(&items, 100 * 1024, max_size);
+    assert_eq!(
+        selected.iter().filter(|&&selected| selected).count(),
+        max_size / 300
+    );
+}
+
+#[test]
+fn pack_defers_blobs_and_their_dependents() {
+    let mut dependent = TestTransaction::new(4, 1);
+    dependent.requires = vec![vec![2]];
+    let ready = vec![
+        Arc::new(TestTransaction::new(1, 10)),
+        Arc::new(TestTransaction::new(2, 1)),
+        Arc::new(TestTransaction::new(3, 1)),
+        Arc::new(dependent),
+    ];
+    // Only one of the blobs fits, the other one is deferred together with the transaction
+    // depending on it. The transactions which are not blob submissions are kept.
+    let blob_sizes = [Some(600), Some(600), None, None];
+
+    let packed = pack(ready, &blob_sizes, 10, 1000);
+    let packed: Vec<Hash> = packed.iter().map(|tx| tx.hash).collect();
+    assert_eq!(packed, vec![Hash::repeat_byte(1), Hash::repeat_byte(3)]);
+}
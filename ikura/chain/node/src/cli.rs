@@ -1,5 +1,6 @@
 use crate::command::export_genesis_metadata;
-use std::path::PathBuf;
+use crate::proposer::ProposerPolicy;
+use std::{path::PathBuf, time::Duration};
 
 /// Sub-commands supported by the collator.
 #[derive(Debug, clap::Subcommand)]
@@ -89,11 +90,45 @@ pub struct Cli {
     #[arg(long)]
     pub no_hardware_benchmarks: bool,
 
+    #[command(flatten)]
+    pub proposer: ProposerParams,
+
     /// Relay chain arguments
     #[arg(raw = true)]
     pub relay_chain_args: Vec<String>,
 }
 
+/// When the collator authors a block for the ready transactions.
+///
+/// A block is authored as soon as any of the thresholds is reached. If none is set, a block is
+/// authored as soon as a transaction is ready. Downward messages and code upgrades are always
+/// included without delay.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ProposerParams {
+    /// Author a block once at least this many transactions are ready.
+    #[arg(long, value_name = "COUNT")]
+    pub proposer_min_ready: Option<usize>,
+
+    /// Author a block once the ready blob submissions amount to at least this many bytes.
+    #[arg(long, value_name = "BYTES")]
+    pub proposer_min_blob_bytes: Option<usize>,
+
+    /// Author a block once transactions have been ready for this many seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub proposer_max_wait: Option<u64>,
+}
+
+impl ProposerParams {
+    /// The proposer policy specified by these parameters.
+    pub fn policy(&self) -> ProposerPolicy {
+        ProposerPolicy {
+            min_ready_count: self.proposer_min_ready,
+            min_pending_blob_bytes: self.proposer_min_blob_bytes,
+            max_wait_time: self.proposer_max_wait.map(Duration::from_secs),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RelayChainCli {
     /// The actual relay chain cli object.
@@ -245,6 +245,7 @@ pub fn run() -> Result<()> {
 		None => {
 			let runner = cli.create_runner(&cli.run.normalize())?;
 			let collator_options = cli.run.collator_options();
+			let proposer_policy = cli.proposer.policy();
 
 			runner.run_node_until_exit(|config| async move {
 				let hwbench = (!cli.no_hardware_benchmarks)
@@ -284,6 +285,7 @@ pub fn run() -> Result<()> {
 					collator_options,
 					id,
 					hwbench,
+					proposer_policy,
 				)
 				.await
 				.map(|r| r.0)
@@ -2,6 +2,7 @@
 
 #![warn(missing_docs)]
 
+mod blob_packing;
 mod chain_spec;
 #[macro_use]
 mod service;
@@ -9,6 +10,7 @@ mod cli;
 mod command;
 mod proposer;
 mod rpc;
+mod sampling;
 
 fn main() -> sc_cli::Result<()> {
     command::run()
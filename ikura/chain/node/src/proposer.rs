@@ -1,69 +1,214 @@
 //! Wrapper around a proposer which only authors blocks when certain conditions are met.
 //!
 //! These conditions are:
-//! 1. There is at least one transaction ready to post. This is used to determine that there were
-//!    non-inherent extrinsics and avoid authoring empty blocks.
+//! 1. The ready transactions are worth a block according to the [`ProposerPolicy`]. By default,
+//!    at least one transaction ready to post is enough. This is used to determine that there
+//!    were non-inherent extrinsics and avoid authoring empty blocks.
 //! 2. There is an incoming downward message from the relay chain.
 //! 3. There is a go-ahead signal for a parachain code upgrade.
 //! 4. The block is the first block of the parachain. Useful for testing.
-//! 5. The chain is not producing blocks for more than the maximum allowed number of skipped blocks
 //!
 //! If any of these conditions are met, then the block is authored.
+//!
+//! There is no limit on how long the chain may go without blocks: the runtime accounts for
+//! any number of skipped blocks exactly when adjusting the length fee.
 
-use parity_scale_codec::Decode;
-
-use sc_client_api::backend::StorageProvider;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::StorageProof;
 use sp_consensus::Proposal;
 use sp_inherents::InherentData;
 use sp_runtime::generic::Digest;
+use substrate_prometheus_endpoint::{
+    register, Counter, CounterVec, Opts, PrometheusError, Registry, U64,
+};
 
 use cumulus_client_consensus_proposer::{Error as ProposerError, ProposerInterface};
 use cumulus_pallet_parachain_system::relay_state_snapshot::RelayChainStateProof;
 use cumulus_primitives_core::ParaId;
 use cumulus_primitives_parachain_inherent::ParachainInherentData;
 
-use ikura_primitives::{
-    opaque::{Block, Header},
-    MAX_SKIPPED_BLOCKS,
-};
+use ikura_primitives::opaque::{Block, Hash, Header};
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::{blob_packing::BlobSizes, service::ParachainClient};
+
+/// When the ready transactions are worth authoring a block for.
+///
+/// A block is authored as soon as any of the thresholds is reached. If none is set, a block is
+/// authored as soon as a transaction is ready.
+#[derive(Debug, Clone, Default)]
+pub struct ProposerPolicy {
+    /// The minimum number of ready transactions, if any.
+    pub min_ready_count: Option<usize>,
+    /// The minimum number of bytes of the ready blob submissions, if any.
+    pub min_pending_blob_bytes: Option<usize>,
+    /// The maximum time ready transactions are kept waiting, if any.
+    pub max_wait_time: Option<Duration>,
+}
+
+impl ProposerPolicy {
+    /// Why the ready transactions are worth a block, if they are.
+    ///
+    /// `waiting_for` is the time the transactions have been ready for. The size of the ready blob
+    /// submissions is only computed if there is a threshold on it.
+    fn reason(
+        &self,
+        ready_count: usize,
+        waiting_for: Duration,
+        pending_blob_bytes: impl FnOnce() -> usize,
+    ) -> Option<AuthoringReason> {
+        if ready_count == 0 {
+            return None;
+        }
+        let min_ready_count = match (
+            self.min_ready_count,
+            self.min_pending_blob_bytes,
+            self.max_wait_time,
+        ) {
+            (None, None, None) => Some(1),
+            (min_ready_count, _, _) => min_ready_count,
+        };
+
+        if let Some(min_ready_count) = min_ready_count {
+            if ready_count >= min_ready_count {
+                return Some(AuthoringReason::ReadyCount);
+            }
+        }
+        if let Some(max_wait_time) = self.max_wait_time {
+            if waiting_for >= max_wait_time {
+                return Some(AuthoringReason::MaxWaitTime);
+            }
+        }
+        if let Some(min_pending_blob_bytes) = self.min_pending_blob_bytes {
+            if pending_blob_bytes() >= min_pending_blob_bytes {
+                return Some(AuthoringReason::PendingBlobBytes);
+            }
+        }
+        None
+    }
+}
 
-use crate::service::ParachainClient;
+/// Why a block was authored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AuthoringReason {
+    DownwardMessage,
+    GoAhead,
+    FirstBlock,
+    ReadyCount,
+    PendingBlobBytes,
+    MaxWaitTime,
+}
+
+impl AuthoringReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuthoringReason::DownwardMessage => "downward_message",
+            AuthoringReason::GoAhead => "go_ahead",
+            AuthoringReason::FirstBlock => "first_block",
+            AuthoringReason::ReadyCount => "ready_count",
+            AuthoringReason::PendingBlobBytes => "pending_blob_bytes",
+            AuthoringReason::MaxWaitTime => "max_wait_time",
+        }
+    }
+}
+
+/// Prometheus metrics of the proposer decisions.
+#[derive(Clone)]
+pub struct Metrics {
+    skipped_slots: Counter<U64>,
+    authored_blocks: CounterVec<U64>,
+}
+
+impl Metrics {
+    /// Register the metrics in the given registry.
+    pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Metrics {
+            skipped_slots: register(
+                Counter::new(
+                    "ikura_proposer_skipped_slots_total",
+                    "Number of slots in which no block was authored.",
+                )?,
+                registry,
+            )?,
+            authored_blocks: register(
+                CounterVec::new(
+                    Opts::new(
+                        "ikura_proposer_authored_blocks_total",
+                        "Number of blocks authored, by the reason for authoring them.",
+                    ),
+                    &["reason"],
+                )?,
+                registry,
+            )?,
+        })
+    }
+}
 
 /// Proposes blocks, but only under certain conditions. See module docs.
-pub struct BlockLimitingProposer<P, C> {
+pub struct BlockLimitingProposer<P> {
     inner: P,
-    client: Arc<C>,
     para_id: ParaId,
+    client: Arc<ParachainClient>,
     transaction_pool: Arc<sc_transaction_pool::FullPool<Block, ParachainClient>>,
+    policy: ProposerPolicy,
+    metrics: Option<Metrics>,
+    /// Since when there have been ready transactions without a block being authored.
+    waiting_since: Option<Instant>,
+    blob_sizes: BlobSizes,
 }
 
-impl<P, C> BlockLimitingProposer<P, C> {
+impl<P> BlockLimitingProposer<P> {
     /// Create a new block-limiting proposer.
     pub fn new(
         inner: P,
-        client: Arc<C>,
         para_id: ParaId,
+        client: Arc<ParachainClient>,
         transaction_pool: Arc<sc_transaction_pool::FullPool<Block, ParachainClient>>,
+        policy: ProposerPolicy,
+        metrics: Option<Metrics>,
     ) -> Self {
         BlockLimitingProposer {
             inner,
-            client,
             para_id,
+            client,
             transaction_pool,
+            policy,
+            metrics,
+            waiting_since: None,
+            blob_sizes: BlobSizes::default(),
         }
     }
+
+    /// Checks the ready transactions against the policy, for a block built on top of `parent`.
+    fn ready_transactions_reason(&mut self, parent: Hash) -> Option<AuthoringReason> {
+        let ready_count = self.transaction_pool.status().ready;
+        if ready_count == 0 {
+            self.waiting_since = None;
+            return None;
+        }
+        let waiting_for = self
+            .waiting_since
+            .get_or_insert_with(Instant::now)
+            .elapsed();
+
+        self.policy.reason(ready_count, waiting_for, || {
+            let ready: Vec<_> = self.transaction_pool.ready().collect();
+            self.blob_sizes
+                .of(&self.client, parent, &ready)
+                .into_iter()
+                .flatten()
+                .map(|size| size as usize)
+                .sum()
+        })
+    }
 }
 
 #[async_trait::async_trait]
-impl<P, C> ProposerInterface<Block> for BlockLimitingProposer<P, C>
+impl<P> ProposerInterface<Block> for BlockLimitingProposer<P>
 where
     P: ProposerInterface<Block> + Send,
-    C: StorageProvider<Block, sc_client_db::Backend<Block>> + Send + Sync,
 {
     async fn propose(
         &mut self,
@@ -75,7 +220,6 @@ where
         block_size_limit: Option<usize>,
     ) -> Result<Option<Proposal<Block, StorageProof>>, ProposerError> {
         let has_downward_message = !paras_inherent_data.downward_messages.is_empty();
-        let has_transactions = self.transaction_pool.status().ready > 0;
         let has_go_ahead = {
             let maybe_go_ahead = RelayChainStateProof::new(
                 self.para_id,
@@ -101,47 +245,106 @@ where
             // testing for detection of healthiness.
             parent_header.number == 0
         };
-        let exceeded_max_skipped_blocks = 'max_skipped: {
-            let maybe_last_relay_block_number = self.client.storage(
-                parent_header.parent_hash,
-                &sp_storage::StorageKey(ikura_primitives::last_relay_block_number_key()),
-            );
-
-            // If the state of the previous block or the last relay block number
-            // is not available, to be sure of not exceeding the max amount of
-            // skippable blocks, the block will be produced.
-            let last_relay_block_number = match maybe_last_relay_block_number {
-                Ok(Some(raw_data)) => match Decode::decode(&mut &raw_data.0[..]) {
-                    Ok(last_relay_block_number) => last_relay_block_number,
-                    Err(_) => break 'max_skipped true,
-                },
-                _ => break 'max_skipped true,
-            };
-            let relay_block_number = paras_inherent_data.validation_data.relay_parent_number;
-
-            let relay_parent_distance = relay_block_number.saturating_sub(last_relay_block_number);
-            let n_skipped_blocks = relay_parent_distance.saturating_sub(2) / 2;
-            n_skipped_blocks >= MAX_SKIPPED_BLOCKS
-        };
 
-        if has_downward_message
-            || has_go_ahead
-            || has_transactions
-            || first_block
-            || exceeded_max_skipped_blocks
-        {
-            self.inner
-                .propose(
-                    parent_header,
-                    paras_inherent_data,
-                    other_inherent_data,
-                    inherent_digests,
-                    max_duration,
-                    block_size_limit,
-                )
-                .await
+        let reason = if has_downward_message {
+            Some(AuthoringReason::DownwardMessage)
+        } else if has_go_ahead {
+            Some(AuthoringReason::GoAhead)
+        } else if first_block {
+            Some(AuthoringReason::FirstBlock)
         } else {
-            Ok(None)
+            self.ready_transactions_reason(parent_header.hash())
+        };
+
+        let Some(reason) = reason else {
+            if let Some(metrics) = &self.metrics {
+                metrics.skipped_slots.inc();
+            }
+            return Ok(None);
+        };
+
+        log::debug!("Authoring a block, reason: {}", reason.as_str());
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .authored_blocks
+                .with_label_values(&[reason.as_str()])
+                .inc();
         }
+        self.waiting_since = None;
+
+        self.inner
+            .propose(
+                parent_header,
+                paras_inherent_data,
+                other_inherent_data,
+                inherent_digests,
+                max_duration,
+                block_size_limit,
+            )
+            .await
     }
 }
+
+#[test]
+fn any_ready_transaction_by_default() {
+    let policy = ProposerPolicy::default();
+    assert_eq!(policy.reason(0, Duration::ZERO, || 0), None);
+    assert_eq!(
+        policy.reason(1, Duration::ZERO, || 0),
+        Some(AuthoringReason::ReadyCount)
+    );
+}
+
+#[test]
+fn thresholds_apply_without_min_ready_count() {
+    let policy = ProposerPolicy {
+        min_ready_count: None,
+        min_pending_blob_bytes: Some(1000),
+        max_wait_time: Some(Duration::from_secs(60)),
+    };
+    // Neither threshold is reached.
+    assert_eq!(policy.reason(5, Duration::from_secs(10), || 999), None);
+    assert_eq!(
+        policy.reason(5, Duration::from_secs(10), || 1000),
+        Some(AuthoringReason::PendingBlobBytes)
+    );
+    assert_eq!(
+        policy.reason(5, Duration::from_secs(60), || 0),
+        Some(AuthoringReason::MaxWaitTime)
+    );
+    // Nothing is ready, however long the wait.
+    assert_eq!(policy.reason(0, Duration::from_secs(600), || 1000), None);
+}
+
+#[test]
+fn min_ready_count_with_other_thresholds() {
+    let policy = ProposerPolicy {
+        min_ready_count: Some(10),
+        min_pending_blob_bytes: Some(1000),
+        max_wait_time: None,
+    };
+    assert_eq!(policy.reason(9, Duration::ZERO, || 0), None);
+    assert_eq!(
+        policy.reason(10, Duration::ZERO, || 0),
+        Some(AuthoringReason::ReadyCount)
+    );
+    assert_eq!(
+        policy.reason(1, Duration::ZERO, || 1000),
+        Some(AuthoringReason::PendingBlobBytes)
+    );
+}
+
+#[test]
+fn pending_blob_bytes_computed_only_if_needed() {
+    let policy = ProposerPolicy {
+        min_ready_count: Some(1),
+        min_pending_blob_bytes: Some(1000),
+        max_wait_time: None,
+    };
+    // The cheaper thresholds are checked first.
+    let pending_blob_bytes = || -> usize { panic!("the blob sizes are not needed") };
+    assert_eq!(
+        policy.reason(1, Duration::ZERO, pending_blob_bytes),
+        Some(AuthoringReason::ReadyCount)
+    );
+}
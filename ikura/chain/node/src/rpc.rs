@@ -9,7 +9,7 @@ use std::sync::Arc;
 
 use ikura_primitives::{opaque::Block, AccountId, Balance, Nonce};
 
-use sc_client_api::AuxStore;
+use sc_client_api::{AuxStore, BlockBackend};
 pub use sc_rpc::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
@@ -27,6 +27,8 @@ pub struct FullDeps<C, P> {
     pub pool: Arc<P>,
     /// Whether to deny unsafe calls
     pub deny_unsafe: DenyUnsafe,
+    /// The data squares of the latest blocks, served by the sampling RPC.
+    pub data_squares: Arc<crate::sampling::DataSquares>,
 }
 
 /// Instantiate all RPC extensions.
@@ -38,12 +40,14 @@ where
         + HeaderBackend<Block>
         + AuxStore
         + HeaderMetadata<Block, Error = BlockChainError>
+        + BlockBackend<Block>
         + Send
         + Sync
         + 'static,
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
     C::Api: BlockBuilder<Block>,
+    C::Api: ikura_primitives::runtime_api::BlobsApi<Block>,
     P: TransactionPool + Sync + Send + 'static,
 {
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
@@ -54,9 +58,15 @@ where
         client,
         pool,
         deny_unsafe,
+        data_squares,
     } = deps;
 
     module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
-    module.merge(TransactionPayment::new(client).into_rpc())?;
+    module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+    module.merge(crate::sampling::rpc_module(
+        client,
+        data_squares,
+        deny_unsafe,
+    )?)?;
     Ok(module)
 }
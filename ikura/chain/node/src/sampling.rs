@@ -0,0 +1,357 @@
+//! Data availability sampling RPC.
+//!
+//! The blobs pallet commits to the erasure-coded data square of the blobs of each block in the
+//! `sdsq` digest of the header. The `blobs_sample` method serves the shares of that square
+//! together with their proofs, so that light clients can check that the data of a block is
+//! available by verifying a few random samples against the header.
+//!
+//! Building a data square requires applying the extrinsics of the block again and extending the
+//! blob data, so the squares of the latest imported blocks are built once at import, by
+//! [`build_data_squares`], and kept in [`DataSquares`]. The older blocks are only sampled by the
+//! RPC if unsafe methods are allowed.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use futures::StreamExt;
+
+use ikura_nmt::{DataSquare, DataSquareRoot, Namespace, Sample};
+use ikura_primitives::{
+    opaque::{Block, Hash, Header},
+    runtime_api::BlobsApi,
+};
+use jsonrpsee::{core::Error as RpcError, types::error::CallError, RpcModule};
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sc_rpc::DenyUnsafe;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+    generic::DigestItem,
+    traits::{Block as _, Header as _},
+};
+
+/// The name of the RPC method. Its parameters are the block hash, the row and the column of the
+/// share in the extended data square.
+const METHOD: &str = "blobs_sample";
+
+/// The number of data squares kept in memory. The extended square of a block takes up to four
+/// times the blob data of the block.
+const CACHED_SQUARES: usize = 16;
+
+/// The access to the blocks whose data squares are sampled.
+pub trait BlobSource: Send + Sync + 'static {
+    /// The header of the given block.
+    fn header(&self, block_hash: Hash) -> anyhow::Result<Header>;
+
+    /// The namespace IDs and data of the blobs stored by the given block, in the order of
+    /// submission.
+    fn submitted_blobs(
+        &self,
+        block_hash: Hash,
+        header: &Header,
+    ) -> anyhow::Result<Vec<(u128, Vec<u8>)>>;
+}
+
+impl<C> BlobSource for C
+where
+    C: BlockBackend<Block>
+        + HeaderBackend<Block>
+        + ProvideRuntimeApi<Block>
+        + Send
+        + Sync
+        + 'static,
+    C::Api: BlobsApi<Block>,
+{
+    fn header(&self, block_hash: Hash) -> anyhow::Result<Header> {
+        HeaderBackend::header(self, block_hash)?
+            .ok_or_else(|| anyhow::anyhow!("unknown block {}", block_hash))
+    }
+
+    fn submitted_blobs(
+        &self,
+        block_hash: Hash,
+        header: &Header,
+    ) -> anyhow::Result<Vec<(u128, Vec<u8>)>> {
+        let body = self
+            .block_body(block_hash)?
+            .ok_or_else(|| anyhow::anyhow!("the body of the block is not available"))?;
+        // Only the runtime knows which of the extrinsics stored a blob, so it applies them again
+        // on top of the parent.
+        let block = Block::new(header.clone(), body);
+        Ok(self
+            .runtime_api()
+            .submitted_blobs(*header.parent_hash(), block)?)
+    }
+}
+
+/// The data squares of the latest imported or sampled blocks, the least recently used first.
+#[derive(Default)]
+pub struct DataSquares(Mutex<VecDeque<(Hash, Arc<DataSquare>)>>);
+
+impl DataSquares {
+    fn get(&self, block_hash: Hash) -> Option<Arc<DataSquare>> {
+        let mut squares = self.0.lock().unwrap();
+        let index = squares.iter().position(|(hash, _)| *hash == block_hash)?;
+        let entry = squares.remove(index)?;
+        let square = entry.1.clone();
+        squares.push_back(entry);
+        Some(square)
+    }
+
+    fn insert(&self, block_hash: Hash, square: Arc<DataSquare>) {
+        let mut squares = self.0.lock().unwrap();
+        squares.retain(|(hash, _)| *hash != block_hash);
+        if squares.len() == CACHED_SQUARES {
+            squares.pop_front();
+        }
+        squares.push_back((block_hash, square));
+    }
+}
+
+/// Builds the data squares of the imported blocks into `squares`, as they are imported.
+///
+/// This is blocking, so it should be spawned as a blocking task. The blocks imported during the
+/// major sync are not notified, so they are skipped.
+pub async fn build_data_squares<C>(client: Arc<C>, squares: Arc<DataSquares>)
+where
+    C: BlobSource + BlockchainEvents<Block>,
+{
+    let mut imported = client.import_notification_stream();
+    while let Some(notification) = imported.next().await {
+        match data_square(&*client, notification.hash) {
+            Ok(square) => squares.insert(notification.hash, Arc::new(square)),
+            Err(e) => log::debug!(
+                "Cannot build the data square of block {}: {}",
+                notification.hash,
+                e
+            ),
+        }
+    }
+}
+
+/// Builds the data square of the given block and checks it against the header.
+fn data_square<C: BlobSource>(client: &C, block_hash: Hash) -> anyhow::Result<DataSquare> {
+    let header = client.header(block_hash)?;
+    let root = data_square_root(header.digest().logs())
+        .ok_or_else(|| anyhow::anyhow!("no data square root in the header"))?;
+
+    let blobs = client.submitted_blobs(block_hash, &header)?;
+    let square = ikura_nmt::data_square_from_blobs(
+        blobs
+            .iter()
+            .map(|(namespace_id, data)| (Namespace::from_u128_be(*namespace_id), &data[..]))
+            .collect(),
+    );
+    // Defensive only. The runtime commits to the very same blobs.
+    anyhow::ensure!(
+        square.root() == root,
+        "the blobs of the block do not match its data square root"
+    );
+    Ok(square)
+}
+
+struct Sampling<C> {
+    client: Arc<C>,
+    squares: Arc<DataSquares>,
+    deny_unsafe: DenyUnsafe,
+}
+
+/// Instantiate the sampling RPC extension, serving the samples of the squares built by
+/// [`build_data_squares`].
+///
+/// The squares of the other blocks are built on demand only if unsafe methods are allowed.
+pub fn rpc_module<C: BlobSource>(
+    client: Arc<C>,
+    squares: Arc<DataSquares>,
+    deny_unsafe: DenyUnsafe,
+) -> Result<RpcModule<()>, RpcError> {
+    let mut module = RpcModule::new(Sampling {
+        client,
+        squares,
+        deny_unsafe,
+    });
+    // Building a square on demand blocks for a while.
+    module.register_blocking_method(METHOD, |params, sampling| {
+        let (block_hash, row, col): (Hash, u32, u32) = params.parse()?;
+        sampling
+            .sample(block_hash, row, col)
+            .map_err(|e| CallError::Failed(e).into())
+    })?;
+
+    let mut extension = RpcModule::new(());
+    extension.merge(module)?;
+    Ok(extension)
+}
+
+impl<C: BlobSource> Sampling<C> {
+    fn sample(&self, block_hash: Hash, row: u32, col: u32) -> anyhow::Result<Sample> {
+        let square = self.data_square(block_hash)?;
+        square.sample(row as usize, col as usize).ok_or_else(|| {
+            anyhow::anyhow!(
+                "({}, {}) is out of the {}x{} data square",
+                row,
+                col,
+                square.extended_width(),
+                square.extended_width()
+            )
+        })
+    }
+
+    fn data_square(&self, block_hash: Hash) -> anyhow::Result<Arc<DataSquare>> {
+        if let Some(square) = self.squares.get(block_hash) {
+            return Ok(square);
+        }
+        if self.deny_unsafe.check_if_safe().is_err() {
+            anyhow::bail!(
+                "the data square of block {} is not available, only the latest blocks can be sampled",
+                block_hash
+            );
+        }
+
+        let square = Arc::new(data_square(&*self.client, block_hash)?);
+        self.squares.insert(block_hash, square.clone());
+        Ok(square)
+    }
+}
+
+/// Extracts the data square root committed in the given header logs.
+fn data_square_root(logs: &[DigestItem]) -> Option<DataSquareRoot> {
+    let bytes = logs.iter().find_map(|log| match log {
+        DigestItem::Other(bytes) if bytes.starts_with(b"sdsq") => Some(&bytes[4..]),
+        _ => None,
+    })?;
+    Some(DataSquareRoot::from_raw_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+struct TestChain {
+    header: Header,
+    blobs: Vec<(u128, Vec<u8>)>,
+}
+
+#[cfg(test)]
+impl BlobSource for TestChain {
+    fn header(&self, block_hash: Hash) -> anyhow::Result<Header> {
+        anyhow::ensure!(block_hash == self.header.hash(), "unknown block");
+        Ok(self.header.clone())
+    }
+
+    fn submitted_blobs(&self, _: Hash, _: &Header) -> anyhow::Result<Vec<(u128, Vec<u8>)>> {
+        Ok(self.blobs.clone())
+    }
+}
+
+#[cfg(test)]
+fn test_header(root: &DataSquareRoot) -> Header {
+    let mut digest = b"sdsq".to_vec();
+    digest.extend_from_slice(&root.to_raw_bytes());
+    Header::new(
+        1,
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        sp_runtime::generic::Digest {
+            logs: vec![DigestItem::Other(digest)],
+        },
+    )
+}
+
+#[cfg(test)]
+fn call_sample(
+    module: &RpcModule<()>,
+    block_hash: Hash,
+    row: u32,
+    col: u32,
+) -> Result<Sample, RpcError> {
+    let params = [
+        serde_json::to_value(block_hash).unwrap(),
+        row.into(),
+        col.into(),
+    ];
+    // The method is blocking, so it is run on the blocking threads of tokio.
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(module.call(METHOD, params))
+}
+
+#[test]
+fn rpc_serves_samples_of_the_committed_square() {
+    let blobs = vec![(2, vec![1u8; 1500]), (1, vec![2u8; 700])];
+    let root = ikura_nmt::data_square_from_blobs(
+        blobs
+            .iter()
+            .map(|(namespace_id, data)| (Namespace::from_u128_be(*namespace_id), &data[..]))
+            .collect(),
+    )
+    .root();
+    let header = test_header(&root);
+    let block_hash = header.hash();
+    let chain = Arc::new(TestChain { header, blobs });
+    let module = rpc_module(chain, Default::default(), DenyUnsafe::No).unwrap();
+
+    let width = root.extended_width() as u32;
+    for (row, col) in [
+        (0, 0),
+        (1, width - 1),
+        (width - 1, 0),
+        (width - 1, width - 1),
+    ] {
+        let sample = call_sample(&module, block_hash, row, col).unwrap();
+        assert_eq!((sample.row, sample.col), (row, col));
+        sample.verify(&root).unwrap();
+    }
+
+    assert!(call_sample(&module, block_hash, width, 0).is_err());
+    assert!(call_sample(&module, Hash::repeat_byte(1), 0, 0).is_err());
+}
+
+#[test]
+fn rpc_rejects_blobs_not_matching_the_header() {
+    let root = ikura_nmt::data_square_from_blobs(vec![]).root();
+    let header = test_header(&root);
+    let block_hash = header.hash();
+    let blobs = vec![(1, vec![1u8; 100])];
+    let chain = Arc::new(TestChain { header, blobs });
+    let module = rpc_module(chain, Default::default(), DenyUnsafe::No).unwrap();
+
+    assert!(call_sample(&module, block_hash, 0, 0).is_err());
+}
+
+#[test]
+fn rpc_only_builds_squares_if_unsafe_methods_are_allowed() {
+    let blobs = vec![(1, vec![1u8; 100])];
+    let root =
+        ikura_nmt::data_square_from_blobs(vec![(Namespace::from_u128_be(1), &blobs[0].1[..])])
+            .root();
+    let header = test_header(&root);
+    let block_hash = header.hash();
+    let chain = Arc::new(TestChain { header, blobs });
+    let squares = Arc::new(DataSquares::default());
+    let module = rpc_module(chain.clone(), squares.clone(), DenyUnsafe::Yes).unwrap();
+
+    assert!(call_sample(&module, block_hash, 0, 0).is_err());
+
+    // As built on import.
+    squares.insert(
+        block_hash,
+        Arc::new(data_square(&*chain, block_hash).unwrap()),
+    );
+    let sample = call_sample(&module, block_hash, 0, 0).unwrap();
+    sample.verify(&root).unwrap();
+}
+
+#[test]
+fn data_squares_evict_the_least_recently_used() {
+    let square = Arc::new(ikura_nmt::data_square_from_blobs(vec![]));
+    let squares = DataSquares::default();
+    for i in 0..CACHED_SQUARES as u64 {
+        squares.insert(Hash::from_low_u64_be(i), square.clone());
+    }
+    assert!(squares.get(Hash::from_low_u64_be(0)).is_some());
+
+    squares.insert(Hash::from_low_u64_be(CACHED_SQUARES as u64), square);
+    assert!(squares.get(Hash::from_low_u64_be(0)).is_some());
+    assert!(squares.get(Hash::from_low_u64_be(1)).is_none());
+}
@@ -14,7 +14,10 @@ use cumulus_client_service::{
     build_network, build_relay_chain_interface, prepare_node_config, start_relay_chain_tasks,
     BuildNetworkParams, CollatorSybilResistance, DARecoveryProfile, StartRelayChainTasksParams,
 };
-use cumulus_primitives_core::{relay_chain::CollatorPair, ParaId};
+use cumulus_primitives_core::{
+    relay_chain::{CollatorPair, ValidationCode},
+    ParaId,
+};
 use cumulus_relay_chain_interface::{OverseerHandle, RelayChainInterface};
 
 // Substrate Imports
@@ -30,7 +33,10 @@ use sc_transaction_pool_api::OffchainTransactionPoolFactory;
 use sp_keystore::KeystorePtr;
 use substrate_prometheus_endpoint::Registry;
 
-use crate::proposer::BlockLimitingProposer;
+use crate::{
+    blob_packing::BlobPackingPool,
+    proposer::{BlockLimitingProposer, Metrics as ProposerMetrics, ProposerPolicy},
+};
 
 // This is fine, even for the Kusama and Polkadot parachains.
 //
@@ -163,6 +169,7 @@ async fn start_node_impl(
     collator_options: CollatorOptions,
     para_id: ParaId,
     hwbench: Option<sc_sysinfo::HwBench>,
+    proposer_policy: ProposerPolicy,
 ) -> sc_service::error::Result<(TaskManager, Arc<ParachainClient>)> {
     let parachain_config = prepare_node_config(parachain_config);
 
@@ -227,6 +234,13 @@ async fn start_node_impl(
         );
     }
 
+    let data_squares = Arc::new(crate::sampling::DataSquares::default());
+    task_manager.spawn_handle().spawn_blocking(
+        "blobs-data-squares",
+        None,
+        crate::sampling::build_data_squares(client.clone(), data_squares.clone()),
+    );
+
     let rpc_builder = {
         let client = client.clone();
         let transaction_pool = transaction_pool.clone();
@@ -236,6 +250,7 @@ async fn start_node_impl(
                 client: client.clone(),
                 pool: transaction_pool.clone(),
                 deny_unsafe,
+                data_squares: data_squares.clone(),
             };
 
             crate::rpc::create_full(deps).map_err(Into::into)
@@ -249,7 +264,7 @@ async fn start_node_impl(
         task_manager: &mut task_manager,
         config: parachain_config,
         keystore: params.keystore_container.keystore(),
-        backend,
+        backend: backend.clone(),
         network: network.clone(),
         sync_service: sync_service.clone(),
         system_rpc_tx,
@@ -313,6 +328,7 @@ async fn start_node_impl(
     if validator {
         start_consensus(
             client.clone(),
+            backend,
             block_import,
             prometheus_registry.as_ref(),
             telemetry.as_ref().map(|t| t.handle()),
@@ -326,6 +342,7 @@ async fn start_node_impl(
             collator_key.expect("Command line arguments do not allow this. qed"),
             overseer_handle,
             announce_block,
+            proposer_policy,
         )?;
     }
 
@@ -368,6 +385,7 @@ fn build_import_queue(
 
 fn start_consensus(
     client: Arc<ParachainClient>,
+    backend: Arc<ParachainBackend>,
     block_import: ParachainBlockImport,
     prometheus_registry: Option<&Registry>,
     telemetry: Option<TelemetryHandle>,
@@ -381,9 +399,10 @@ fn start_consensus(
     collator_key: CollatorPair,
     overseer_handle: OverseerHandle,
     announce_block: Arc<dyn Fn(Hash, Option<Vec<u8>>) + Send + Sync>,
+    proposer_policy: ProposerPolicy,
 ) -> Result<(), sc_service::Error> {
-    use cumulus_client_consensus_aura::collators::basic::{
-        self as basic_aura, Params as BasicAuraParams,
+    use cumulus_client_consensus_aura::collators::lookahead::{
+        self as lookahead_aura, Params as LookaheadAuraParams,
     };
 
     // NOTE: because we use Aura here explicitly, we can use `CollatorSybilResistance::Resistant`
@@ -395,14 +414,27 @@ fn start_consensus(
         let proposer_factory = sc_basic_authorship::ProposerFactory::with_proof_recording(
             task_manager.spawn_handle(),
             client.clone(),
-            transaction_pool.clone(),
+            Arc::new(BlobPackingPool::new(
+                transaction_pool.clone(),
+                client.clone(),
+            )),
             prometheus_registry,
             telemetry.clone(),
         );
 
         let proposer = Proposer::new(proposer_factory);
+        let metrics = prometheus_registry
+            .map(ProposerMetrics::register)
+            .transpose()?;
 
-        BlockLimitingProposer::new(proposer, client.clone(), para_id, transaction_pool)
+        BlockLimitingProposer::new(
+            proposer,
+            para_id,
+            client.clone(),
+            transaction_pool,
+            proposer_policy,
+            metrics,
+        )
     };
 
     let collator_service = CollatorService::new(
@@ -412,11 +444,18 @@ fn start_consensus(
         client.clone(),
     );
 
-    let params = BasicAuraParams {
+    let params = LookaheadAuraParams {
         create_inherent_data_providers: move |_, ()| async move { Ok(()) },
         block_import,
-        para_client: client,
+        para_client: client.clone(),
+        para_backend: backend,
         relay_client: relay_chain_interface,
+        code_hash_provider: move |block_hash| {
+            client
+                .code_at(block_hash)
+                .ok()
+                .map(|c| ValidationCode::from(c).hash())
+        },
         sync_oracle,
         keystore,
         collator_key,
@@ -426,15 +465,24 @@ fn start_consensus(
         relay_chain_slot_duration,
         proposer,
         collator_service,
-        // Very limited proposal time.
-        authoring_duration: Duration::from_millis(500),
-        collation_request_receiver: None,
+        // Leaves time for the block to be imported within the 2 seconds of execution
+        // allowed by the runtime.
+        authoring_duration: Duration::from_millis(1500),
     };
 
-    let fut =
-        basic_aura::run::<Block, sp_consensus_aura::sr25519::AuthorityPair, _, _, _, _, _, _, _>(
-            params,
-        );
+    let fut = lookahead_aura::run::<
+        Block,
+        sp_consensus_aura::sr25519::AuthorityPair,
+        _,
+        _,
+        _,
+        _,
+        _,
+        _,
+        _,
+        _,
+        _,
+    >(params);
     task_manager
         .spawn_essential_handle()
         .spawn("aura", None, fut);
@@ -449,6 +497,7 @@ pub async fn start_parachain_node(
     collator_options: CollatorOptions,
     para_id: ParaId,
     hwbench: Option<sc_sysinfo::HwBench>,
+    proposer_policy: ProposerPolicy,
 ) -> sc_service::error::Result<(TaskManager, Arc<ParachainClient>)> {
     start_node_impl(
         parachain_config,
@@ -456,6 +505,7 @@ pub async fn start_parachain_node(
         collator_options,
         para_id,
         hwbench,
+        proposer_policy,
     )
     .await
 }
@@ -6,9 +6,10 @@ use crate::Pallet as Blobs;
 use frame_benchmarking::__private::traits::Hooks;
 #[allow(unused)]
 use frame_benchmarking::v2::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
-use frame_support::traits::Get;
+use frame_support::traits::{Currency, Get};
 use frame_system::RawOrigin;
 use parity_scale_codec::Encode;
+use sp_runtime::traits::Bounded;
 use sp_std::vec;
 
 // Command to run the benchmarks:
@@ -25,13 +26,16 @@ use sp_std::vec;
 mod benchmarks {
     use super::*;
 
-    fn init_state<T: Config>(caller: T::AccountId, x: u32) {
+    fn init_state<T: Config>(caller: T::AccountId, x: u32, blob_len: u32) {
+        // enough to pay the blob fees of all the submitted blobs
+        T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+
         for ext_index in 0..x {
             sp_io::storage::set(b":extrinsic_index", &(ext_index).encode());
             Blobs::<T>::submit_blob(
                 RawOrigin::Signed(caller.clone()).into(),
                 (ext_index as u128).into(),
-                ext_index.to_le_bytes().to_vec(),
+                vec![ext_index as u8; blob_len as usize],
             )
             .expect("Preparation Extrinsic failed");
         }
@@ -48,7 +52,7 @@ mod benchmarks {
 
         // the values in the submitted data are not important so
         // the ext_index will be used as namespace_id and as blob
-        init_state::<T>(caller.clone(), x);
+        init_state::<T>(caller.clone(), x, 4);
         sp_io::storage::set(b":extrinsic_index", &(x).encode());
 
         // Create a random blob that needs to be hashed on chain
@@ -66,10 +70,16 @@ mod benchmarks {
 
     #[benchmark]
     // x represent the amount of SubmittedBlobMetadata already stored in BlobList
-    fn on_finalize(x: Linear<0, { T::MaxBlobs::get() }>) {
+    // while y is the total size of the blobs in bytes, split evenly among them, which are all
+    // hashed into their share commitments and laid out in the data square
+    fn on_finalize(
+        x: Linear<1, { T::MaxBlobs::get() }>,
+        y: Linear<0, { T::MaxTotalBlobSize::get() }>,
+    ) {
         let caller: T::AccountId = whitelisted_caller();
 
-        init_state::<T>(caller.clone(), x);
+        let blob_len = (y / x).min(T::MaxBlobSize::get());
+        init_state::<T>(caller.clone(), x, blob_len);
 
         #[block]
         {
@@ -78,6 +88,7 @@ mod benchmarks {
 
         // Every storage Item should be killed
         assert_eq!(BlobList::<T>::get().len(), 0);
+        assert_eq!(BlobData::<T>::get().len(), 0);
         assert_eq!(TotalBlobs::<T>::get(), 0);
         assert_eq!(TotalBlobSize::<T>::get(), 0);
     }
@@ -22,11 +22,13 @@ use scale_info::TypeInfo;
 use sp_runtime::{
     traits::{DispatchInfoOf, SignedExtension},
     transaction_validity::{
-        InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+        InvalidTransaction, TransactionLongevity, TransactionValidity, TransactionValidityError,
+        ValidTransaction,
     },
+    Saturating,
 };
 
-use frame_support::traits::{Get, IsSubType};
+use frame_support::traits::{Currency, Get, IsSubType};
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -35,10 +37,21 @@ pub mod pallet {
     use frame_support::{
         dispatch::DispatchResultWithPostInfo,
         pallet_prelude::{ValueQuery, *},
+        traits::{Currency, ExistenceRequirement, OnUnbalanced, WithdrawReasons},
     };
     use frame_system::pallet_prelude::*;
+    use sp_runtime::{FixedPointNumber, Perquintill, Saturating};
     use sp_std::prelude::*;
 
+    /// The multiplier of the blob fee.
+    pub type Multiplier = sp_runtime::FixedU128;
+
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::NegativeImbalance;
+
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
     pub trait Config: frame_system::Config {
@@ -57,6 +70,35 @@ pub mod pallet {
         #[pallet::constant]
         type MaxTotalBlobSize: Get<u32>;
 
+        /// The currency the blob fee is paid in.
+        type Currency: Currency<Self::AccountId>;
+
+        /// The handler of the charged blob fees, e.g. `()` to burn them.
+        type OnBlobFee: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// The fee per byte of blob data, before the adjustment by `NextBlobFeeMultiplier`.
+        ///
+        /// The blob fee is charged by `submit_blob` on top of the transaction fee.
+        #[pallet::constant]
+        type BlobByteFee: Get<BalanceOf<Self>>;
+
+        // `NextBlobFeeMultiplier` parameters
+        /// The targeted total size of the blobs in a block, as a fraction of `MaxTotalBlobSize`.
+        #[pallet::constant]
+        type TargetTotalBlobSize: Get<Perquintill>;
+        /// Higher values make the blob fee multiplier change more rapidly.
+        #[pallet::constant]
+        type AdjustmentVariableBlobSize: Get<Multiplier>;
+        #[pallet::constant]
+        type MinimumMultiplierBlobSize: Get<Multiplier>;
+        #[pallet::constant]
+        type MaximumMultiplierBlobSize: Get<Multiplier>;
+
+        /// The number of blocks a blob submission is valid for in the transaction pool. Blobs
+        /// that could not be included by then are dropped rather than kept around indefinitely.
+        #[pallet::constant]
+        type BlobLongevity: Get<TransactionLongevity>;
+
         // The weight information of this pallet.
         type WeightInfo: WeightInfo;
     }
@@ -74,6 +116,22 @@ pub mod pallet {
     #[pallet::whitelist_storage]
     pub type TotalBlobs<T: Config> = StorageValue<_, u32, ValueQuery>;
 
+    pub struct NextBlobFeeMultiplierDefault;
+    impl Get<Multiplier> for NextBlobFeeMultiplierDefault {
+        fn get() -> Multiplier {
+            Multiplier::saturating_from_integer(1)
+        }
+    }
+
+    /// The multiplier applied to the blob fee, updated at the end of each block depending on
+    /// `TotalBlobSize` against the `TargetTotalBlobSize`.
+    ///
+    /// This is independent of the length multiplier of the transaction fee, so that the
+    /// congestion of the blob space does not affect the fees of the other transactions.
+    #[pallet::storage]
+    pub type NextBlobFeeMultiplier<T: Config> =
+        StorageValue<_, Multiplier, ValueQuery, NextBlobFeeMultiplierDefault>;
+
     #[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Clone)]
     #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
     pub struct SubmittedBlobMetadata<AccountId> {
@@ -91,6 +149,13 @@ pub mod pallet {
     pub type BlobList<T: Config> =
         StorageValue<_, Vec<SubmittedBlobMetadata<T::AccountId>>, ValueQuery>;
 
+    /// The data of all submitted blobs, in the same order as BlobList. Kept until the end of the
+    /// block to build the data square.
+    #[pallet::storage]
+    #[pallet::whitelist_storage]
+    pub type BlobData<T: Config> =
+        StorageValue<_, BoundedVec<BoundedVec<u8, T::MaxBlobSize>, T::MaxBlobs>, ValueQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
@@ -107,6 +172,13 @@ pub mod pallet {
             /// The SHA256 hash of the blob.
             blob_hash: [u8; 32],
         },
+        /// The blob fee was charged for a blob.
+        BlobFeeCharged {
+            /// Who paid the fee.
+            who: T::AccountId,
+            /// The charged fee.
+            fee: BalanceOf<T>,
+        },
     }
 
     // Errors inform users that something went wrong.
@@ -114,14 +186,108 @@ pub mod pallet {
     pub enum Error<T> {
         /// The extrinsic index is not available.
         NoExtrinsicIndex,
+        /// The balance of the submitter is too low to pay the blob fee.
+        InsufficientBalanceForBlobFee,
     }
 
     impl<T: Config> Pallet<T> {
-        /// Emit a digest item containing the root of the namespace merkle tree.
+        /// The read of `NextBlobFeeMultiplier` by `submit_blob` to charge the blob fee, which
+        /// `WeightInfo::submit_blob` does not cover yet.
+        ///
+        /// Remove it once the weights are regenerated with `benchmark pallet`.
+        pub(crate) fn unbenchmarked_submit_blob_weight() -> Weight {
+            // `NextBlobFeeMultiplier` is a 16-byte value, with a proof size of 1501 bytes.
+            T::DbWeight::get()
+                .reads(1)
+                .saturating_add(Weight::from_parts(0, 1501))
+        }
+
+        /// The cost per byte of blob of `on_finalize`, which hashes the blobs into their share
+        /// commitments and builds the data square over them. `WeightInfo::on_finalize` does not
+        /// cover it yet.
+        ///
+        /// This is an estimate of twice the native time of a block of 2 MiB of blobs, not a
+        /// measurement. Remove it once the weights are regenerated with `benchmark pallet`,
+        /// which measures the `y` component of the `on_finalize` benchmark.
+        pub(crate) fn unbenchmarked_blob_bytes_weight(blob_len: u32) -> Weight {
+            Weight::from_parts(303_000, 0).saturating_mul(blob_len.into())
+        }
+
+        /// The blob fee of a blob of the given length submitted in the current block.
+        pub fn blob_fee(blob_len: u32) -> BalanceOf<T> {
+            let fee = T::BlobByteFee::get().saturating_mul(blob_len.into());
+            NextBlobFeeMultiplier::<T>::get().saturating_mul_int(fee)
+        }
+
+        /// Computes the blob fee multiplier of the next block, given the total size of the blobs
+        /// in the current one.
+        ///
+        /// This follows `TargetedFeeAdjustment::convert`, with the total blob size as the
+        /// limiting dimension, the same way the length multiplier is updated.
+        pub fn next_blob_fee_multiplier(previous: Multiplier, total_blob_size: u32) -> Multiplier {
+            let min_multiplier = T::MinimumMultiplierBlobSize::get();
+            let max_multiplier = T::MaximumMultiplierBlobSize::get();
+            // Defensive only. The multiplier in storage should always be at least the minimum.
+            let previous = previous.max(min_multiplier);
+
+            let max_total_blob_size = T::MaxTotalBlobSize::get() as u64;
+            let target_size = (T::TargetTotalBlobSize::get() * max_total_blob_size) as u128;
+            let total_blob_size = (total_blob_size as u64).min(max_total_blob_size) as u128;
+
+            // determines if the first_term is positive
+            let positive = total_blob_size >= target_size;
+            let diff_abs = total_blob_size.max(target_size) - total_blob_size.min(target_size);
+
+            let diff = Multiplier::saturating_from_rational(diff_abs, max_total_blob_size.max(1));
+            let diff_squared = diff.saturating_mul(diff);
+
+            let adjustment_variable = T::AdjustmentVariableBlobSize::get();
+            let v_squared_2 = adjustment_variable.saturating_mul(adjustment_variable)
+                / Multiplier::saturating_from_integer(2);
+
+            let first_term = adjustment_variable.saturating_mul(diff);
+            let second_term = v_squared_2.saturating_mul(diff_squared);
+
+            if positive {
+                let excess = first_term
+                    .saturating_add(second_term)
+                    .saturating_mul(previous);
+                previous
+                    .saturating_add(excess)
+                    .clamp(min_multiplier, max_multiplier)
+            } else {
+                // Defensive-only: first_term > second_term. Safe subtraction.
+                let negative = first_term
+                    .saturating_sub(second_term)
+                    .saturating_mul(previous);
+                previous
+                    .saturating_sub(negative)
+                    .clamp(min_multiplier, max_multiplier)
+            }
+        }
+
+        /// The namespace IDs and data of the blobs submitted so far in the current block, in the
+        /// order of submission.
+        pub fn submitted_blobs() -> Vec<(u128, Vec<u8>)> {
+            BlobList::<T>::get()
+                .into_iter()
+                .zip(BlobData::<T>::get())
+                .map(|(blob, data)| (blob.namespace_id, data.into_inner()))
+                .collect()
+        }
+
+        /// Emit a digest item containing the root of the namespace merkle tree, tagged with the
+        /// version of its leaves.
         fn deposit_nmt_digest(root: ikura_nmt::TreeRoot) {
+            let digest = root.to_digest();
+            <frame_system::Pallet<T>>::deposit_log(sp_runtime::generic::DigestItem::Other(digest));
+        }
+
+        /// Emit a digest item containing the root of the erasure-coded data square.
+        fn deposit_data_square_digest(root: ikura_nmt::DataSquareRoot) {
             let bytes = root.to_raw_bytes();
             let mut digest = Vec::with_capacity(4 + bytes.len());
-            digest.extend_from_slice(b"snmt");
+            digest.extend_from_slice(b"sdsq");
             digest.extend_from_slice(&bytes);
             <frame_system::Pallet<T>>::deposit_log(sp_runtime::generic::DigestItem::Other(digest));
         }
@@ -131,29 +297,66 @@ pub mod pallet {
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
         fn on_initialize(_: BlockNumberFor<T>) -> Weight {
             // BlobList: 1r + 1w
+            // BlobData: 1w
             // TotalBlobSize: 1w
             // TotalBlobs: 1w
+            // NextBlobFeeMultiplier: 1r + 1w
             // deposit_log: 1r + 1w
-            T::DbWeight::get().reads_writes(2, 4)
+            //
+            // The computation of on_finalize without any blob, the rest is charged by submit_blob
+            T::DbWeight::get()
+                .reads_writes(3, 6)
+                .saturating_add(T::WeightInfo::on_finalize(0))
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MaxTotalBlobSize::get() as usize <= ikura_nmt::MAX_DATA_SIZE,
+                "MaxTotalBlobSize must fit in the data square"
+            );
         }
 
         fn on_finalize(_n: BlockNumberFor<T>) {
-            TotalBlobSize::<T>::kill();
+            let total_blob_size = TotalBlobSize::<T>::take();
+            NextBlobFeeMultiplier::<T>::mutate(|multiplier| {
+                *multiplier = Self::next_blob_fee_multiplier(*multiplier, total_blob_size);
+            });
+
             TotalBlobs::<T>::kill();
-            let blobs = BlobList::<T>::take()
+            let blob_list = BlobList::<T>::take();
+            let blob_data = BlobData::<T>::take();
+
+            let square = ikura_nmt::data_square_from_blobs(
+                blob_list
+                    .iter()
+                    .zip(&blob_data)
+                    .map(|(blob, data)| {
+                        let namespace = ikura_nmt::Namespace::from_u128_be(blob.namespace_id);
+                        (namespace, &data[..])
+                    })
+                    .collect(),
+            );
+
+            let blobs = blob_list
                 .iter()
-                .map(|blob| ikura_nmt::BlobMetadata {
+                .zip(&blob_data)
+                .map(|(blob, data)| ikura_nmt::BlobMetadata {
                     namespace: ikura_nmt::Namespace::from_u128_be(blob.namespace_id),
                     leaf: ikura_nmt::NmtLeaf {
                         extrinsic_index: blob.extrinsic_index,
                         who: blob.who.encode().try_into().unwrap(),
                         blob_hash: blob.blob_hash,
+                        shares: Some(ikura_nmt::ShareCommitment::new(data)),
                     },
                 })
                 .collect::<Vec<_>>();
 
-            let root = ikura_nmt::tree_from_blobs(blobs).root();
+            // The leaves commit to the shares of the blobs, so the digest is tagged `snm1` rather
+            // than the `snmt` of the former 68-byte leaves. Clients should accept both, like
+            // `TreeRoot::from_digest` does, to read the blocks from before the switch.
+            let root = ikura_nmt::tree_from_blobs(blobs, ikura_nmt::LeafVersion::V1).root();
             Self::deposit_nmt_digest(root);
+            Self::deposit_data_square_digest(square.root());
         }
     }
 
@@ -164,15 +367,24 @@ pub mod pallet {
         // to split the cost among everyone the amount of already present element
         // is set to half of the max possible elements
         //
-        // To the submit_blob weight is added a flat cost relative to the on_finalize execution
-        // the amount is equal to the entire weight of on_finalized divided by 1/4 of the MaxBlobs
-        // this covers perfectly the on_finalize cost if on average 1/4 of the possible blobs are submitted in one block
+        // To the submit_blob weight is added the cost of the blob in on_finalize, which hashes
+        // the blob into its share commitment and builds the data square over the bytes of all
+        // blobs. The cost of the data square grows faster than the total blob size, so its cost
+        // per byte at MaxTotalBlobSize is an upper bound for smaller squares.
+        //
+        // The account of the submitter, charged the blob fee, was already accessed to charge the
+        // transaction fee, so it is whitelisted in the benchmark.
         //
         // Note: this PANICS if the size of the blob, the total size of all blobs, or the total number of blobs submitted
         // exceed their respective configured limits. These panics are intended to be protected against by the [`crate::PrevalidateBlobs`] extension.
         #[pallet::weight(
             T::WeightInfo::submit_blob(T::MaxBlobs::get() / 2, blob.len() as u32)
-            .saturating_add(T::WeightInfo::on_finalize(0) / (T::MaxBlobs::get() / 4) as u64)
+            .saturating_add(Pallet::<T>::unbenchmarked_submit_blob_weight())
+            .saturating_add(
+                T::WeightInfo::on_finalize(1)
+                    .saturating_sub(T::WeightInfo::on_finalize(0))
+            )
+            .saturating_add(Pallet::<T>::unbenchmarked_blob_bytes_weight(blob.len() as u32))
         )]
         pub fn submit_blob(
             origin: OriginFor<T>,
@@ -195,6 +407,16 @@ pub mod pallet {
                 return Err(Error::<T>::NoExtrinsicIndex.into());
             };
 
+            let fee = Self::blob_fee(blob_len);
+            let imbalance = T::Currency::withdraw(
+                &who,
+                fee,
+                WithdrawReasons::FEE,
+                ExistenceRequirement::KeepAlive,
+            )
+            .map_err(|_| Error::<T>::InsufficientBalanceForBlobFee)?;
+            T::OnBlobFee::on_unbalanced(imbalance);
+
             let total_blobs = TotalBlobs::<T>::get();
             if total_blobs + 1 > T::MaxBlobs::get() {
                 panic!("Maximum blob limit exceeded");
@@ -216,8 +438,15 @@ pub mod pallet {
                 namespace_id,
                 blob_hash,
             });
+            if BlobData::<T>::try_append(BoundedVec::truncate_from(blob)).is_err() {
+                panic!("Maximum blob limit exceeded");
+            }
 
-            // Emit an event.
+            // Emit the events.
+            Self::deposit_event(Event::<T>::BlobFeeCharged {
+                who: who.clone(),
+                fee,
+            });
             Self::deposit_event(Event::<T>::BlobStored {
                 who,
                 extrinsic_index,
@@ -307,7 +536,7 @@ where
 
     fn validate(
         &self,
-        _who: &Self::AccountId,
+        who: &Self::AccountId,
         call: &Self::Call,
         _info: &DispatchInfoOf<Self::Call>,
         _len: usize,
@@ -338,6 +567,24 @@ where
                     )
                     .into());
                 }
+
+                // The submitter must be able to pay the blob fee on top of the transaction fee
+                // without being reaped. This could become valid later, if the balance of the
+                // submitter is increased or the blob fee multiplier drops.
+                let fee = Pallet::<T>::blob_fee(blob.len() as u32);
+                if T::Currency::free_balance(who)
+                    < fee.saturating_add(T::Currency::minimum_balance())
+                {
+                    return Err(InvalidTransaction::Payment.into());
+                }
+
+                // The blob fee is the same per byte for every blob of the block, so the priority
+                // is left to the transaction payment, which ranks the extrinsics by their tip over
+                // their length, the blob included.
+                return Ok(ValidTransaction {
+                    longevity: T::BlobLongevity::get(),
+                    ..Default::default()
+                });
             }
         }
         Ok(ValidTransaction::default())
@@ -1,9 +1,13 @@
 use crate as pallet_blobs;
-use frame_support::{parameter_types, traits::ConstU32};
-use sp_core::H256;
+use crate::Multiplier;
+use frame_support::{
+    parameter_types,
+    traits::{ConstU32, ConstU64},
+};
+use sp_core::{crypto::Pair, sr25519, H256};
 use sp_runtime::{
     traits::{BlakeTwo256, IdentifyAccount, IdentityLookup, Verify},
-    BuildStorage, MultiSignature,
+    BuildStorage, FixedPointNumber, MultiSignature, Perquintill,
 };
 
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -15,6 +19,7 @@ pub type AccountId = <<MultiSignature as Verify>::Signer as IdentifyAccount>::Ac
 frame_support::construct_runtime!(
     pub enum Test {
         System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
+        Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
         Blobs: crate::{Pallet, Call, Storage, Event<T>},
     }
 );
@@ -42,7 +47,7 @@ impl frame_system::Config for Test {
     type BlockHashCount = BlockHashCount;
     type Version = ();
     type PalletInfo = PalletInfo;
-    type AccountData = ();
+    type AccountData = pallet_balances::AccountData<u64>;
     type OnNewAccount = ();
     type OnKilledAccount = ();
     type SystemWeightInfo = ();
@@ -51,20 +56,67 @@ impl frame_system::Config for Test {
     type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+impl pallet_balances::Config for Test {
+    type Balance = u64;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type WeightInfo = ();
+    type FreezeIdentifier = ();
+    type MaxFreezes = ();
+    type RuntimeHoldReason = ();
+    type RuntimeFreezeReason = ();
+    type MaxHolds = ();
+}
+
+parameter_types! {
+    pub TargetTotalBlobSize: Perquintill = Perquintill::from_percent(50);
+    pub AdjustmentVariableBlobSize: Multiplier = Multiplier::saturating_from_rational(1, 10);
+    pub MinimumMultiplierBlobSize: Multiplier = Multiplier::saturating_from_rational(1, 10);
+    pub MaximumMultiplierBlobSize: Multiplier = Multiplier::saturating_from_integer(10);
+}
+
 impl pallet_blobs::Config for Test {
     type RuntimeEvent = RuntimeEvent;
     type MaxBlobs = ConstU32<16>;
     type MaxBlobSize = ConstU32<1024>;
     type MaxTotalBlobSize = ConstU32<{ 10 * 1024 }>;
+    type Currency = Balances;
+    type OnBlobFee = ();
+    type BlobByteFee = ConstU64<1>;
+    type TargetTotalBlobSize = TargetTotalBlobSize;
+    type AdjustmentVariableBlobSize = AdjustmentVariableBlobSize;
+    type MinimumMultiplierBlobSize = MinimumMultiplierBlobSize;
+    type MaximumMultiplierBlobSize = MaximumMultiplierBlobSize;
+    type BlobLongevity = ConstU64<64>;
     type WeightInfo = ();
 }
 
+/// The balance of Alice at genesis.
+pub const ALICE_BALANCE: u64 = 1_000_000_000;
+
+pub fn alice() -> AccountId {
+    sr25519::Pair::from_string("//Alice", None)
+        .expect("Impossible generate Alice AccountId")
+        .public()
+        .into()
+}
+
 // Build genesis storage according to the mock runtime.
 // TODO: https://github.com/thrumdev/blobs/issues/28
 #[allow(unused)]
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    frame_system::GenesisConfig::<Test>::default()
+    let mut storage = frame_system::GenesisConfig::<Test>::default()
         .build_storage()
-        .unwrap()
-        .into()
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(alice(), ALICE_BALANCE)],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    storage.into()
 }
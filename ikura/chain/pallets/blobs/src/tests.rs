@@ -2,13 +2,13 @@ use crate as pallet_blobs;
 use crate::{mock::*, *};
 use frame_support::traits::Hooks;
 use frame_support::{assert_noop, assert_ok, traits::Get};
-use ikura_nmt::{Namespace, NmtLeaf};
+use ikura_nmt::{LeafVersion, Namespace, NmtLeaf, ShareCommitment};
 use parity_scale_codec::Encode;
 use sha2::Digest;
 use sp_core::storage::well_known_keys;
-use sp_core::{crypto::Pair, sr25519};
-use sp_runtime::transaction_validity::{
-    InvalidTransaction, TransactionValidityError, ValidTransaction,
+use sp_runtime::{
+    transaction_validity::{InvalidTransaction, TransactionValidityError, ValidTransaction},
+    FixedPointNumber,
 };
 use sp_state_machine::backend::Backend;
 use sp_state_machine::LayoutV1;
@@ -18,11 +18,13 @@ fn get_blob(size: u32) -> Vec<u8> {
     vec![12u8].repeat(size as usize)
 }
 
-fn alice() -> <Test as frame_system::Config>::AccountId {
-    sr25519::Pair::from_string("//Alice", None)
-        .expect("Impossible generate Alice AccountId")
-        .public()
-        .into()
+fn expect_data_square_digest(log: Option<sp_runtime::DigestItem>) -> ikura_nmt::DataSquareRoot {
+    match log {
+        Some(sp_runtime::DigestItem::Other(bytes)) if bytes.starts_with(b"sdsq") => {
+            ikura_nmt::DataSquareRoot::from_raw_bytes(&bytes[4..].try_into().unwrap())
+        }
+        _ => panic!("The data square root should follow the nmt root in the Digest"),
+    }
 }
 
 #[test]
@@ -50,6 +52,33 @@ fn test_correct_submitted_blob() {
     });
 }
 
+#[test]
+fn test_submitted_blobs() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Blobs::submit_blob(
+            RuntimeOrigin::signed(alice()),
+            2.into(),
+            get_blob(10)
+        ));
+        // A failed submission stores no blob.
+        NextBlobFeeMultiplier::<Test>::put(Multiplier::saturating_from_integer(ALICE_BALANCE));
+        assert!(
+            Blobs::submit_blob(RuntimeOrigin::signed(alice()), 3.into(), get_blob(30)).is_err()
+        );
+        NextBlobFeeMultiplier::<Test>::kill();
+        assert_ok!(Blobs::submit_blob(
+            RuntimeOrigin::signed(alice()),
+            1.into(),
+            get_blob(20)
+        ));
+
+        assert_eq!(
+            Blobs::submitted_blobs(),
+            vec![(2, get_blob(10)), (1, get_blob(20))]
+        );
+    });
+}
+
 #[test]
 fn test_no_extrinsic_index() {
     new_test_ext().execute_with(|| {
@@ -153,7 +182,7 @@ fn test_namespace_order() {
         let blob = get_blob(blob_len);
         let blob_hash: [u8; 32] = sha2::Sha256::digest(blob.clone()).into();
 
-        let mut tree = ikura_nmt::TreeBuilder::new();
+        let mut tree = ikura_nmt::TreeBuilder::with_leaf_version(LeafVersion::V1);
         let mut blobs_metadata = vec![];
 
         let mut push_leaf = |namespace_id, extrinsic_index| {
@@ -163,6 +192,7 @@ fn test_namespace_order() {
                     extrinsic_index,
                     who: alice().into(),
                     blob_hash: blob_hash.clone(),
+                    shares: Some(ShareCommitment::new(&blob)),
                 },
             )
             .expect("Impossible push leaf into nmt-tree");
@@ -201,11 +231,15 @@ fn test_namespace_order() {
 
         let mut logs = System::digest().logs.into_iter();
         match logs.next() {
-            Some(sp_runtime::DigestItem::Other(bytes)) if bytes.starts_with(b"snmt") => {
+            Some(sp_runtime::DigestItem::Other(bytes)) if bytes.starts_with(b"snm1") => {
                 assert_eq!(bytes[4..], tree.root().to_raw_bytes());
             }
             _ => panic!("One DigestItem::Other should be contained in the Digest"),
         }
+        assert_eq!(
+            expect_data_square_digest(logs.next()),
+            ikura_nmt::DataSquare::new(vec![&blob[..]; 4]).root()
+        );
         // No other logs are expected
         assert_eq!(None, logs.next());
     });
@@ -243,13 +277,78 @@ fn test_deposited_event() {
     });
 }
 
+#[test]
+fn test_blob_fee_charged() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        NextBlobFeeMultiplier::<Test>::put(Multiplier::saturating_from_integer(3));
+
+        let blob_len = 100;
+        assert_ok!(Blobs::submit_blob(
+            RuntimeOrigin::signed(alice()),
+            0.into(),
+            get_blob(blob_len)
+        ));
+
+        // BlobByteFee is 1
+        let fee = 3 * blob_len as u64;
+        assert_eq!(Balances::free_balance(alice()), ALICE_BALANCE - fee);
+        System::assert_has_event(Event::<Test>::BlobFeeCharged { who: alice(), fee }.into());
+    });
+}
+
+#[test]
+fn test_insufficient_balance_for_blob_fee() {
+    new_test_ext().execute_with(|| {
+        NextBlobFeeMultiplier::<Test>::put(Multiplier::saturating_from_integer(ALICE_BALANCE));
+
+        assert_noop!(
+            Blobs::submit_blob(RuntimeOrigin::signed(alice()), 0.into(), get_blob(1)),
+            Error::<Test>::InsufficientBalanceForBlobFee
+        );
+    });
+}
+
+#[test]
+fn test_blob_fee_multiplier_update() {
+    let max_total_blob_size: u32 = <Test as pallet_blobs::Config>::MaxTotalBlobSize::get();
+    let max_blob_size: u32 = <Test as pallet_blobs::Config>::MaxBlobSize::get();
+    let target = <Test as pallet_blobs::Config>::TargetTotalBlobSize::get();
+    let target_blobs = (target * max_total_blob_size) / max_blob_size;
+
+    let multiplier_after = |n_blobs: u32| {
+        new_test_ext().execute_with(|| {
+            submit_blobs!([blob_size] max_blob_size, [blobs_number] n_blobs);
+            Blobs::on_finalize(System::block_number());
+            NextBlobFeeMultiplier::<Test>::get()
+        })
+    };
+
+    let one = Multiplier::saturating_from_integer(1);
+    assert_eq!(multiplier_after(target_blobs), one);
+    assert!(multiplier_after(target_blobs + 1) > one);
+    assert!(multiplier_after(target_blobs - 1) < one);
+
+    // The multiplier is kept within the bounds.
+    let min_multiplier = <Test as pallet_blobs::Config>::MinimumMultiplierBlobSize::get();
+    let max_multiplier = <Test as pallet_blobs::Config>::MaximumMultiplierBlobSize::get();
+    assert_eq!(
+        Blobs::next_blob_fee_multiplier(min_multiplier, 0),
+        min_multiplier
+    );
+    assert_eq!(
+        Blobs::next_blob_fee_multiplier(max_multiplier, max_total_blob_size),
+        max_multiplier
+    );
+}
+
 #[test]
 fn test_on_finalize() {
     use ikura_nmt::TreeBuilder;
     use sha2::Digest;
 
     let max_blobs: u32 = <Test as pallet_blobs::Config>::MaxBlobs::get();
-    let mut tree = TreeBuilder::new();
+    let mut tree = TreeBuilder::with_leaf_version(LeafVersion::V1);
     let blob = get_blob(1);
     // Counter to avoid recreating the tree from scratch everytime the loop restarts
     let mut added_leaf = 0;
@@ -263,6 +362,7 @@ fn test_on_finalize() {
                     extrinsic_index,
                     who: alice().into(),
                     blob_hash: sha2::Sha256::digest(blob.clone()).into(),
+                    shares: Some(ShareCommitment::new(&blob)),
                 },
             )
             .expect("Impossible push leaf into nmt-tree");
@@ -285,17 +385,95 @@ fn test_on_finalize() {
 
             let mut logs = System::digest().logs.into_iter();
             match logs.next() {
-                Some(sp_runtime::DigestItem::Other(bytes)) if bytes.starts_with(b"snmt") => {
+                Some(sp_runtime::DigestItem::Other(bytes)) if bytes.starts_with(b"snm1") => {
                     assert_eq!(bytes[4..], tree.root().to_raw_bytes());
                 }
                 _ => panic!("One DigestItem::Other should be contained in the Digest"),
             }
+            assert_eq!(
+                expect_data_square_digest(logs.next()),
+                ikura_nmt::DataSquare::new(vec![&blob[..]; n_blob_to_test as usize]).root()
+            );
             // No other logs are expected
             assert_eq!(None, logs.next());
         });
     }
 }
 
+#[test]
+fn test_data_square_digest() {
+    new_test_ext().execute_with(|| {
+        let blobs = [(3u128, get_blob(700)), (1, vec![1u8; 1000]), (2, vec![])];
+        for (extrinsic_index, (namespace_id, blob)) in blobs.iter().enumerate() {
+            sp_io::storage::set(b":extrinsic_index", &(extrinsic_index as u32).encode());
+            assert_ok!(Blobs::submit_blob(
+                RuntimeOrigin::signed(alice()),
+                (*namespace_id).into(),
+                blob.clone()
+            ));
+        }
+
+        Blobs::on_finalize(System::block_number());
+
+        let root = expect_data_square_digest(System::digest().logs.into_iter().nth(1));
+        let square = ikura_nmt::data_square_from_blobs(
+            blobs
+                .iter()
+                .map(|(namespace_id, blob)| (Namespace::from_u128_be(*namespace_id), &blob[..]))
+                .collect(),
+        );
+        assert_eq!(root, square.root());
+        // 1700 bytes of blob data take 4 shares, hence a 2 * 2 original square.
+        assert_eq!(root.original_width, 2);
+        assert!(square.sample(3, 2).unwrap().verify(&root).is_ok());
+    });
+}
+
+#[test]
+fn test_nmt_leaves_commit_to_shares() {
+    new_test_ext().execute_with(|| {
+        let blob: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        assert_ok!(Blobs::submit_blob(
+            RuntimeOrigin::signed(alice()),
+            1.into(),
+            blob.clone()
+        ));
+
+        Blobs::on_finalize(System::block_number());
+
+        let root = match System::digest().logs.into_iter().next() {
+            Some(sp_runtime::DigestItem::Other(bytes)) => {
+                ikura_nmt::TreeRoot::from_digest(&bytes).expect("the nmt root is deposited first")
+            }
+            _ => panic!("One DigestItem::Other should be contained in the Digest"),
+        };
+        assert_eq!(root.leaf_version, LeafVersion::V1);
+
+        // Rebuild the tree to prove the namespace, then a part of the blob against its leaf.
+        let mut tree = ikura_nmt::TreeBuilder::with_leaf_version(LeafVersion::V1);
+        tree.push_leaf(
+            Namespace::from_u128_be(1),
+            NmtLeaf {
+                extrinsic_index: 0,
+                who: alice().into(),
+                blob_hash: sha2::Sha256::digest(&blob).into(),
+                shares: Some(ShareCommitment::new(&blob)),
+            },
+        )
+        .unwrap();
+        let leaves = tree
+            .proof(Namespace::from_u128_be(1))
+            .verify_leaves(&root, Namespace::from_u128_be(1))
+            .unwrap();
+        let commitment = leaves[0].shares.clone().unwrap();
+
+        let proof = ikura_nmt::ShareRangeProof::new(&blob, 1..2).unwrap();
+        assert!(proof
+            .verify(&blob[commitment.byte_range(1..2)], &commitment)
+            .is_ok());
+    });
+}
+
 macro_rules! submit_blob_call {
     ([blob_size] $blob_size: expr) => {
         RuntimeCall::Blobs(
@@ -312,11 +490,16 @@ macro_rules! submit_blob_call {
 fn test_validate_ok() {
     let prevalidate_blobs = PrevalidateBlobs::<Test>::new();
 
-    let call = submit_blob_call!([blob_size] 1);
-    assert_eq!(
-        Ok(ValidTransaction::default()),
-        prevalidate_blobs.validate(&alice(), &call, &Default::default(), 0)
-    );
+    new_test_ext().execute_with(|| {
+        let call = submit_blob_call!([blob_size] 1);
+        assert_eq!(
+            Ok(ValidTransaction {
+                longevity: 64,
+                ..Default::default()
+            }),
+            prevalidate_blobs.validate(&alice(), &call, &Default::default(), 0)
+        );
+    });
 }
 
 #[test]
@@ -334,6 +517,30 @@ fn test_validate_exceeded() {
     );
 }
 
+#[test]
+fn test_validate_insufficient_balance_for_blob_fee() {
+    let prevalidate_blobs = PrevalidateBlobs::<Test>::new();
+
+    new_test_ext().execute_with(|| {
+        // The blob fee of `blob_size` bytes would spend the whole balance, reaping the account.
+        let blob_size = 1000;
+        NextBlobFeeMultiplier::<Test>::put(Multiplier::saturating_from_integer(
+            ALICE_BALANCE / blob_size as u64,
+        ));
+
+        let call = submit_blob_call!([blob_size] blob_size);
+        assert_eq!(
+            Err(InvalidTransaction::Payment.into()),
+            prevalidate_blobs.validate(&alice(), &call, &Default::default(), 0)
+        );
+
+        let call = submit_blob_call!([blob_size] blob_size - 1);
+        assert!(prevalidate_blobs
+            .validate(&alice(), &call, &Default::default(), 0)
+            .is_ok());
+    });
+}
+
 #[test]
 fn test_pre_dispatch_ok() {
     let prevalidate_blobs = PrevalidateBlobs::<Test>::new();
@@ -405,6 +612,7 @@ fn test_no_commitment_to_storage_after_finalization() {
     assert_present_key(&TotalBlobSize::<Test>::hashed_key());
     assert_present_key(&TotalBlobs::<Test>::hashed_key());
     assert_present_key(&BlobList::<Test>::hashed_key());
+    assert_present_key(&BlobData::<Test>::hashed_key());
 
     // Execute on_finalize and commit changes
     ext.execute_with(|| {
@@ -421,6 +629,7 @@ fn test_no_commitment_to_storage_after_finalization() {
     assert_non_present_key(&TotalBlobSize::<Test>::hashed_key());
     assert_non_present_key(&TotalBlobs::<Test>::hashed_key());
     assert_non_present_key(&BlobList::<Test>::hashed_key());
+    assert_non_present_key(&BlobData::<Test>::hashed_key());
 }
 
 #[test]
@@ -475,4 +684,5 @@ fn test_storage_proof_does_not_contain_ephemeral_keys() {
     assert_non_present_key(&TotalBlobSize::<Test>::hashed_key());
     assert_non_present_key(&TotalBlobs::<Test>::hashed_key());
     assert_non_present_key(&BlobList::<Test>::hashed_key());
+    assert_non_present_key(&BlobData::<Test>::hashed_key());
 }
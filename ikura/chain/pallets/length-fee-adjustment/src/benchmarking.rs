@@ -0,0 +1,61 @@
+//! Benchmarking setup for pallet-ikura-length-fee-adjustment
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as LengthFeeAdjustment;
+#[allow(unused)]
+use frame_benchmarking::v2::{benchmarks, impl_benchmark_test_suite, BenchmarkError};
+use frame_support::traits::EnsureOrigin;
+use pallet_transaction_payment::Multiplier;
+use sp_runtime::{FixedPointNumber, Perquintill};
+
+// Command to run the benchmarks:
+// ./target/release/ikura-node benchmark pallet \
+// --dev \
+// --pallet pallet_ikura_length_fee_adjustment \
+// --extrinsic '*' \
+// --steps 20 \
+// --repeat 20 \
+// --template <path_to_weight_template_file>.hbs \
+// --output ikura-chain/pallets/length-fee-adjustment/src/weights.rs
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn set_target_block_size() -> Result<(), BenchmarkError> {
+        let origin =
+            T::UpdateOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let target_block_size = Perquintill::from_percent(25);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, target_block_size);
+
+        assert_eq!(TargetBlockSize::<T>::get(), target_block_size);
+        Ok(())
+    }
+
+    #[benchmark]
+    fn set_adjustment_params() -> Result<(), BenchmarkError> {
+        let origin =
+            T::UpdateOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let params = AdjustmentParameters {
+            adjustment_variable: Multiplier::saturating_from_rational(1, 420),
+            min_multiplier: Multiplier::saturating_from_rational(1, 100),
+            max_multiplier: Multiplier::saturating_from_integer(100),
+        };
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, params);
+
+        assert_eq!(AdjustmentParams::<T>::get(), params);
+        Ok(())
+    }
+
+    impl_benchmark_test_suite!(
+        LengthFeeAdjustment,
+        crate::mock::new_test_ext(),
+        crate::mock::Test
+    );
+}
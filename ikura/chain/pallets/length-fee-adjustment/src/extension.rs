@@ -0,0 +1,160 @@
+//! The signed extension charging the transaction fees and burning the base fee.
+
+use crate::{Config, ExcludedBlockLength, ExcludedLength, Pallet};
+use frame_support::{
+    dispatch::{DispatchInfo, PostDispatchInfo},
+    pallet_prelude::Pays,
+    traits::{Currency, ExistenceRequirement, Imbalance, WithdrawReasons},
+};
+use pallet_transaction_payment::BalanceOf;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{
+    traits::{DispatchInfoOf, Dispatchable, PostDispatchInfoOf, SignedExtension, Zero},
+    transaction_validity::{InvalidTransaction, TransactionValidity, TransactionValidityError},
+    DispatchResult, FixedPointOperand,
+};
+
+type InnerExtension<T> = pallet_transaction_payment::ChargeTransactionPayment<T>;
+type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as frame_system::Config>::AccountId,
+>>::NegativeImbalance;
+
+/// Charges the transaction fees like `pallet_transaction_payment::ChargeTransactionPayment`,
+/// except for the length fee, which is the base fee of [`Pallet::base_fee`].
+///
+/// The base fee is withdrawn separately and burned, while the weight fee and the tip are charged
+/// by the `OnChargeTransaction` of `pallet_transaction_payment`. The bytes given by
+/// `Config::ExcludedLength`, e.g. the blob data, are not charged the base fee.
+///
+/// It has the same encoding and identifier as the extension of `pallet_transaction_payment`, so
+/// it replaces it in the `SignedExtra` of the runtime without any change for the clients.
+///
+/// The priority in the transaction pool is still derived from the full length of the extrinsic,
+/// so that the extrinsics compete for the block space by their tip per byte.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct ChargeTransactionPayment<T: Config>(#[codec(compact)] BalanceOf<T>);
+
+impl<T: Config> ChargeTransactionPayment<T> {
+    /// Utility constructor. Used only in client/factory code.
+    pub fn from(tip: BalanceOf<T>) -> Self {
+        Self(tip)
+    }
+
+    fn inner(&self) -> InnerExtension<T> {
+        InnerExtension::<T>::from(self.0)
+    }
+}
+
+impl<T: Config> core::fmt::Debug for ChargeTransactionPayment<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "ChargeTransactionPayment<{:?}>", self.0)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut core::fmt::Formatter) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config> SignedExtension for ChargeTransactionPayment<T>
+where
+    BalanceOf<T>: Send + Sync + From<u64> + FixedPointOperand,
+    <T as frame_system::Config>::RuntimeCall:
+        Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+{
+    const IDENTIFIER: &'static str = "ChargeTransactionPayment";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Config>::RuntimeCall;
+    type AdditionalSigned = ();
+    /// The pre-dispatch data of the inner extension, the payer and the withdrawn base fee.
+    type Pre = (
+        <InnerExtension<T> as SignedExtension>::Pre,
+        Self::AccountId,
+        NegativeImbalanceOf<T>,
+    );
+
+    fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        len: usize,
+    ) -> TransactionValidity {
+        let fee_len = Pallet::<T>::fee_length(call, len as u32);
+        // The length fee is the base fee, charged below.
+        let mut valid = self.inner().validate(who, call, info, 0)?;
+        let _ = withdraw_base_fee::<T>(who, info, fee_len)?;
+
+        let fee = pallet_transaction_payment::Pallet::<T>::compute_fee(fee_len, info, self.0);
+        valid.priority = InnerExtension::<T>::get_priority(info, len, self.0, fee);
+        Ok(valid)
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        let excluded_len = T::ExcludedLength::excluded_length(call).min(len as u32);
+        let fee_len = len as u32 - excluded_len;
+        let pre = self.inner().pre_dispatch(who, call, info, 0)?;
+        let base_fee = withdraw_base_fee::<T>(who, info, fee_len)?;
+
+        ExcludedBlockLength::<T>::mutate(|block_len| {
+            *block_len = block_len.saturating_add(excluded_len)
+        });
+        Ok((pre, who.clone(), base_fee))
+    }
+
+    fn post_dispatch(
+        maybe_pre: Option<Self::Pre>,
+        info: &DispatchInfoOf<Self::Call>,
+        post_info: &PostDispatchInfoOf<Self::Call>,
+        _len: usize,
+        result: &DispatchResult,
+    ) -> Result<(), TransactionValidityError> {
+        let Some((pre, who, base_fee)) = maybe_pre else {
+            return Ok(());
+        };
+        InnerExtension::<T>::post_dispatch(Some(pre), info, post_info, 0, result)?;
+
+        if post_info.pays_fee(info) == Pays::No {
+            // Refund the base fee, like the rest of the fee.
+            if !base_fee.peek().is_zero() {
+                let _ = T::Currency::resolve_into_existing(&who, base_fee);
+            }
+        } else {
+            // Dropping the imbalance burns it.
+            Pallet::<T>::note_burned_base_fee(base_fee.peek());
+        }
+        Ok(())
+    }
+}
+
+/// Withdraws the base fee of an extrinsic whose length charged the length fee is `fee_len`.
+fn withdraw_base_fee<T: Config>(
+    who: &T::AccountId,
+    info: &DispatchInfo,
+    fee_len: u32,
+) -> Result<NegativeImbalanceOf<T>, TransactionValidityError> {
+    let base_fee = Pallet::<T>::base_fee(fee_len);
+    if info.pays_fee == Pays::No || base_fee.is_zero() {
+        return Ok(NegativeImbalanceOf::<T>::zero());
+    }
+    T::Currency::withdraw(
+        who,
+        base_fee,
+        WithdrawReasons::TRANSACTION_PAYMENT,
+        ExistenceRequirement::KeepAlive,
+    )
+    .map_err(|_| InvalidTransaction::Payment.into())
+}
@@ -7,6 +7,13 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod extension;
+pub mod weights;
+
+pub use extension::ChargeTransactionPayment;
+
 /// Currently, the `pallet_transaction_payment` uses the following formula:
 ///
 /// ```ignore
@@ -39,21 +46,42 @@ mod tests;
 ///
 /// `targeted_length_fee_adjustment` is updated at the end of each block inside `on_finalize`
 ///
-/// The pallet also implements `cumulus_pallet_parachain_system::OnSystemEvent` which is used to update `NextLenghtMultiplier`
-/// when blocks are skipped, the implementation follows the following formula to update the multiplier:
+/// When blocks are skipped, `NextLenghtMultiplier` is updated following the formula:
 ///
 /// ```ignore
-/// c_traffic = c_traffic * e^(-target*v*n)
+/// c_traffic = max(c_traffic * e^(-v*target*n), min_multiplier)
 /// ```
 ///
-/// where the exponential is evaluated with the first SkippedBlocksNumberTerms terms, specified in the pallet Config, of the Taylor Series expansion of e^x.
+/// where n is the number of skipped blocks. The exponential is computed in fixed point, so it is
+/// exact up to the fixed-point rounding for any number of skipped blocks.
+///
+/// The skipped blocks are accounted for as soon as the relay-parent of the current block is known,
+/// through the implementation of `cumulus_pallet_parachain_system::OnSystemEvent`. Runtimes which
+/// don't wire it still get them accounted for in `on_initialize`, one block late, using the
+/// relay-parent of the previous block. `LastSkippedBlocksUpdate` records the relay-parent up to
+/// which the skipped blocks were accounted for, so that no gap is ever accounted for twice.
+///
+/// The adjusted length fee is the base fee of an extrinsic. With [`ChargeTransactionPayment`] in
+/// place of the one of `pallet_transaction_payment`, the base fee is burned, EIP-1559 style, while
+/// the rest of the fee and the tip are handled by the `OnChargeTransaction` of
+/// `pallet_transaction_payment`, e.g. paid to the collator. Since the base fee of the next block is
+/// known in advance, see [`Pallet::next_base_fee`], the submitters can bid precisely.
+///
+/// The bytes of an extrinsic that are priced separately, e.g. the blob data, are given by
+/// `Config::ExcludedLength`. They are not charged the length fee by [`ChargeTransactionPayment`],
+/// and they do not count towards the block size when `NextLengthMultiplier` is updated.
+///
+/// `TargetBlockSize` and the adjustment parameters are kept in storage and can be updated by the
+/// `UpdateOrigin`, so that the fees can be tuned without a runtime upgrade. The adjustment
+/// parameters default to the constants specified in the pallet Config.
 #[frame_support::pallet]
 pub mod pallet {
 
+    pub use crate::weights::WeightInfo;
     use cumulus_pallet_parachain_system::OnSystemEvent;
-    use frame_support::pallet_prelude::*;
+    use frame_support::{pallet_prelude::*, traits::Currency};
     use frame_system::pallet_prelude::*;
-    use pallet_transaction_payment::{Multiplier, OnChargeTransaction};
+    use pallet_transaction_payment::{BalanceOf, Multiplier, OnChargeTransaction};
     use polkadot_primitives::v6::{BlockNumber as RelayChainBlockNumber, PersistedValidationData};
     use sp_runtime::{
         traits::{Get, One, Zero},
@@ -73,45 +101,76 @@ pub mod pallet {
         }
     }
 
+    /// The number of bytes of an extrinsic that are priced separately, e.g. the blob data, and
+    /// are thus not charged the length fee.
+    pub trait ExcludedLength<Call> {
+        /// Get the number of bytes of the extrinsic with the given call that are excluded.
+        fn excluded_length(call: &Call) -> u32;
+    }
+
+    impl<Call> ExcludedLength<Call> for () {
+        fn excluded_length(_: &Call) -> u32 {
+            0
+        }
+    }
+
+    /// The parameters of the `targeted_length_fee_adjustment` update.
+    #[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    pub struct AdjustmentParameters {
+        /// Higher values make the multiplier change more rapidly. Must be in (0, 1].
+        pub adjustment_variable: Multiplier,
+        /// The minimum value of the multiplier. Must be positive, so that the multiplier can grow
+        /// back.
+        pub min_multiplier: Multiplier,
+        /// The maximum value of the multiplier. Must not be less than `min_multiplier`.
+        pub max_multiplier: Multiplier,
+    }
+
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
     pub trait Config: frame_system::Config + pallet_transaction_payment::Config {
+        /// Because this pallet emits events, it depends on the runtime's definition of an event.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// The origin allowed to update `TargetBlockSize` and the adjustment parameters.
+        type UpdateOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// The currency the base fee is burned in, the same as the one of the transaction fees.
+        type Currency: Currency<Self::AccountId, Balance = BalanceOf<Self>>;
+
+        // The weight information of this pallet.
+        type WeightInfo: WeightInfo;
+
         // `targeted_length_fee_adjustment` parameters
         #[pallet::constant]
         type TransactionByteFee: Get<<<Self as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<Self>>::Balance>;
         #[pallet::constant]
         type MaximumBlockLength: Get<u32>;
+        /// The default `adjustment_variable` of the `AdjustmentParams`.
         #[pallet::constant]
         type AdjustmentVariableBlockSize: Get<Multiplier>;
+        /// The default `min_multiplier` of the `AdjustmentParams`.
         #[pallet::constant]
         type MinimumMultiplierBlockSize: Get<Multiplier>;
+        /// The default `max_multiplier` of the `AdjustmentParams`.
         #[pallet::constant]
         type MaximumMultiplierBlockSize: Get<Multiplier>;
 
-        /// SkippedBlocksNumberTerms indicates the number of terms used in evaluating e^x in the Taylor expansion.
-        /// The number of terms ensures that, given the following parameters, the error will be below a certain value.
-        ///
-        /// t = TargetBlockSize
-        /// v = AdjustmentVariableBlockSize
-        /// n = maximum number of skipped blocks
-        ///
-        /// (1 / (m+1)!) * (n * v * t)^(m + 1) <= err
-        ///
-        /// Here, m represents the number of terms needed to be below the error.
-        /// The smallest value of m that satisfies the inequality should be used as the value for this parameter.
-        #[pallet::constant]
-        type SkippedBlocksNumberTerms: Get<u32>;
+        /// A source to provide the relay-parent number of the previous block.
+        type LastRelayBlockNumberProvider: LastRelayBlockNumberProvider;
 
-        /// The maximum number of skipped blocks is used to ensure that only the right amount of terms is used
-        /// and no terms are wasted. If n is exceeded, the approximation could diverge significantly from the actual value of e^x.
-        /// If the number of skipped blocks exceeds this value, it will be bounded to this value.
-        /// However, this limitation comes at the cost of losing the real fee adjustment update.
-        /// Therefore, producing a block at most every n skipped blocks should be enforced to avoid falling into this circumstance.
+        /// The expected number of relay chain blocks between the relay-parents of two
+        /// consecutive parachain blocks, i.e. the parachain block time over the relay chain one:
+        /// 2 with 12 seconds blocks, 1 with 6 seconds blocks.
+        /// Any additional distance is accounted for as skipped parachain blocks.
         #[pallet::constant]
-        type MaximumSkippedBlocks: Get<u32>;
+        type RelayBlocksPerParachainBlock: Get<u32>;
 
-        /// A source to provide the relay-parent number of the previous block.
-        type LastRelayBlockNumberProvider: LastRelayBlockNumberProvider;
+        /// The number of bytes of an extrinsic that are priced separately, e.g. the blob data.
+        ///
+        /// They are not charged the length fee by [`crate::ChargeTransactionPayment`], and do not
+        /// count towards the block size when `NextLengthMultiplier` is updated.
+        type ExcludedLength: ExcludedLength<<Self as frame_system::Config>::RuntimeCall>;
     }
 
     #[pallet::pallet]
@@ -139,6 +198,60 @@ pub mod pallet {
     pub type TargetBlockSize<T: Config> =
         StorageValue<_, Perquintill, ValueQuery, TargetBlockSizeDefault>;
 
+    pub struct AdjustmentParamsDefault<T>(PhantomData<T>);
+    impl<T: Config> Get<AdjustmentParameters> for AdjustmentParamsDefault<T> {
+        fn get() -> AdjustmentParameters {
+            AdjustmentParameters {
+                adjustment_variable: T::AdjustmentVariableBlockSize::get(),
+                min_multiplier: T::MinimumMultiplierBlockSize::get(),
+                max_multiplier: T::MaximumMultiplierBlockSize::get(),
+            }
+        }
+    }
+
+    #[pallet::storage]
+    pub type AdjustmentParams<T: Config> =
+        StorageValue<_, AdjustmentParameters, ValueQuery, AdjustmentParamsDefault<T>>;
+
+    /// The relay-parent number up to which the skipped blocks were accounted for in
+    /// `NextLengthMultiplier`.
+    #[pallet::storage]
+    pub type LastSkippedBlocksUpdate<T: Config> =
+        StorageValue<_, RelayChainBlockNumber, OptionQuery>;
+
+    /// The number of bytes of the extrinsics of the current block that were not charged the
+    /// length fee, see `Config::ExcludedLength`. Cleared in `on_finalize`.
+    #[pallet::storage]
+    #[pallet::whitelist_storage]
+    pub type ExcludedBlockLength<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// The base fees burned in the current block. Cleared in `on_finalize`, where they are reported
+    /// by a single `BaseFeeBurned` event.
+    #[pallet::storage]
+    #[pallet::whitelist_storage]
+    pub type BurnedBaseFee<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// `TargetBlockSize` was updated.
+        TargetBlockSizeSet { target_block_size: Perquintill },
+        /// The adjustment parameters were updated.
+        AdjustmentParamsSet { params: AdjustmentParameters },
+        /// The base fees of the extrinsics of the block were burned.
+        BaseFeeBurned { amount: BalanceOf<T> },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The target block size must be neither 0% nor 100%.
+        InvalidTargetBlockSize,
+        /// The adjustment variable must be in (0, 1].
+        InvalidAdjustmentVariable,
+        /// The minimum multiplier must be positive and not greater than the maximum one.
+        InvalidMultiplierBounds,
+    }
+
     /// Genesis config for setting up `NextLengthMultiplier` and `TargetBlockSize` storage values.
     #[pallet::genesis_config]
     #[derive(frame_support::DefaultNoBound)]
@@ -162,15 +275,24 @@ pub mod pallet {
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
         fn on_initialize(_: BlockNumberFor<T>) -> Weight {
-            // TODO: implement skip block logic
-            // https://github.com/thrumdev/blobs/issues/165
-
-            // NextLengthMultiplier: 1r + 1w
-            // TargetBlockSize: 1r
-            T::DbWeight::get().reads_writes(2, 1)
+            // The relay-parent of the current block is not known yet, but the one of the previous
+            // block is. If `on_validation_data` was called in the previous block, this is a no-op.
+            Self::account_skipped_blocks(T::LastRelayBlockNumberProvider::last_relay_block_number());
+
+            // LastRelayBlockNumberProvider: 1r
+            // LastSkippedBlocksUpdate: 1r + 1w
+            // NextLengthMultiplier: 2r + 2w
+            // TargetBlockSize: 2r
+            // AdjustmentParams: 2r
+            T::DbWeight::get().reads_writes(8, 3)
         }
 
         fn on_finalize(_n: BlockNumberFor<T>) {
+            let burned = BurnedBaseFee::<T>::take();
+            if !burned.is_zero() {
+                Self::deposit_event(Event::BaseFeeBurned { amount: burned });
+            }
+
             // update targeted_weight_fee_adjustment,
             // contained in NextLengthMultiplier storage item
 
@@ -184,18 +306,22 @@ pub mod pallet {
             // we recover here in case of errors, because any value below this would be stale and can
             // never change.
             let previous_len_multiplier = NextLengthMultiplier::<T>::get();
-            let min_multiplier = T::MinimumMultiplierBlockSize::get();
-            let max_multiplier = T::MaximumMultiplierBlockSize::get();
+            let params = AdjustmentParams::<T>::get();
+            let min_multiplier = params.min_multiplier;
+            let max_multiplier = params.max_multiplier;
             let previous_len_multiplier = previous_len_multiplier.max(min_multiplier);
 
-            // The limiting dimension is the length of all extrinsic
+            // The limiting dimension is the length of all extrinsic, except for the bytes
+            // priced separately
             let (normal_limiting_dimension, max_limiting_dimension) = (
-                <frame_system::Pallet<T>>::all_extrinsics_len().min(T::MaximumBlockLength::get()),
+                <frame_system::Pallet<T>>::all_extrinsics_len()
+                    .saturating_sub(ExcludedBlockLength::<T>::take())
+                    .min(T::MaximumBlockLength::get()),
                 T::MaximumBlockLength::get() as u64,
             );
 
             let target_block_size = TargetBlockSize::<T>::get();
-            let adjustment_variable = T::AdjustmentVariableBlockSize::get();
+            let adjustment_variable = params.adjustment_variable;
 
             let target_size = (target_block_size * max_limiting_dimension) as u128;
             let block_size = normal_limiting_dimension as u128;
@@ -237,81 +363,208 @@ pub mod pallet {
         }
     }
 
-    impl<T: Config + pallet_transaction_payment::Config> sp_weights::WeightToFee for Pallet<T> {
-        type Balance = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
-
-        fn weight_to_fee(weight: &Weight) -> Self::Balance {
-            // really weird but weight.ref_time will contain the length of the extrinsic
-            let length_fee = Self::Balance::saturated_from(weight.ref_time())
-                .saturating_mul(T::TransactionByteFee::get());
-            let multiplier = NextLengthMultiplier::<T>::get();
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Sets the target block size, as a fraction of `MaximumBlockLength`.
+        #[pallet::call_index(0)]
+        #[pallet::weight(T::WeightInfo::set_target_block_size())]
+        pub fn set_target_block_size(
+            origin: OriginFor<T>,
+            target_block_size: Perquintill,
+        ) -> DispatchResult {
+            T::UpdateOrigin::ensure_origin(origin)?;
+            ensure!(
+                !target_block_size.is_zero() && !target_block_size.is_one(),
+                Error::<T>::InvalidTargetBlockSize
+            );
+            TargetBlockSize::<T>::put(target_block_size);
+            Self::deposit_event(Event::TargetBlockSizeSet { target_block_size });
+            Ok(())
+        }
 
-            // final adjusted length fee
-            multiplier.saturating_mul_int(length_fee)
+        /// Sets the parameters of the length multiplier update.
+        ///
+        /// The current multiplier is brought within the new bounds at the end of the block.
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::WeightInfo::set_adjustment_params())]
+        pub fn set_adjustment_params(
+            origin: OriginFor<T>,
+            params: AdjustmentParameters,
+        ) -> DispatchResult {
+            T::UpdateOrigin::ensure_origin(origin)?;
+            ensure!(
+                !params.adjustment_variable.is_zero()
+                    && params.adjustment_variable <= Multiplier::one(),
+                Error::<T>::InvalidAdjustmentVariable
+            );
+            ensure!(
+                !params.min_multiplier.is_zero() && params.min_multiplier <= params.max_multiplier,
+                Error::<T>::InvalidMultiplierBounds
+            );
+            AdjustmentParams::<T>::put(params);
+            Self::deposit_event(Event::AdjustmentParamsSet { params });
+            Ok(())
         }
     }
 
-    impl<T: Config> OnSystemEvent for Pallet<T> {
-        fn on_validation_data(data: &PersistedValidationData) {
-            let relay_block_number = data.relay_parent_number;
-            let prev_relay_block_number =
-                T::LastRelayBlockNumberProvider::last_relay_block_number();
+    impl<T: Config> Pallet<T> {
+        /// The base fee of an extrinsic of the given length included in the current block.
+        ///
+        /// This is the adjusted length fee, burned by [`crate::ChargeTransactionPayment`].
+        pub fn base_fee(length: u32) -> BalanceOf<T> {
+            <Self as sp_weights::WeightToFee>::weight_to_fee(&Weight::from_parts(length as u64, 0))
+        }
+
+        /// The base fee of an extrinsic of the given length, were it included in the next block,
+        /// built on the given relay-parent.
+        ///
+        /// The parachain blocks skipped until the relay-parent are accounted for, as the next block
+        /// will, so the further the relay-parent, the lower the base fee.
+        pub fn next_base_fee(
+            length: u32,
+            relay_parent_number: RelayChainBlockNumber,
+        ) -> BalanceOf<T> {
+            let multiplier = Self::multiplier_after_skipped_blocks(relay_parent_number)
+                .unwrap_or_else(NextLengthMultiplier::<T>::get);
+            multiplier.saturating_mul_int(Self::length_fee(length))
+        }
+
+        /// Records the base fee burned by an extrinsic, to be reported at the end of the block.
+        pub(crate) fn note_burned_base_fee(amount: BalanceOf<T>) {
+            BurnedBaseFee::<T>::mutate(|burned| *burned = burned.saturating_add(amount));
+        }
+
+        /// The length fee before the adjustment by `NextLengthMultiplier`.
+        fn length_fee(length: u32) -> BalanceOf<T> {
+            BalanceOf::<T>::saturated_from(length).saturating_mul(T::TransactionByteFee::get())
+        }
+
+        /// The length of an extrinsic with the given call that is charged the length fee, i.e.
+        /// without the bytes given by `Config::ExcludedLength`.
+        pub fn fee_length(call: &<T as frame_system::Config>::RuntimeCall, length: u32) -> u32 {
+            length.saturating_sub(T::ExcludedLength::excluded_length(call))
+        }
+
+        /// Accounts for the parachain blocks skipped until the given relay-parent number, since
+        /// the last time this was done.
+        ///
+        /// Does nothing if the skipped blocks were already accounted for up to `relay_parent_number`,
+        /// so it is never applied twice in one block, even when called from multiple hooks.
+        pub fn account_skipped_blocks(relay_parent_number: RelayChainBlockNumber) {
+            if let Some(multiplier) = Self::multiplier_after_skipped_blocks(relay_parent_number) {
+                LastSkippedBlocksUpdate::<T>::put(relay_parent_number);
+                NextLengthMultiplier::<T>::put(multiplier);
+            }
+        }
+
+        /// The length multiplier after accounting for the parachain blocks skipped until the given
+        /// relay-parent number, since the last time this was done.
+        ///
+        /// Returns None if the skipped blocks were already accounted for up to
+        /// `relay_parent_number`.
+        fn multiplier_after_skipped_blocks(
+            relay_parent_number: RelayChainBlockNumber,
+        ) -> Option<Multiplier> {
+            let prev_relay_block_number = LastSkippedBlocksUpdate::<T>::get()
+                .unwrap_or_else(T::LastRelayBlockNumberProvider::last_relay_block_number);
+
+            // It should never be lower, because the relay-parent numbers are increasing.
+            // Nothing to account for otherwise.
+            if relay_parent_number <= prev_relay_block_number {
+                return None;
+            }
+
+            let multiplier = NextLengthMultiplier::<T>::get();
 
             // a value of zero here implies this is the first block of the parachain. no need
             // to do a massive fee update.
             if prev_relay_block_number == RelayChainBlockNumber::zero() {
-                return;
+                return Some(multiplier);
             }
 
-            // It should never be negative because the relay_block_number is surely
-            // greater than the para_block_number.
-            // However, saturating it to zero will prevent the multiplier from changing
-            let relay_parent_distance = relay_block_number.saturating_sub(prev_relay_block_number);
+            let relay_parent_distance = relay_parent_number - prev_relay_block_number;
 
-            // TODO: update with `relay_parent_distance.saturating_sub(1)`
-            // when updating to asynchronous backing
-            // https://github.com/thrumdev/blobs/issues/166
-            let n_skipped_blocks =
-                (relay_parent_distance.saturating_sub(2) / 2).min(T::MaximumSkippedBlocks::get());
+            let relay_blocks_per_para_block = T::RelayBlocksPerParachainBlock::get().max(1);
+            let n_skipped_blocks = relay_parent_distance
+                .saturating_sub(relay_blocks_per_para_block)
+                / relay_blocks_per_para_block;
 
-            let n_skipped_blocks = Multiplier::saturating_from_integer(n_skipped_blocks);
-            let target_block_size = Multiplier::from(TargetBlockSize::<T>::get());
-            let adjustment_variable = Multiplier::from(T::AdjustmentVariableBlockSize::get());
+            let params = AdjustmentParams::<T>::get();
+            let decay = skipped_blocks_decay(
+                params.adjustment_variable,
+                TargetBlockSize::<T>::get(),
+                n_skipped_blocks,
+            );
 
-            let x = adjustment_variable
-                .saturating_mul(target_block_size)
-                .saturating_mul(n_skipped_blocks);
+            // The empty blocks would not bring the multiplier below the minimum either.
+            Some(multiplier.saturating_mul(decay).max(params.min_multiplier))
+        }
+    }
 
-            // terms = sum_i (x^i / i!) where i in 0..=n_terms
-            let mut terms = Multiplier::one().saturating_sub(x);
+    impl<T: Config + pallet_transaction_payment::Config> sp_weights::WeightToFee for Pallet<T> {
+        type Balance = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
 
-            let mut fact = Multiplier::one();
-            let mut x_i = x;
+        fn weight_to_fee(weight: &Weight) -> Self::Balance {
+            // really weird but weight.ref_time will contain the length of the extrinsic
+            let length_fee = Self::length_fee(weight.ref_time().saturated_into());
+            let multiplier = NextLengthMultiplier::<T>::get();
 
-            for index in 2..=T::SkippedBlocksNumberTerms::get() {
-                x_i = x_i.saturating_mul(x);
+            // final adjusted length fee
+            multiplier.saturating_mul_int(length_fee)
+        }
+    }
 
-                if x_i == Multiplier::zero() {
-                    // If x_i is zero, the current term and all subsequent terms become useless,
-                    // and therefore all next terms can be skipped.
-                    // This happens when only a few blocks are skipped, making vnt very small
-                    break;
-                }
+    impl<T: Config> OnSystemEvent for Pallet<T> {
+        fn on_validation_data(data: &PersistedValidationData) {
+            Self::account_skipped_blocks(data.relay_parent_number);
+        }
 
-                fact = fact.saturating_mul(Multiplier::saturating_from_integer(index));
+        fn on_validation_code_applied() {}
+    }
 
-                // (-1)^index
-                terms = match index & 1 {
-                    1 => terms.saturating_sub(x_i.div(fact)),
-                    _ => terms.saturating_add(x_i.div(fact)),
-                };
-            }
+    /// `e^(-1)`, rounded to the precision of the multiplier.
+    const EXP_MINUS_ONE: Multiplier = Multiplier::from_inner(367_879_441_171_442_322);
+
+    /// The factor by which the multiplier is multiplied when `n_skipped_blocks` are skipped,
+    /// that is `e^(-v*t*n)`, where `t` is the target block size.
+    pub fn skipped_blocks_decay(
+        adjustment_variable: Multiplier,
+        target_block_size: Perquintill,
+        n_skipped_blocks: u32,
+    ) -> Multiplier {
+        let x = adjustment_variable.saturating_mul(Multiplier::from(target_block_size));
+        // x * n, without rounding. It cannot overflow, since x is at most 1.
+        let exponent =
+            Multiplier::from_inner(x.into_inner().saturating_mul(n_skipped_blocks as u128));
+        exp_neg(exponent)
+    }
 
-            NextLengthMultiplier::<T>::mutate(|multiplier| {
-                *multiplier = multiplier.saturating_mul(terms);
-            });
+    /// Computes `e^(-y)` in fixed point.
+    ///
+    /// `y` is split into its integer part `k` and its fractional part `f`, so that
+    /// `e^(-y) = e^(-1)^k / e^f`. The power is evaluated by exponentiation by squaring, and `e^f`
+    /// by its Taylor series, which converges quickly since `f < 1`.
+    fn exp_neg(y: Multiplier) -> Multiplier {
+        // e^(-y) rounds to zero from there on.
+        const MAX_EXPONENT: u128 = 64;
+
+        let k = y.into_inner() / Multiplier::DIV;
+        if k >= MAX_EXPONENT {
+            return Multiplier::zero();
+        }
+        let f = y.frac();
+
+        // The terms decrease at least as fast as 1/i!, so they become zero after a few iterations.
+        let mut exp_f = Multiplier::one();
+        let mut term = Multiplier::one();
+        let mut i = 1u128;
+        while !term.is_zero() {
+            term = term.saturating_mul(f) / Multiplier::saturating_from_integer(i);
+            exp_f = exp_f.saturating_add(term);
+            i += 1;
         }
 
-        fn on_validation_code_applied() {}
+        // e^f is at least 1, so the division is safe.
+        EXP_MINUS_ONE.saturating_pow(k as usize) / exp_f
     }
 }
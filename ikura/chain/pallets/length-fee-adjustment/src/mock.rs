@@ -1,14 +1,14 @@
 use crate as pallet_ikura_length_fee_adjustment;
 use frame_support::{
     parameter_types,
-    traits::ConstU64,
+    traits::{ConstU64, Currency, OnUnbalanced},
     weights::{Weight, WeightToFee as WeightToFeeT},
 };
 use pallet_transaction_payment::Multiplier;
 use sp_core::H256;
 use sp_runtime::{
     traits::{BlakeTwo256, IdentityLookup},
-    FixedPointNumber, SaturatedConversion,
+    BuildStorage, FixedPointNumber, SaturatedConversion,
 };
 
 use pallet_ikura_length_fee_adjustment::LastRelayBlockNumberProvider;
@@ -22,7 +22,7 @@ frame_support::construct_runtime!(
         System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
         TransactionPayment: pallet_transaction_payment::{Pallet, Storage, Event<T>},
-        LengthFeeAdjustment: pallet_ikura_length_fee_adjustment::{Pallet, Storage},
+        LengthFeeAdjustment: pallet_ikura_length_fee_adjustment::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -82,16 +82,10 @@ parameter_types! {
     pub MinimumMultiplierBlockSize: Multiplier = Multiplier::saturating_from_rational(1, 200u128);
     pub MaximumMultiplierBlockSize: Multiplier = Multiplier::saturating_from_integer(10);
 
-    // TODO: NumberTerms neets to be changed 5
-    // when updating to asynchronous backing
-    // https://github.com/thrumdev/blobs/issues/166
-    // Accepted error is less than 10^(-2)
-    pub SkippedBlocksNumberTerms: u32 = 3;
-    pub MaximumSkippedBlocks: u32 = ikura_primitives::MAX_SKIPPED_BLOCKS;
-
     pub static WeightToFee: u64 = 1;
     pub static OperationalFeeMultiplier: u8 = 5;
     pub static LastRelayBlockNumber: RelayChainBlockNumber = 0;
+    pub static RelayBlocksPerParachainBlock: u32 = 1;
 }
 
 pub struct MockLastRelayBlockNumberProvider;
@@ -115,9 +109,20 @@ impl WeightToFeeT for WeightToFee {
     }
 }
 
+/// The account receiving the fees that are not burned.
+pub const COLLATOR: u64 = 42;
+
+pub struct ToCollator;
+
+impl OnUnbalanced<pallet_balances::NegativeImbalance<Test>> for ToCollator {
+    fn on_nonzero_unbalanced(amount: pallet_balances::NegativeImbalance<Test>) {
+        Balances::resolve_creating(&COLLATOR, amount);
+    }
+}
+
 impl pallet_transaction_payment::Config for Test {
     type RuntimeEvent = RuntimeEvent;
-    type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, ()>;
+    type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, ToCollator>;
     type OperationalFeeMultiplier = OperationalFeeMultiplier;
     type WeightToFee = WeightToFee;
     type LengthToFee = LengthFeeAdjustment;
@@ -125,12 +130,38 @@ impl pallet_transaction_payment::Config for Test {
 }
 
 impl pallet_ikura_length_fee_adjustment::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type UpdateOrigin = frame_system::EnsureRoot<u64>;
+    type Currency = Balances;
+    type WeightInfo = ();
     type TransactionByteFee = TransactionByteFee;
     type MaximumBlockLength = MaximumBlockLength;
     type AdjustmentVariableBlockSize = AdjustmentVariableBlockSize;
     type MinimumMultiplierBlockSize = MinimumMultiplierBlockSize;
     type MaximumMultiplierBlockSize = MaximumMultiplierBlockSize;
-    type SkippedBlocksNumberTerms = SkippedBlocksNumberTerms;
-    type MaximumSkippedBlocks = MaximumSkippedBlocks;
     type LastRelayBlockNumberProvider = MockLastRelayBlockNumberProvider;
+    type RelayBlocksPerParachainBlock = RelayBlocksPerParachainBlock;
+    type ExcludedLength = RemarkLength;
+}
+
+/// The remarks are priced separately, like the blob data in the runtimes.
+pub struct RemarkLength;
+
+impl pallet_ikura_length_fee_adjustment::ExcludedLength<RuntimeCall> for RemarkLength {
+    fn excluded_length(call: &RuntimeCall) -> u32 {
+        match call {
+            RuntimeCall::System(frame_system::Call::remark { remark }) => remark.len() as u32,
+            _ => 0,
+        }
+    }
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut ext: sp_io::TestExternalities = frame_system::GenesisConfig::<Test>::default()
+        .build_storage()
+        .unwrap()
+        .into();
+    // Events are not deposited in the genesis block.
+    ext.execute_with(|| System::set_block_number(1));
+    ext
 }
@@ -1,24 +1,27 @@
 use crate::{
-    mock::{self, LengthFeeAdjustment, Test},
+    mock::{
+        self, new_test_ext, Balances, LengthFeeAdjustment, RuntimeCall, RuntimeEvent,
+        RuntimeOrigin, System, Test, TransactionPayment, COLLATOR,
+    },
     *,
 };
 use cumulus_pallet_parachain_system::OnSystemEvent;
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::{DispatchInfo, Pays, PostDispatchInfo},
+    traits::{Currency, Hooks},
+};
 use pallet_transaction_payment::Multiplier;
 use polkadot_primitives::{v6::PersistedValidationData, HeadData};
-use sp_runtime::{assert_eq_error_rate, BuildStorage};
+use quickcheck_macros::quickcheck;
+use sp_runtime::assert_eq_error_rate;
 use sp_runtime::{
-    traits::{Get, One},
-    FixedPointNumber,
+    traits::{Get, One, SignedExtension, Zero},
+    transaction_validity::InvalidTransaction,
+    DispatchError, FixedPointNumber, Perquintill,
 };
 use sp_weights::{Weight, WeightToFee};
 
-fn new_test_ext() -> sp_io::TestExternalities {
-    frame_system::GenesisConfig::<Test>::default()
-        .build_storage()
-        .unwrap()
-        .into()
-}
-
 #[test]
 fn test_weight_to_fee() {
     new_test_ext().execute_with(|| {
@@ -90,18 +93,17 @@ fn test_no_update_when_prev_is_zero() {
 #[test]
 fn test_skipped_block_multiplier_update() {
     new_test_ext().execute_with(|| {
-        let max_skipped_blocks = <Test as Config>::MaximumSkippedBlocks::get();
-        for d in (0..max_skipped_blocks).step_by(max_skipped_blocks as usize / 100) {
+        // Half a day of parachain blocks.
+        let skipped_blocks = 3600;
+        for d in (0..skipped_blocks).step_by(skipped_blocks as usize / 100) {
             // using Multiplier::one() only e^(-vnt) is tested
             NextLengthMultiplier::<Test>::put(Multiplier::one());
+            LastSkippedBlocksUpdate::<Test>::kill();
             mock::set_last_relay_block_number(1);
 
-            // TODO: update with `1 + d`
-            // when updating to asynchronous backing
-            // https://github.com/thrumdev/blobs/issues/166
             let relay_data = PersistedValidationData {
                 parent_head: HeadData(vec![]),
-                relay_parent_number: 1 + 2 + d * 2, // extra 1 is because last rp was 1
+                relay_parent_number: 1 + 1 + d, // extra 1 is because last rp was 1
                 relay_parent_storage_root: sp_core::H256::zero(),
                 max_pov_size: 0,
             };
@@ -121,20 +123,49 @@ fn test_skipped_block_multiplier_update() {
     });
 }
 
+#[test]
+fn test_skipped_blocks_with_sync_backing() {
+    new_test_ext().execute_with(|| {
+        mock::RelayBlocksPerParachainBlock::set(2);
+        let skipped_blocks = 100;
+        let decay = skipped_blocks_decay(
+            <Test as Config>::AdjustmentVariableBlockSize::get(),
+            TargetBlockSize::<Test>::get(),
+            skipped_blocks,
+        );
+
+        // One relay chain block more than expected is not yet a skipped block.
+        for relay_parent_number in [1 + 2 + skipped_blocks * 2, 1 + 2 + skipped_blocks * 2 + 1] {
+            NextLengthMultiplier::<Test>::put(Multiplier::one());
+            LastSkippedBlocksUpdate::<Test>::kill();
+            mock::set_last_relay_block_number(1);
+
+            let relay_data = PersistedValidationData {
+                parent_head: HeadData(vec![]),
+                relay_parent_number,
+                relay_parent_storage_root: sp_core::H256::zero(),
+                max_pov_size: 0,
+            };
+
+            LengthFeeAdjustment::on_validation_data(&relay_data);
+            assert_eq!(NextLengthMultiplier::<Test>::get(), decay);
+        }
+    });
+}
+
 #[test]
 fn test_max_skipped_block_exceeded() {
     new_test_ext().execute_with(|| {
-        NextLengthMultiplier::<Test>::put(Multiplier::one());
+        NextLengthMultiplier::<Test>::put(<Test as Config>::MaximumMultiplierBlockSize::get());
         mock::set_last_relay_block_number(1);
 
-        let max_skipped_blocks = <Test as Config>::MaximumSkippedBlocks::get();
+        // The former cap on the number of skipped blocks, half a day of blocks.
+        let max_skipped_blocks = 3600;
         let relay_data = PersistedValidationData {
             parent_head: HeadData(vec![]),
-            // The previous relay parent was 10 times greater than the expected MaximumSkippedBlocks.
-            // If the multiplier is updated with that number of skipped blocks
-            // there's should be a significant divergence in the final result.
-            // However, we expect it to be bounded by max_skipped_blocks.
-            relay_parent_number: 1 + 2 + ((max_skipped_blocks * 10) * 2),
+            // The previous relay parent was 10 times greater than the former cap.
+            // All the skipped blocks are accounted for, without any loss of precision.
+            relay_parent_number: 1 + 1 + max_skipped_blocks * 10,
             relay_parent_storage_root: sp_core::H256::zero(),
             max_pov_size: 0,
         };
@@ -146,11 +177,97 @@ fn test_max_skipped_block_exceeded() {
         // calculate expected result using f64::exp and assert on the error rate
         let target = Multiplier::from(TargetBlockSize::<Test>::get()).to_float();
         let v = <Test as Config>::AdjustmentVariableBlockSize::get().to_float();
-        let expected_mul =
-            Multiplier::from_float((-1.0 * target * v * max_skipped_blocks as f64).exp());
+        let max_mul = <Test as Config>::MaximumMultiplierBlockSize::get().to_float();
+        let expected_mul = Multiplier::from_float(
+            max_mul * (-1.0 * target * v * (max_skipped_blocks * 10) as f64).exp(),
+        );
+        assert!(expected_mul > <Test as Config>::MinimumMultiplierBlockSize::get());
+
+        //Accepted error is less than 10^(-12)
+        assert_eq_error_rate!(mul, expected_mul, Multiplier::from_inner(1000000));
+    });
+}
+
+#[test]
+fn test_long_gap_clamped_to_min_multiplier() {
+    new_test_ext().execute_with(|| {
+        NextLengthMultiplier::<Test>::put(Multiplier::one());
+        mock::set_last_relay_block_number(1);
 
-        //Accepted error is less than 10^(-2)
-        assert_eq_error_rate!(mul, expected_mul, Multiplier::from_inner(10000000000000000));
+        // A million skipped blocks would bring the multiplier to zero,
+        // but it is never updated below the minimum.
+        let relay_data = PersistedValidationData {
+            parent_head: HeadData(vec![]),
+            relay_parent_number: 1 + 1 + 1_000_000,
+            relay_parent_storage_root: sp_core::H256::zero(),
+            max_pov_size: 0,
+        };
+
+        LengthFeeAdjustment::on_validation_data(&relay_data);
+
+        assert_eq!(
+            NextLengthMultiplier::<Test>::get(),
+            <Test as Config>::MinimumMultiplierBlockSize::get()
+        );
+    });
+}
+
+#[test]
+fn test_skipped_blocks_on_initialize() {
+    new_test_ext().execute_with(|| {
+        let skipped_blocks = 100;
+        NextLengthMultiplier::<Test>::put(Multiplier::one());
+        LastSkippedBlocksUpdate::<Test>::put(1);
+        // `on_validation_data` was not called in the previous block.
+        mock::set_last_relay_block_number(1 + 1 + skipped_blocks);
+
+        LengthFeeAdjustment::on_initialize(2);
+
+        assert_eq!(
+            NextLengthMultiplier::<Test>::get(),
+            skipped_blocks_decay(
+                <Test as Config>::AdjustmentVariableBlockSize::get(),
+                TargetBlockSize::<Test>::get(),
+                skipped_blocks,
+            )
+        );
+        assert_eq!(
+            LastSkippedBlocksUpdate::<Test>::get(),
+            Some(1 + 1 + skipped_blocks)
+        );
+    });
+}
+
+#[test]
+fn test_skipped_blocks_not_applied_twice() {
+    new_test_ext().execute_with(|| {
+        NextLengthMultiplier::<Test>::put(Multiplier::one());
+        mock::set_last_relay_block_number(1);
+
+        let relay_data = PersistedValidationData {
+            parent_head: HeadData(vec![]),
+            relay_parent_number: 1 + 1 + 100,
+            relay_parent_storage_root: sp_core::H256::zero(),
+            max_pov_size: 0,
+        };
+
+        LengthFeeAdjustment::on_validation_data(&relay_data);
+        let mul = NextLengthMultiplier::<Test>::get();
+        assert!(mul < Multiplier::one());
+        assert_eq!(
+            LastSkippedBlocksUpdate::<Test>::get(),
+            Some(relay_data.relay_parent_number)
+        );
+
+        // Applying it again in the same block does nothing.
+        LengthFeeAdjustment::on_validation_data(&relay_data);
+        assert_eq!(NextLengthMultiplier::<Test>::get(), mul);
+
+        // Neither does the `on_initialize` of the next block.
+        mock::set_last_relay_block_number(relay_data.relay_parent_number);
+        System::set_block_number(2);
+        LengthFeeAdjustment::on_initialize(2);
+        assert_eq!(NextLengthMultiplier::<Test>::get(), mul);
     });
 }
 
@@ -173,3 +290,345 @@ fn test_skipped_block_no_prev_data() {
         assert_eq!(NextLengthMultiplier::<Test>::get(), prev_multiplier);
     });
 }
+
+#[test]
+fn test_set_target_block_size() {
+    new_test_ext().execute_with(|| {
+        let target_block_size = Perquintill::from_percent(50);
+        assert_noop!(
+            LengthFeeAdjustment::set_target_block_size(RuntimeOrigin::signed(1), target_block_size),
+            DispatchError::BadOrigin
+        );
+        for invalid in [Perquintill::zero(), Perquintill::one()] {
+            assert_noop!(
+                LengthFeeAdjustment::set_target_block_size(RuntimeOrigin::root(), invalid),
+                Error::<Test>::InvalidTargetBlockSize
+            );
+        }
+
+        assert_ok!(LengthFeeAdjustment::set_target_block_size(
+            RuntimeOrigin::root(),
+            target_block_size
+        ));
+        assert_eq!(TargetBlockSize::<Test>::get(), target_block_size);
+        System::assert_last_event(RuntimeEvent::LengthFeeAdjustment(
+            Event::TargetBlockSizeSet { target_block_size },
+        ));
+    });
+}
+
+#[test]
+fn test_default_adjustment_params() {
+    new_test_ext().execute_with(|| {
+        let params = AdjustmentParams::<Test>::get();
+        assert_eq!(
+            params.adjustment_variable,
+            <Test as Config>::AdjustmentVariableBlockSize::get()
+        );
+        assert_eq!(
+            params.min_multiplier,
+            <Test as Config>::MinimumMultiplierBlockSize::get()
+        );
+        assert_eq!(
+            params.max_multiplier,
+            <Test as Config>::MaximumMultiplierBlockSize::get()
+        );
+    });
+}
+
+#[test]
+fn test_set_adjustment_params() {
+    new_test_ext().execute_with(|| {
+        let valid = AdjustmentParameters {
+            adjustment_variable: Multiplier::saturating_from_rational(1, 420),
+            min_multiplier: Multiplier::saturating_from_rational(1, 2),
+            max_multiplier: Multiplier::saturating_from_integer(2),
+        };
+        assert_noop!(
+            LengthFeeAdjustment::set_adjustment_params(RuntimeOrigin::signed(1), valid),
+            DispatchError::BadOrigin
+        );
+
+        let invalid = [
+            (
+                AdjustmentParameters {
+                    adjustment_variable: Multiplier::zero(),
+                    ..valid
+                },
+                Error::<Test>::InvalidAdjustmentVariable,
+            ),
+            (
+                AdjustmentParameters {
+                    adjustment_variable: Multiplier::saturating_from_integer(2),
+                    ..valid
+                },
+                Error::<Test>::InvalidAdjustmentVariable,
+            ),
+            (
+                AdjustmentParameters {
+                    min_multiplier: Multiplier::zero(),
+                    ..valid
+                },
+                Error::<Test>::InvalidMultiplierBounds,
+            ),
+            (
+                AdjustmentParameters {
+                    min_multiplier: Multiplier::saturating_from_integer(3),
+                    ..valid
+                },
+                Error::<Test>::InvalidMultiplierBounds,
+            ),
+        ];
+        for (params, error) in invalid {
+            assert_noop!(
+                LengthFeeAdjustment::set_adjustment_params(RuntimeOrigin::root(), params),
+                error
+            );
+        }
+
+        assert_ok!(LengthFeeAdjustment::set_adjustment_params(
+            RuntimeOrigin::root(),
+            valid
+        ));
+        assert_eq!(AdjustmentParams::<Test>::get(), valid);
+        System::assert_last_event(RuntimeEvent::LengthFeeAdjustment(
+            Event::AdjustmentParamsSet { params: valid },
+        ));
+
+        // The new bounds are applied at the end of the block.
+        NextLengthMultiplier::<Test>::put(Multiplier::saturating_from_integer(10));
+        LengthFeeAdjustment::on_finalize(1);
+        assert_eq!(
+            NextLengthMultiplier::<Test>::get(),
+            Multiplier::saturating_from_integer(2)
+        );
+    });
+}
+
+#[test]
+fn test_base_fee_burned() {
+    new_test_ext().execute_with(|| {
+        let who = 1;
+        let initial_balance = 1_000_000_000_000;
+        Balances::make_free_balance_be(&who, initial_balance);
+        NextLengthMultiplier::<Test>::put(Multiplier::saturating_from_rational(3, 2));
+
+        let call = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+        let info = DispatchInfo {
+            weight: Weight::from_parts(100, 0),
+            ..Default::default()
+        };
+        let tip = 7;
+        let total_issuance = Balances::total_issuance();
+
+        let mut fees = 0;
+        let mut base_fees = 0;
+        for len in [1000, 3000] {
+            let pre = ChargeTransactionPayment::<Test>::from(tip)
+                .pre_dispatch(&who, &call, &info, len)
+                .unwrap();
+            assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+                Some(pre),
+                &info,
+                &PostDispatchInfo::default(),
+                len,
+                &Ok(())
+            ));
+            fees += TransactionPayment::compute_fee(len as u32, &info, tip);
+            base_fees += LengthFeeAdjustment::base_fee(len as u32);
+        }
+        assert!(!base_fees.is_zero());
+
+        // The base fees are burned, the rest of the fees and the tips go to the collator.
+        assert_eq!(Balances::free_balance(who), initial_balance - fees);
+        assert_eq!(Balances::total_issuance(), total_issuance - base_fees);
+        assert_eq!(Balances::free_balance(COLLATOR), fees - base_fees);
+        assert_eq!(BurnedBaseFee::<Test>::get(), base_fees);
+
+        // They are reported by a single event at the end of the block.
+        System::reset_events();
+        LengthFeeAdjustment::on_finalize(1);
+        assert_eq!(
+            System::events()
+                .into_iter()
+                .map(|record| record.event)
+                .collect::<Vec<_>>(),
+            vec![RuntimeEvent::LengthFeeAdjustment(Event::BaseFeeBurned {
+                amount: base_fees
+            })]
+        );
+        assert!(BurnedBaseFee::<Test>::get().is_zero());
+    });
+}
+
+#[test]
+fn test_base_fee_refund() {
+    new_test_ext().execute_with(|| {
+        let who = 1;
+        let initial_balance = 1_000_000_000_000;
+        Balances::make_free_balance_be(&who, initial_balance);
+
+        let call = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+        let info = DispatchInfo {
+            weight: Weight::from_parts(1_000_000, 0),
+            ..Default::default()
+        };
+        // Only half of the weight is used, the base fee is not affected by the refund.
+        let post_info = PostDispatchInfo {
+            actual_weight: Some(Weight::from_parts(500_000, 0)),
+            ..Default::default()
+        };
+        let len = 1000;
+
+        let pre = ChargeTransactionPayment::<Test>::from(0)
+            .pre_dispatch(&who, &call, &info, len)
+            .unwrap();
+        assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+            Some(pre),
+            &info,
+            &post_info,
+            len,
+            &Ok(())
+        ));
+
+        let fee = TransactionPayment::compute_actual_fee(len as u32, &info, &post_info, 0);
+        let base_fee = LengthFeeAdjustment::base_fee(len as u32);
+        assert_eq!(Balances::free_balance(who), initial_balance - fee);
+        assert_eq!(Balances::free_balance(COLLATOR), fee - base_fee);
+        assert_eq!(BurnedBaseFee::<Test>::get(), base_fee);
+
+        // The whole fee, the base fee included, is refunded if the call turns out not to pay.
+        let pre = ChargeTransactionPayment::<Test>::from(0)
+            .pre_dispatch(&who, &call, &info, len)
+            .unwrap();
+        assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+            Some(pre),
+            &info,
+            &PostDispatchInfo {
+                pays_fee: Pays::No,
+                ..post_info
+            },
+            len,
+            &Ok(())
+        ));
+        assert_eq!(Balances::free_balance(who), initial_balance - fee);
+        assert_eq!(Balances::free_balance(COLLATOR), fee - base_fee);
+        assert_eq!(BurnedBaseFee::<Test>::get(), base_fee);
+    });
+}
+
+#[test]
+fn test_base_fee_not_affordable() {
+    new_test_ext().execute_with(|| {
+        let who = 1;
+        let call = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+        let info = DispatchInfo {
+            weight: Weight::from_parts(100, 0),
+            ..Default::default()
+        };
+        let len = 1000;
+
+        // Enough for the weight fee, but not for the base fee.
+        let base_fee = LengthFeeAdjustment::base_fee(len as u32);
+        let weight_fee = TransactionPayment::compute_fee(0, &info, 0);
+        Balances::make_free_balance_be(&who, weight_fee + base_fee / 2);
+
+        let extension = ChargeTransactionPayment::<Test>::from(0);
+        assert_eq!(
+            extension.validate(&who, &call, &info, len).err(),
+            Some(InvalidTransaction::Payment.into())
+        );
+        assert_eq!(
+            extension.pre_dispatch(&who, &call, &info, len).err(),
+            Some(InvalidTransaction::Payment.into())
+        );
+    });
+}
+
+#[test]
+fn test_next_base_fee_accounts_for_skipped_blocks() {
+    new_test_ext().execute_with(|| {
+        mock::set_last_relay_block_number(10);
+        NextLengthMultiplier::<Test>::put(Multiplier::saturating_from_integer(2));
+        let len = 1000;
+
+        // No block skipped, the base fee does not change.
+        assert_eq!(
+            LengthFeeAdjustment::next_base_fee(len, 11),
+            LengthFeeAdjustment::base_fee(len)
+        );
+
+        // The further the relay-parent, the more blocks are skipped and the lower the base fee.
+        let after_skipped = LengthFeeAdjustment::next_base_fee(len, 20);
+        assert!(after_skipped < LengthFeeAdjustment::base_fee(len));
+        assert!(LengthFeeAdjustment::next_base_fee(len, 30) < after_skipped);
+
+        // It is the base fee charged once the next block accounts for the skipped blocks.
+        LengthFeeAdjustment::account_skipped_blocks(20);
+        assert_eq!(LengthFeeAdjustment::base_fee(len), after_skipped);
+        assert_eq!(LengthFeeAdjustment::next_base_fee(len, 20), after_skipped);
+    });
+}
+
+#[test]
+fn test_excluded_length_not_charged() {
+    new_test_ext().execute_with(|| {
+        let who = 1;
+        let initial_balance = 1_000_000_000_000;
+        Balances::make_free_balance_be(&who, initial_balance);
+
+        // The remark is excluded by the mock, like the blob data in the runtimes.
+        let call = RuntimeCall::System(frame_system::Call::remark {
+            remark: vec![0; 600],
+        });
+        let info = DispatchInfo {
+            weight: Weight::from_parts(100, 0),
+            ..Default::default()
+        };
+        let len = 1000;
+
+        let pre = ChargeTransactionPayment::<Test>::from(0)
+            .pre_dispatch(&who, &call, &info, len)
+            .unwrap();
+        assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+            Some(pre),
+            &info,
+            &PostDispatchInfo::default(),
+            len,
+            &Ok(())
+        ));
+
+        // Only the bytes besides the remark are charged the length fee.
+        let fee = TransactionPayment::compute_fee(400, &info, 0);
+        assert_eq!(Balances::free_balance(who), initial_balance - fee);
+        assert_eq!(ExcludedBlockLength::<Test>::get(), 600);
+
+        // Nor do they count towards the block size.
+        System::set_block_consumed_resources(Weight::zero(), len);
+        LengthFeeAdjustment::on_finalize(1);
+        let multiplier = NextLengthMultiplier::<Test>::get();
+        assert_eq!(ExcludedBlockLength::<Test>::get(), 0);
+
+        NextLengthMultiplier::<Test>::put(Multiplier::one());
+        System::set_block_consumed_resources(Weight::zero(), 400);
+        LengthFeeAdjustment::on_finalize(1);
+        assert_eq!(NextLengthMultiplier::<Test>::get(), multiplier);
+    });
+}
+
+#[quickcheck]
+fn skipped_blocks_decay_matches_float_reference(
+    adjustment_variable: u64,
+    target_block_size: u64,
+    n_skipped_blocks: u32,
+) -> bool {
+    let v = Multiplier::from(Perquintill::from_parts(adjustment_variable));
+    let t = Perquintill::from_parts(target_block_size);
+
+    let decay = skipped_blocks_decay(v, t, n_skipped_blocks).to_float();
+    // v * t is rounded to the precision of the multiplier, the exponential must not lose more.
+    let x = v.saturating_mul(Multiplier::from(t)).to_float();
+    let expected = (-x * n_skipped_blocks as f64).exp();
+
+    (decay - expected).abs() <= 1e-15 + expected * 1e-12
+}
@@ -0,0 +1,66 @@
+//! Weights for `pallet_ikura_length_fee_adjustment`
+//!
+//! NOT MEASURED: no benchmark has been run for this pallet yet, the values are estimates to be
+//! replaced by the output of the benchmark CLI on the reference hardware before a release.
+//!
+//! Both calls are a root origin check and a single storage write. They are charged 7 µs, above the
+//! 4.6 µs measured for the read and the write of `System::Digest` in `on_finalize` of
+//! `pallet_ikura_blobs` on the same hardware, and no proof size as nothing is read.
+//!
+//! Regenerate this file with:
+//!
+//! ./target/release/ikura-node benchmark pallet \
+//! --dev \
+//! --pallet pallet_ikura_length_fee_adjustment \
+//! --extrinsic '*' \
+//! --steps 20 \
+//! --repeat 20 \
+//! --template ../frame-weight-template.hbs \
+//! --output ikura-chain/pallets/length-fee-adjustment/src/weights.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+#![allow(missing_docs)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for `pallet_ikura_length_fee_adjustment`.
+pub trait WeightInfo {
+	fn set_target_block_size() -> Weight;
+	fn set_adjustment_params() -> Weight;
+}
+
+/// Weights for `pallet_ikura_length_fee_adjustment` using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: `LengthFeeAdjustment::TargetBlockSize` (r:0 w:1)
+	/// Proof: `LengthFeeAdjustment::TargetBlockSize` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	fn set_target_block_size() -> Weight {
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `LengthFeeAdjustment::AdjustmentParams` (r:0 w:1)
+	/// Proof: `LengthFeeAdjustment::AdjustmentParams` (`max_values`: Some(1), `max_size`: Some(52), added: 547, mode: `MaxEncodedLen`)
+	fn set_adjustment_params() -> Weight {
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests.
+impl WeightInfo for () {
+	/// Storage: `LengthFeeAdjustment::TargetBlockSize` (r:0 w:1)
+	/// Proof: `LengthFeeAdjustment::TargetBlockSize` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	fn set_target_block_size() -> Weight {
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: `LengthFeeAdjustment::AdjustmentParams` (r:0 w:1)
+	/// Proof: `LengthFeeAdjustment::AdjustmentParams` (`max_values`: Some(1), `max_size`: Some(52), added: 547, mode: `MaxEncodedLen`)
+	fn set_adjustment_params() -> Weight {
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}
@@ -0,0 +1,37 @@
+//! The runtime APIs of the ikura runtimes.
+
+use sp_runtime::sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// The API to query the base fee of the extrinsics, burned on inclusion.
+    pub trait BaseFeeApi<Balance> where Balance: parity_scale_codec::Codec {
+        /// The base fee of an extrinsic of the given length, were it included in the next block,
+        /// built on the relay-parent with the given number.
+        ///
+        /// The parachain blocks skipped until the relay-parent lower the base fee, so it should be
+        /// the relay-parent the next block is expected to be built on, or the latest relay-chain
+        /// block for a lower bound.
+        ///
+        /// The fee of an extrinsic in excess of the base fee, including the tip, is paid to the
+        /// collator.
+        fn next_base_fee(length: u32, relay_parent_number: u32) -> Balance;
+    }
+
+    /// The API to inspect the blob submissions, without knowing the extrinsic format of the
+    /// runtime.
+    pub trait BlobsApi {
+        /// The size of the blob submitted by the given extrinsic, if it is a blob submission.
+        fn blob_size(extrinsic: <Block as sp_runtime::traits::Block>::Extrinsic) -> Option<u32>;
+
+        /// The maximum number of blobs in a block and their maximum total size.
+        fn blob_limits() -> (u32, u32);
+
+        /// The namespace IDs and data of the blobs stored by the given block, in the order of
+        /// submission. These are the blobs committed to by the digests of the block.
+        ///
+        /// To be called at the parent of the block, whose extrinsics are applied again. Only the
+        /// blob submissions which succeeded are returned, including those dispatched by another
+        /// call.
+        fn submitted_blobs(block: Block) -> Vec<(u128, Vec<u8>)>;
+    }
+}
@@ -6,7 +6,7 @@ pub mod consensus {
 
     /// Maximum number of blocks simultaneously accepted by the Runtime, not yet included
     /// into the relay chain.
-    pub const UNINCLUDED_SEGMENT_CAPACITY: u32 = 1;
+    pub const UNINCLUDED_SEGMENT_CAPACITY: u32 = 3;
     /// How many parachain blocks are processed by the relay chain per parent. Limits the
     /// number of blocks authored per slot.
     pub const BLOCK_PROCESSING_VELOCITY: u32 = 1;
@@ -19,6 +19,9 @@ pub mod consensus {
     /// slot_duration()`.
     ///
     /// Change this to adjust the block time.
+    ///
+    /// NOTE: Currently it is not possible to change the slot duration after the chain has
+    /// started. Attempting to do so will brick block production.
     pub const MILLISECS_PER_BLOCK: u64 = 12000;
     pub const SLOT_DURATION: u64 = MILLISECS_PER_BLOCK;
 
@@ -34,9 +37,9 @@ pub mod consensus {
     /// Operational  extrinsics.
     pub const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 
-    /// We allow for 0.5 seconds of compute with a 6 second average block time.
+    /// We allow for 2 seconds of compute with a 12 second average block time.
     pub const MAXIMUM_BLOCK_WEIGHT: Weight = Weight::from_parts(
-        WEIGHT_REF_TIME_PER_SECOND.saturating_div(2),
+        WEIGHT_REF_TIME_PER_SECOND.saturating_mul(2),
         cumulus_primitives_core::relay_chain::MAX_POV_SIZE as u64,
     );
 }
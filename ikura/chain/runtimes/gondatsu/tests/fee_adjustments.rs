@@ -9,7 +9,7 @@ use gondatsu_runtime::{
         kusama::currency::{CENTS, MILLICENTS},
     },
     AdjustmentVariableBlockFullness, AdjustmentVariableBlockSize, LengthFeeAdjustment,
-    MinimumMultiplierBlockFullness, MinimumMultiplierBlockSize, Runtime,
+    MaxTotalBlobSize, MinimumMultiplierBlockFullness, MinimumMultiplierBlockSize, Runtime,
     RuntimeBlockWeights as BlockWeights, SlowAdjustingFeeUpdate, System, TargetBlockFullness,
     TransactionPayment,
 };
@@ -240,11 +240,7 @@ fn multiplier_cannot_go_below_limit() {
 
 #[test]
 fn time_to_reach_zero() {
-    // TODO: 1440 needs to be used instead of 7200
-    // when updating to asynchronous backing
-    // https://github.com/thrumdev/blobs/issues/166
-    //
-    // blocks per 24h in cumulus-node: 7200 (k)
+    // blocks per 24h with 12 seconds blocks: 7200 (k)
     // s* = 0.1875 (TargetBlockFullness) or 0.16 (TargetBlockSize)
     // The bound from the research in an empty chain is:
     // v <~ (p / k(0 - s*))
@@ -287,11 +283,7 @@ fn time_to_reach_zero() {
 
 #[test]
 fn time_to_reach_one() {
-    // TODO: 1440 needs to ne used insted of 7200
-    // when updating to asynchronous backing
-    // https://github.com/thrumdev/blobs/issues/166
-    //
-    // blocks per 24h in cumulus-node: 7200 (k)
+    // blocks per 24h with 12 seconds blocks: 7200 (k)
     // s* = 0.1875 (TargetBlockFullness) or 0.16 (TargetBlockSize)
     // The bound from the research in an full chain is:
     // v <~ (p / k(1 - s*))
@@ -344,14 +336,10 @@ fn min_change_per_day() {
                 let next = mul_type.runtime_multiplier_update(fm);
                 fm = next;
             }
-            // TODO: 1440 needs to ne used insted of 7200
-            // when updating to asynchronous backing
-            // https://github.com/thrumdev/blobs/issues/166
-            //
-            // 7200 blocks per day with one 12 seconds blocks
+            // DAYS blocks per day with 12 seconds blocks
             // v * k * (1 - s)
             let expected = mul_type.adjustment_variable()
-                * Multiplier::saturating_from_integer(7200)
+                * Multiplier::saturating_from_integer(DAYS)
                 * (Multiplier::saturating_from_integer(1) - mul_type.target_percentage());
             assert!(fm > expected);
         })
@@ -624,3 +612,29 @@ fn weight_to_fee_should_not_overflow_on_large_weights() {
     test(MultiplierType::Fee);
     test(MultiplierType::Length);
 }
+
+#[test]
+fn blob_bytes_do_not_affect_length_multiplier() {
+    let next_length_multiplier = |all_extrinsics_len: usize, excluded_len: u32| {
+        let mut t: sp_io::TestExternalities = frame_system::GenesisConfig::<Runtime>::default()
+            .build_storage()
+            .unwrap()
+            .into();
+        t.execute_with(|| {
+            System::set_block_consumed_resources(Weight::zero(), all_extrinsics_len);
+            pallet_ikura_length_fee_adjustment::ExcludedBlockLength::<Runtime>::put(excluded_len);
+            LengthFeeAdjustment::on_finalize(0);
+            NextLengthMultiplier::<Runtime>::get()
+        })
+    };
+
+    let target = MultiplierType::Length.target().ref_time() as usize;
+    let blob_bytes = MaxTotalBlobSize::get();
+
+    // A block at the target does not move the multiplier, even with the blobs on top of it.
+    assert_eq!(next_length_multiplier(target, 0), Multiplier::one());
+    assert_eq!(
+        next_length_multiplier(target + blob_bytes as usize, blob_bytes),
+        Multiplier::one()
+    );
+}
@@ -79,7 +79,7 @@ fn create_submit_blob_utx(
         frame_system::CheckEra::<Runtime>::from(sp_runtime::generic::Era::immortal()),
         frame_system::CheckNonce::<Runtime>::from(0),
         frame_system::CheckWeight::<Runtime>::new(),
-        pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+        pallet_ikura_length_fee_adjustment::ChargeTransactionPayment::<Runtime>::from(0),
         pallet_ikura_blobs::PrevalidateBlobs::<Runtime>::new(),
     );
 
@@ -193,3 +193,21 @@ fn test_inclusion_fee_uses_length_to_fee() {
         assert_eq!(length_fee, expected_length_fee);
     });
 }
+
+#[test]
+fn test_blob_bytes_not_charged_length_fee() {
+    new_test_ext().execute_with(|| {
+        let blob = vec![0; 1000];
+        let call: RuntimeCall = pallet_ikura_blobs::Call::submit_blob {
+            namespace_id: 0.into(),
+            blob: blob.clone(),
+        }
+        .into();
+        let len = call.size_hint() as u32;
+        assert_eq!(LengthFeeAdjustment::fee_length(&call, len), len - 1000);
+
+        let call: RuntimeCall = frame_system::Call::remark { remark: blob }.into();
+        let len = call.size_hint() as u32;
+        assert_eq!(LengthFeeAdjustment::fee_length(&call, len), len);
+    });
+}
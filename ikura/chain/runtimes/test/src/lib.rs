@@ -9,7 +9,7 @@ include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 mod weights;
 pub mod xcm_config;
 
-use cumulus_pallet_parachain_system::RelayNumberStrictlyIncreases;
+use cumulus_pallet_parachain_system::RelayNumberMonotonicallyIncreases;
 use cumulus_primitives_core::{AggregateMessageOrigin, ParaId};
 use polkadot_runtime_common::xcm_sender::NoPriceForMessageDelivery;
 use smallvec::smallvec;
@@ -95,7 +95,7 @@ pub type SignedExtra = (
     frame_system::CheckEra<Runtime>,
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
-    pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+    pallet_ikura_length_fee_adjustment::ChargeTransactionPayment<Runtime>,
     pallet_ikura_blobs::PrevalidateBlobs<Runtime>,
 );
 
@@ -150,7 +150,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("ikura-chain"),
     impl_name: create_runtime_str!("ikura-chain"),
     authoring_version: 1,
-    spec_version: 1001,
+    spec_version: 1003,
     impl_version: 0,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 1,
@@ -192,9 +192,9 @@ const AVERAGE_ON_INITIALIZE_RATIO: Perbill = Perbill::from_percent(5);
 /// `Operational` extrinsics.
 const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 
-/// We allow for 0.5 of a second of compute with a 12 second average block time.
+/// We allow for 2 seconds of compute with a 12 second average block time.
 const MAXIMUM_BLOCK_WEIGHT: Weight = Weight::from_parts(
-    WEIGHT_REF_TIME_PER_SECOND.saturating_div(2),
+    WEIGHT_REF_TIME_PER_SECOND.saturating_mul(2),
     cumulus_primitives_core::relay_chain::MAX_POV_SIZE as u64,
 );
 
@@ -203,7 +203,7 @@ pub const MAXIMUM_BLOCK_LENGTH: u32 = 5 * 1024 * 1024;
 
 /// Maximum number of blocks simultaneously accepted by the Runtime, not yet included
 /// into the relay chain.
-const UNINCLUDED_SEGMENT_CAPACITY: u32 = 1;
+const UNINCLUDED_SEGMENT_CAPACITY: u32 = 3;
 /// How many parachain blocks are processed by the relay chain per parent. Limits the
 /// number of blocks authored per slot.
 const BLOCK_PROCESSING_VELOCITY: u32 = 1;
@@ -362,28 +362,39 @@ parameter_types! {
     // it would require 5298 full blocks to grow back to one.
     pub MinimumMultiplierBlockSize: Multiplier = Multiplier::saturating_from_rational(1, 200u128);
     pub MaximumMultiplierBlockSize: Multiplier = Bounded::max_value();
-
-    // TODO: Change SkippedBlocksNumberTerms to 5
-    // and keep the expected maximum skipped blocks at half a day,
-    // which is 7200 blocks when updating to asynchronous backing
-    // https://github.com/thrumdev/blobs/issues/166
-    // The accepted error is less than 10^(-2) for an expected
-    // maximum of 3600 skipped blocks (half a day)
-    pub SkippedBlocksNumberTerms: u32 = 3;
-
-    // Maximum acceptable number of skipped parachain blocks.
-    pub MaximumSkippedBlocks: u32 = ikura_primitives::MAX_SKIPPED_BLOCKS;
+    // The relay chain blocks between the relay-parents of two consecutive parachain blocks,
+    // one parachain slot apart.
+    pub const RelayBlocksPerParachainBlock: u32 =
+        (MILLISECS_PER_BLOCK / RELAY_CHAIN_SLOT_DURATION_MILLIS as u64) as u32;
 }
 
 impl pallet_ikura_length_fee_adjustment::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type UpdateOrigin = EnsureRoot<AccountId>;
+    type Currency = Balances;
+    type WeightInfo = pallet_ikura_length_fee_adjustment::weights::SubstrateWeight<Runtime>;
     type MaximumBlockLength = MaximumBlockLength;
     type TransactionByteFee = TransactionByteFee;
     type AdjustmentVariableBlockSize = AdjustmentVariableBlockSize;
     type MaximumMultiplierBlockSize = MaximumMultiplierBlockSize;
     type MinimumMultiplierBlockSize = MinimumMultiplierBlockSize;
-    type SkippedBlocksNumberTerms = SkippedBlocksNumberTerms;
-    type MaximumSkippedBlocks = MaximumSkippedBlocks;
     type LastRelayBlockNumberProvider = Runtime;
+    type RelayBlocksPerParachainBlock = RelayBlocksPerParachainBlock;
+    type ExcludedLength = BlobLength;
+}
+
+/// The blob data is priced by the blob fee of `pallet_ikura_blobs`, so it is not charged the
+/// length fee and it is excluded from the block size that drives the length fee multiplier.
+pub struct BlobLength;
+impl pallet_ikura_length_fee_adjustment::ExcludedLength<RuntimeCall> for BlobLength {
+    fn excluded_length(call: &RuntimeCall) -> u32 {
+        match call {
+            RuntimeCall::Blobs(pallet_ikura_blobs::Call::submit_blob { blob, .. }) => {
+                blob.len() as u32
+            }
+            _ => 0,
+        }
+    }
 }
 
 pub type SlowAdjustingFeeUpdate<R> = TargetedFeeAdjustment<
@@ -396,7 +407,12 @@ pub type SlowAdjustingFeeUpdate<R> = TargetedFeeAdjustment<
 
 impl pallet_transaction_payment::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
-    type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, ()>;
+    // The adjusted length fee is burned by the signed extension of LengthFeeAdjustment,
+    // the rest of the fee and the tip go to the collator.
+    type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<
+        Balances,
+        polkadot_runtime_common::impls::ToAuthor<Runtime>,
+    >;
     type WeightToFee = WeightToFee;
     type LengthToFee = LengthFeeAdjustment;
     type FeeMultiplierUpdate = SlowAdjustingFeeUpdate<Self>;
@@ -425,15 +441,19 @@ impl cumulus_pallet_parachain_system::Config for Runtime {
     type ReservedDmpWeight = ReservedDmpWeight;
     type XcmpMessageHandler = XcmpQueue;
     type ReservedXcmpWeight = ReservedXcmpWeight;
-    type CheckAssociatedRelayNumber = RelayNumberStrictlyIncreases;
-    type ConsensusHook = cumulus_pallet_aura_ext::FixedVelocityConsensusHook<
-        Runtime,
-        RELAY_CHAIN_SLOT_DURATION_MILLIS,
-        BLOCK_PROCESSING_VELOCITY,
-        UNINCLUDED_SEGMENT_CAPACITY,
-    >;
+    type CheckAssociatedRelayNumber = RelayNumberMonotonicallyIncreases;
+    type ConsensusHook = ConsensusHook;
 }
 
+/// Allows up to `UNINCLUDED_SEGMENT_CAPACITY` blocks to be built ahead of their inclusion in the
+/// relay chain, as the lookahead collator does.
+type ConsensusHook = cumulus_pallet_aura_ext::FixedVelocityConsensusHook<
+    Runtime,
+    RELAY_CHAIN_SLOT_DURATION_MILLIS,
+    BLOCK_PROCESSING_VELOCITY,
+    UNINCLUDED_SEGMENT_CAPACITY,
+>;
+
 impl parachain_info::Config for Runtime {}
 
 parameter_types! {
@@ -538,6 +558,15 @@ parameter_types! {
     pub const MaxBlobs: u32 = 100 * 1024;
     pub const MaxBlobSize: u32 = 100 * 1024;
     pub const MaxTotalBlobSize: u32 = 2 * 1024 * 1024;
+
+    pub const BlobByteFee: Balance = 10 * MICROUNIT;
+    pub TargetTotalBlobSize: Perquintill = Perquintill::from_percent(50);
+    //  v = p / k * (1 - s*) = 0.3 / (300 * (1 - 0.5))
+    //  at most 30% (=p) fees variation in one hour, 300 blocks (=k)
+    pub AdjustmentVariableBlobSize: Multiplier = Multiplier::saturating_from_rational(1, 500);
+    pub MinimumMultiplierBlobSize: Multiplier = Multiplier::saturating_from_rational(1, 10u128);
+    pub MaximumMultiplierBlobSize: Multiplier = Bounded::max_value();
+    pub const BlobLongevity: u64 = 10 * MINUTES as u64;
 }
 
 impl pallet_ikura_blobs::Config for Runtime {
@@ -545,6 +574,15 @@ impl pallet_ikura_blobs::Config for Runtime {
     type MaxBlobs = MaxBlobs;
     type MaxBlobSize = MaxBlobSize;
     type MaxTotalBlobSize = MaxTotalBlobSize;
+    type Currency = Balances;
+    // burn the blob fees, as the transaction fees
+    type OnBlobFee = ();
+    type BlobByteFee = BlobByteFee;
+    type TargetTotalBlobSize = TargetTotalBlobSize;
+    type AdjustmentVariableBlobSize = AdjustmentVariableBlobSize;
+    type MinimumMultiplierBlobSize = MinimumMultiplierBlobSize;
+    type MaximumMultiplierBlobSize = MaximumMultiplierBlobSize;
+    type BlobLongevity = BlobLongevity;
     type WeightInfo = pallet_ikura_blobs::weights::SubstrateWeight<Runtime>;
 }
 
@@ -594,6 +632,7 @@ mod benches {
         [pallet_collator_selection, CollatorSelection]
         [cumulus_pallet_xcmp_queue, XcmpQueue]
         [pallet_ikura_blobs, Blobs]
+        [pallet_ikura_length_fee_adjustment, LengthFeeAdjustment]
     );
 }
 
@@ -608,6 +647,15 @@ impl_runtime_apis! {
         }
     }
 
+    impl cumulus_primitives_aura::AuraUnincludedSegmentApi<Block> for Runtime {
+        fn can_build_upon(
+            included_hash: <Block as BlockT>::Hash,
+            slot: cumulus_primitives_aura::Slot,
+        ) -> bool {
+            ConsensusHook::can_build_upon(included_hash, slot)
+        }
+    }
+
     impl sp_api::Core<Block> for Runtime {
         fn version() -> RuntimeVersion {
             VERSION
@@ -696,12 +744,14 @@ impl_runtime_apis! {
             uxt: <Block as BlockT>::Extrinsic,
             len: u32,
         ) -> pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo<Balance> {
+            let len = LengthFeeAdjustment::fee_length(&uxt.function, len);
             TransactionPayment::query_info(uxt, len)
         }
         fn query_fee_details(
             uxt: <Block as BlockT>::Extrinsic,
             len: u32,
         ) -> pallet_transaction_payment::FeeDetails<Balance> {
+            let len = LengthFeeAdjustment::fee_length(&uxt.function, len);
             TransactionPayment::query_fee_details(uxt, len)
         }
         fn query_weight_to_fee(weight: Weight) -> Balance {
@@ -712,6 +762,37 @@ impl_runtime_apis! {
         }
     }
 
+    impl ikura_primitives::runtime_api::BaseFeeApi<Block, Balance> for Runtime {
+        fn next_base_fee(length: u32, relay_parent_number: u32) -> Balance {
+            LengthFeeAdjustment::next_base_fee(length, relay_parent_number)
+        }
+    }
+
+    impl ikura_primitives::runtime_api::BlobsApi<Block> for Runtime {
+        fn blob_size(extrinsic: <Block as BlockT>::Extrinsic) -> Option<u32> {
+            match extrinsic.function {
+                RuntimeCall::Blobs(pallet_ikura_blobs::Call::submit_blob { blob, .. }) => {
+                    Some(blob.len() as u32)
+                }
+                _ => None,
+            }
+        }
+
+        fn blob_limits() -> (u32, u32) {
+            (MaxBlobs::get(), MaxTotalBlobSize::get())
+        }
+
+        fn submitted_blobs(block: Block) -> Vec<(u128, Vec<u8>)> {
+            let (header, extrinsics) = block.deconstruct();
+            Executive::initialize_block(&header);
+            for extrinsic in extrinsics {
+                // The failed extrinsics do not store any blob.
+                let _ = Executive::apply_extrinsic(extrinsic);
+            }
+            Blobs::submitted_blobs()
+        }
+    }
+
     impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentCallApi<Block, Balance, RuntimeCall>
         for Runtime
     {
@@ -719,12 +800,14 @@ impl_runtime_apis! {
             call: RuntimeCall,
             len: u32,
         ) -> pallet_transaction_payment::RuntimeDispatchInfo<Balance> {
+            let len = LengthFeeAdjustment::fee_length(&call, len);
             TransactionPayment::query_call_info(call, len)
         }
         fn query_call_fee_details(
             call: RuntimeCall,
             len: u32,
         ) -> pallet_transaction_payment::FeeDetails<Balance> {
+            let len = LengthFeeAdjustment::fee_length(&call, len);
             TransactionPayment::query_call_fee_details(call, len)
         }
         fn query_weight_to_fee(weight: Weight) -> Balance {
@@ -0,0 +1,242 @@
+//! The erasure-coded data square over the blobs of a block.
+//!
+//! The blob data, in the order of the leaves of the namespaced merkle tree, is concatenated and
+//! split into shares of [`SHARE_SIZE`] bytes, the last one padded with zeroes. The shares are laid
+//! out row by row in the smallest `k * k` square fitting them, the remaining shares being zeroes.
+//!
+//! The square is then extended to `2k * 2k` with the Reed-Solomon code of [`crate::erasure`]: each
+//! row of the original square is extended to the right, then each of the `2k` columns is
+//! extended downwards. Any `k` shares of a row or of a column of the extended square determine
+//! the whole row or column, so that withholding any data requires withholding more than a
+//! quarter of the extended square, which random sampling detects with high probability.
+//!
+//! The square is committed to by the merkle roots of its rows and columns, and by the
+//! [`DataSquareRoot`] over those, which is deposited in the header of the block. A [`Sample`]
+//! proves one share of the extended square against the latter.
+
+use crate::{
+    erasure::{Encoder, MAX_CODEWORD_LEN},
+    merkle::{leaf_hash, merkle_proof, merkle_root, root_from_proof},
+};
+use alloc::{vec, vec::Vec};
+
+/// The size of a share of the data square, in bytes.
+pub const SHARE_SIZE: usize = 512;
+
+/// The maximum width of the original data square. The extended square is twice as wide, which
+/// is the longest codeword of the Reed-Solomon code over GF(2^8).
+pub const MAX_ORIGINAL_WIDTH: usize = MAX_CODEWORD_LEN / 2;
+
+/// The maximum number of bytes of blob data fitting in a data square.
+pub const MAX_DATA_SIZE: usize = MAX_ORIGINAL_WIDTH * MAX_ORIGINAL_WIDTH * SHARE_SIZE;
+
+/// The commitment to a data square, as deposited in the header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataSquareRoot {
+    /// The width `k` of the original data square. The extended one is `2k` wide.
+    pub original_width: u32,
+    /// The merkle root over the roots of the `2k` rows followed by the `2k` columns.
+    #[cfg_attr(feature = "serde", serde(with = "ikura_serde_util::bytes32_hex"))]
+    pub root: [u8; 32],
+}
+
+impl DataSquareRoot {
+    pub fn from_raw_bytes(raw: &[u8; 36]) -> Self {
+        let mut original_width = [0u8; 4];
+        original_width.copy_from_slice(&raw[0..4]);
+
+        let mut root = [0u8; 32];
+        root.copy_from_slice(&raw[4..36]);
+
+        Self {
+            original_width: u32::from_le_bytes(original_width),
+            root,
+        }
+    }
+
+    pub fn to_raw_bytes(&self) -> [u8; 36] {
+        let mut raw = [0u8; 36];
+        raw[0..4].copy_from_slice(&self.original_width.to_le_bytes());
+        raw[4..36].copy_from_slice(&self.root);
+        raw
+    }
+
+    /// The width of the extended data square.
+    pub fn extended_width(&self) -> usize {
+        2 * self.original_width as usize
+    }
+}
+
+/// An extended data square. See module docs.
+pub struct DataSquare {
+    /// The width of the original square.
+    k: usize,
+    /// The `2k * 2k` shares of the extended square, row by row.
+    shares: Vec<Vec<u8>>,
+    row_roots: Vec<[u8; 32]>,
+    col_roots: Vec<[u8; 32]>,
+}
+
+impl DataSquare {
+    /// Lays out and extends the given blob data, in order.
+    ///
+    /// Panics if the data exceeds [`MAX_DATA_SIZE`].
+    pub fn new<'a>(blobs: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let data: Vec<u8> = blobs.into_iter().flatten().copied().collect();
+        assert!(
+            data.len() <= MAX_DATA_SIZE,
+            "blob data exceeds the data square"
+        );
+
+        let n_shares = data.len().div_ceil(SHARE_SIZE);
+        let mut k = 1;
+        while k * k < n_shares {
+            k += 1;
+        }
+        let width = 2 * k;
+
+        let mut shares = vec![vec![0u8; SHARE_SIZE]; width * width];
+        for (i, chunk) in data.chunks(SHARE_SIZE).enumerate() {
+            shares[(i / k) * width + i % k][..chunk.len()].copy_from_slice(chunk);
+        }
+
+        let encoder = Encoder::new(k);
+        for row in 0..k {
+            let parity = encoder.encode(&shares[row * width..row * width + k]);
+            for (col, share) in (k..width).zip(parity) {
+                shares[row * width + col] = share;
+            }
+        }
+        for col in 0..width {
+            let column: Vec<&Vec<u8>> = (0..k).map(|row| &shares[row * width + col]).collect();
+            let parity = encoder.encode(&column);
+            for (row, share) in (k..width).zip(parity) {
+                shares[row * width + col] = share;
+            }
+        }
+
+        let row_roots = (0..width)
+            .map(|row| merkle_root(&shares[row * width..(row + 1) * width]))
+            .collect();
+        let col_roots = (0..width)
+            .map(|col| {
+                let column: Vec<&Vec<u8>> =
+                    (0..width).map(|row| &shares[row * width + col]).collect();
+                merkle_root(&column)
+            })
+            .collect();
+
+        Self {
+            k,
+            shares,
+            row_roots,
+            col_roots,
+        }
+    }
+
+    /// The width of the original square.
+    pub fn original_width(&self) -> usize {
+        self.k
+    }
+
+    /// The width of the extended square.
+    pub fn extended_width(&self) -> usize {
+        2 * self.k
+    }
+
+    /// The share at the given coordinates of the extended square.
+    pub fn share(&self, row: usize, col: usize) -> &[u8] {
+        &self.shares[row * self.extended_width() + col]
+    }
+
+    pub fn row_roots(&self) -> &[[u8; 32]] {
+        &self.row_roots
+    }
+
+    pub fn col_roots(&self) -> &[[u8; 32]] {
+        &self.col_roots
+    }
+
+    pub fn root(&self) -> DataSquareRoot {
+        DataSquareRoot {
+            original_width: self.k as u32,
+            root: merkle_root(&self.axis_roots()),
+        }
+    }
+
+    /// Proves the share at the given coordinates of the extended square.
+    ///
+    /// Returns `None` if the coordinates are out of the square.
+    pub fn sample(&self, row: usize, col: usize) -> Option<Sample> {
+        let width = self.extended_width();
+        if row >= width || col >= width {
+            return None;
+        }
+        let row_shares = &self.shares[row * width..(row + 1) * width];
+        Some(Sample {
+            row: row as u32,
+            col: col as u32,
+            share: self.share(row, col).to_vec(),
+            row_proof: merkle_proof(row_shares, col),
+            row_root: self.row_roots[row],
+            axis_proof: merkle_proof(&self.axis_roots(), row),
+        })
+    }
+
+    fn axis_roots(&self) -> Vec<[u8; 32]> {
+        self.row_roots
+            .iter()
+            .chain(&self.col_roots)
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErr {
+    OutOfSquare,
+    MalformedShare,
+    RowProof,
+    AxisProof,
+}
+
+/// A share of an extended data square, together with its proof against the [`DataSquareRoot`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample {
+    pub row: u32,
+    pub col: u32,
+    #[cfg_attr(feature = "serde", serde(with = "ikura_serde_util::bytes_hex"))]
+    pub share: Vec<u8>,
+    /// The proof of the share against the root of its row.
+    pub row_proof: Vec<[u8; 32]>,
+    #[cfg_attr(feature = "serde", serde(with = "ikura_serde_util::bytes32_hex"))]
+    pub row_root: [u8; 32],
+    /// The proof of the root of the row against the data square root.
+    pub axis_proof: Vec<[u8; 32]>,
+}
+
+impl Sample {
+    pub fn verify(&self, root: &DataSquareRoot) -> Result<(), VerifyErr> {
+        let width = root.extended_width();
+        let (row, col) = (self.row as usize, self.col as usize);
+        if row >= width || col >= width {
+            return Err(VerifyErr::OutOfSquare);
+        }
+        if self.share.len() != SHARE_SIZE {
+            return Err(VerifyErr::MalformedShare);
+        }
+        if root_from_proof(leaf_hash(&self.share), col, width, &self.row_proof)
+            != Some(self.row_root)
+        {
+            return Err(VerifyErr::RowProof);
+        }
+        if root_from_proof(leaf_hash(&self.row_root), row, 2 * width, &self.axis_proof)
+            != Some(root.root)
+        {
+            return Err(VerifyErr::AxisProof);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Systematic Reed-Solomon code over GF(2^8).
+//!
+//! A codeword of `2k` symbols is the evaluation of the polynomial of degree less than `k` going
+//! through the `k` data symbols at the points `0..k`, at the points `0..2k`. The first half of a
+//! codeword is thus the data itself, and the second half is the parity. Any `k` symbols of a
+//! codeword are enough to recover the others.
+//!
+//! The encoding is applied bytewise to shares: the `i`-th byte of the parity shares is the
+//! encoding of the `i`-th bytes of the data shares.
+
+use alloc::{vec, vec::Vec};
+
+/// The field has 256 elements, so a codeword has at most 256 symbols.
+pub const MAX_CODEWORD_LEN: usize = 256;
+
+/// The primitive polynomial x^8 + x^4 + x^3 + x^2 + 1 defining the field.
+const PRIMITIVE_POLY: u16 = 0x11d;
+
+/// EXP[i] is g^i for the generator g = 2. Doubled in length so that the sum of two logarithms
+/// does not need to be reduced modulo 255.
+const EXP: [u8; 512] = {
+    let mut exp = [0u8; 512];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= PRIMITIVE_POLY;
+        }
+        i += 1;
+    }
+    exp
+};
+
+/// LOG[x] is the discrete logarithm of x in base g. LOG[0] is meaningless.
+const LOG: [u8; 256] = {
+    let mut log = [0u8; 256];
+    let mut i = 0;
+    while i < 255 {
+        log[EXP[i] as usize] = i as u8;
+        i += 1;
+    }
+    log
+};
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+fn inv(a: u8) -> u8 {
+    debug_assert!(a != 0);
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+/// The encoder of the codewords of `2k` symbols.
+pub struct Encoder {
+    k: usize,
+    /// `coefficients[j][i]` is the Lagrange basis polynomial of the data point `i` evaluated at
+    /// the parity point `k + j`, so that `parity[j] = sum_i coefficients[j][i] * data[i]`.
+    coefficients: Vec<Vec<u8>>,
+}
+
+impl Encoder {
+    /// Creates the encoder of `k` data symbols into `2k` symbols.
+    ///
+    /// Panics if `k` is zero or `2k` exceeds [`MAX_CODEWORD_LEN`].
+    pub fn new(k: usize) -> Self {
+        assert!(k > 0 && 2 * k <= MAX_CODEWORD_LEN);
+        // The points are the field elements 0..2k, where addition and subtraction are XOR.
+        let coefficients = (k..2 * k)
+            .map(|x| {
+                (0..k)
+                    .map(|i| {
+                        let (mut num, mut den) = (1, 1);
+                        for m in (0..k).filter(|&m| m != i) {
+                            num = mul(num, (x ^ m) as u8);
+                            den = mul(den, (i ^ m) as u8);
+                        }
+                        mul(num, inv(den))
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { k, coefficients }
+    }
+
+    /// Computes the `k` parity shares of the given `k` data shares, all of the same length.
+    pub fn encode<S: AsRef<[u8]>>(&self, data: &[S]) -> Vec<Vec<u8>> {
+        assert_eq!(data.len(), self.k);
+        let share_len = data[0].as_ref().len();
+        self.coefficients
+            .iter()
+            .map(|row| {
+                let mut parity = vec![0u8; share_len];
+                for (&c, share) in row.iter().zip(data) {
+                    if c == 0 {
+                        continue;
+                    }
+                    let mul_c: [u8; 256] = core::array::from_fn(|b| mul(c, b as u8));
+                    for (p, &b) in parity.iter_mut().zip(share.as_ref()) {
+                        *p ^= mul_c[b as usize];
+                    }
+                }
+                parity
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates the polynomial through the given points at `x`.
+    fn interpolate(points: &[(u8, u8)], x: u8) -> u8 {
+        let mut y = 0;
+        for (i, &(xi, yi)) in points.iter().enumerate() {
+            let (mut num, mut den) = (1, 1);
+            for (m, &(xm, _)) in points.iter().enumerate() {
+                if m != i {
+                    num = mul(num, x ^ xm);
+                    den = mul(den, xi ^ xm);
+                }
+            }
+            y ^= mul(yi, mul(num, inv(den)));
+        }
+        y
+    }
+
+    #[test]
+    fn field_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn any_k_symbols_recover_the_codeword() {
+        let k = 5;
+        let data: Vec<[u8; 1]> = (0..k).map(|i| [(i * 37 + 11) as u8]).collect();
+        let parity = Encoder::new(k).encode(&data);
+        let codeword: Vec<u8> = data
+            .iter()
+            .map(|s| s[0])
+            .chain(parity.iter().map(|s| s[0]))
+            .collect();
+
+        // Keep the last k symbols, i.e. mostly parity, and recover the data from them.
+        let points: Vec<(u8, u8)> = (k..2 * k).map(|x| (x as u8, codeword[x])).collect();
+        for x in 0..2 * k {
+            assert_eq!(interpolate(&points, x as u8), codeword[x]);
+        }
+    }
+}
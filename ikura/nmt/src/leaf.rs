@@ -1,13 +1,73 @@
+use crate::share::ShareCommitment;
+use alloc::vec::Vec;
+
+/// The version of the leaves of a namespaced merkle tree.
+///
+/// All the leaves of a tree are of the same version, which is told apart by the tag of the digest
+/// holding the root of the tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LeafVersion {
+    /// 68-byte leaves committing to the hash of the blob.
+    #[default]
+    V0,
+    /// 104-byte leaves additionally committing to the shares of the blob.
+    V1,
+}
+
+impl LeafVersion {
+    /// The tag of the digest holding the root of a tree with leaves of this version.
+    pub fn digest_tag(self) -> &'static [u8; 4] {
+        match self {
+            LeafVersion::V0 => b"snmt",
+            LeafVersion::V1 => b"snm1",
+        }
+    }
+
+    /// The version of the leaves of the tree whose root is held by a digest with the given tag.
+    ///
+    /// Returns `None` if the tag is not the one of any version, e.g. the digest does not hold a
+    /// tree root.
+    pub fn from_digest_tag(tag: &[u8]) -> Option<Self> {
+        [LeafVersion::V0, LeafVersion::V1]
+            .into_iter()
+            .find(|version| &version.digest_tag()[..] == tag)
+    }
+
+    /// The length of the leaves of this version, in bytes.
+    pub fn leaf_len(self) -> usize {
+        match self {
+            LeafVersion::V0 => 68,
+            LeafVersion::V1 => 104,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct NmtLeaf {
     pub extrinsic_index: u32,
     pub who: [u8; 32],
     pub blob_hash: [u8; 32],
+    /// The commitment to the shares of the blob. Only present in version 1 leaves.
+    pub shares: Option<ShareCommitment>,
 }
 
 impl NmtLeaf {
-    /// Read the NMT leaf from the given raw bytes.
-    pub fn from_raw_bytes(raw: &[u8; 68]) -> Self {
+    pub fn version(&self) -> LeafVersion {
+        match self.shares {
+            None => LeafVersion::V0,
+            Some(_) => LeafVersion::V1,
+        }
+    }
+
+    /// Read the NMT leaf of the given version from the given raw bytes.
+    ///
+    /// Returns `None` if the length of the raw bytes does not match the version.
+    pub fn from_raw_bytes(raw: &[u8], version: LeafVersion) -> Option<Self> {
+        if raw.len() != version.leaf_len() {
+            return None;
+        }
+
         let mut extrinsic_index = [0u8; 4];
         extrinsic_index.copy_from_slice(&raw[0..4]);
         let extrinsic_index = u32::from_le_bytes(extrinsic_index);
@@ -18,19 +78,40 @@ impl NmtLeaf {
         let mut blob_hash = [0u8; 32];
         blob_hash.copy_from_slice(&raw[36..68]);
 
-        Self {
+        let shares = match version {
+            LeafVersion::V0 => None,
+            LeafVersion::V1 => {
+                let mut blob_len = [0u8; 4];
+                blob_len.copy_from_slice(&raw[68..72]);
+
+                let mut share_root = [0u8; 32];
+                share_root.copy_from_slice(&raw[72..104]);
+
+                Some(ShareCommitment {
+                    blob_len: u32::from_le_bytes(blob_len),
+                    share_root,
+                })
+            }
+        };
+
+        Some(Self {
             extrinsic_index,
             who,
             blob_hash,
-        }
+            shares,
+        })
     }
 
     /// Convert the NMT leaf to raw bytes.
-    pub fn to_raw_bytes(&self) -> [u8; 68] {
-        let mut raw = [0u8; 68];
-        raw[0..4].copy_from_slice(&self.extrinsic_index.to_le_bytes());
-        raw[4..36].copy_from_slice(&self.who);
-        raw[36..68].copy_from_slice(&self.blob_hash);
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.version().leaf_len());
+        raw.extend_from_slice(&self.extrinsic_index.to_le_bytes());
+        raw.extend_from_slice(&self.who);
+        raw.extend_from_slice(&self.blob_hash);
+        if let Some(shares) = &self.shares {
+            raw.extend_from_slice(&shares.blob_len.to_le_bytes());
+            raw.extend_from_slice(&shares.share_root);
+        }
         raw
     }
 }
@@ -7,36 +7,61 @@ extern crate alloc;
 pub const NS_ID_SIZE: usize = 16;
 
 mod blob_metadata;
+mod data_square;
+mod erasure;
 mod leaf;
+mod merkle;
 mod ns;
 mod ns_proof;
 mod root;
+mod share;
 mod tree;
 
 #[cfg(test)]
 mod tests;
 
 pub use blob_metadata::BlobMetadata;
-pub use leaf::NmtLeaf;
+pub use data_square::{
+    DataSquare, DataSquareRoot, Sample, MAX_DATA_SIZE, MAX_ORIGINAL_WIDTH, SHARE_SIZE,
+};
+pub use leaf::{LeafVersion, NmtLeaf};
 pub use ns::Namespace;
 pub use ns_proof::NamespaceProof;
 pub use root::TreeRoot;
+pub use share::{ShareCommitment, ShareRangeProof};
 pub use tree::{PushLeafErr, TreeBuilder};
 
 use alloc::vec::Vec;
 
-/// Creates a namespaced merkle tree from the list of blob metadata.
-pub fn tree_from_blobs(mut blob_metadata: Vec<BlobMetadata>) -> TreeBuilder {
+/// Creates a namespaced merkle tree with leaves of the given version from the list of blob
+/// metadata.
+///
+/// Panics if the leaves of the blob metadata are not of the given version.
+pub fn tree_from_blobs(
+    mut blob_metadata: Vec<BlobMetadata>,
+    leaf_version: LeafVersion,
+) -> TreeBuilder {
     blob_metadata.sort_by_key(|blob| blob.namespace);
 
-    let mut tree = TreeBuilder::new();
+    let mut tree = TreeBuilder::with_leaf_version(leaf_version);
     for blob in blob_metadata {
         match tree.push_leaf(blob.namespace, blob.leaf) {
             Ok(()) => (),
             Err(PushLeafErr::AscendingOrder) => {
                 panic!("sorted by namespace, so this should not happen")
             }
+            Err(PushLeafErr::LeafVersion) => {
+                panic!("the leaf is not of version {:?}", leaf_version)
+            }
         }
     }
     tree
 }
+
+/// Creates the extended data square from the blobs, given with their namespaces.
+///
+/// The blobs are laid out in the order of the leaves of the tree built by [`tree_from_blobs`].
+pub fn data_square_from_blobs(mut blobs: Vec<(Namespace, &[u8])>) -> DataSquare {
+    blobs.sort_by_key(|(namespace, _)| *namespace);
+    DataSquare::new(blobs.into_iter().map(|(_, data)| data))
+}
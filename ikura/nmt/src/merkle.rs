@@ -0,0 +1,137 @@
+//! Binary merkle trees over byte strings.
+//!
+//! The trees follow RFC 6962, with domain-separated leaves and inner nodes, and the left subtree
+//! of a node holding the largest power of two of the leaves smaller than their count.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use sha2::{Digest, Sha256};
+
+pub fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The number of leaves in the left subtree of a node with `n > 1` leaves.
+fn split_point(n: usize) -> usize {
+    debug_assert!(n > 1);
+    1 << (usize::BITS - (n - 1).leading_zeros() - 1)
+}
+
+pub fn merkle_root<L: AsRef<[u8]>>(leaves: &[L]) -> [u8; 32] {
+    match leaves.len() {
+        0 => Sha256::digest(b"").into(),
+        1 => leaf_hash(leaves[0].as_ref()),
+        n => {
+            let (left, right) = leaves.split_at(split_point(n));
+            node_hash(&merkle_root(left), &merkle_root(right))
+        }
+    }
+}
+
+/// The sibling hashes on the path from the leaf at `index` to the root, from the bottom up.
+pub fn merkle_proof<L: AsRef<[u8]>>(leaves: &[L], index: usize) -> Vec<[u8; 32]> {
+    if leaves.len() <= 1 {
+        return Vec::new();
+    }
+    let (left, right) = leaves.split_at(split_point(leaves.len()));
+    let (mut proof, sibling) = if index < left.len() {
+        (merkle_proof(left, index), merkle_root(right))
+    } else {
+        (merkle_proof(right, index - left.len()), merkle_root(left))
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// Computes the root of a tree of `n` leaves from the leaf hash at `index` and its proof.
+pub fn root_from_proof(
+    leaf: [u8; 32],
+    index: usize,
+    n: usize,
+    proof: &[[u8; 32]],
+) -> Option<[u8; 32]> {
+    if n <= 1 {
+        return proof.is_empty().then_some(leaf);
+    }
+    let (sibling, rest) = proof.split_last()?;
+    let k = split_point(n);
+    if index < k {
+        Some(node_hash(&root_from_proof(leaf, index, k, rest)?, sibling))
+    } else {
+        Some(node_hash(
+            sibling,
+            &root_from_proof(leaf, index - k, n - k, rest)?,
+        ))
+    }
+}
+
+/// The roots of the maximal subtrees disjoint from the given non-empty range of leaves, from left
+/// to right.
+pub fn range_proof<L: AsRef<[u8]>>(leaves: &[L], range: Range<usize>) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    collect_range_proof(leaves, 0, &range, &mut proof);
+    proof
+}
+
+fn collect_range_proof<L: AsRef<[u8]>>(
+    leaves: &[L],
+    offset: usize,
+    range: &Range<usize>,
+    proof: &mut Vec<[u8; 32]>,
+) {
+    if offset + leaves.len() <= range.start || range.end <= offset {
+        proof.push(merkle_root(leaves));
+    } else if leaves.len() > 1 {
+        let (left, right) = leaves.split_at(split_point(leaves.len()));
+        collect_range_proof(left, offset, range, proof);
+        collect_range_proof(right, offset + left.len(), range, proof);
+    }
+}
+
+/// Computes the root of a tree of `n` leaves from the leaves in the given non-empty range and its
+/// proof. Returns `None` if the amount of leaves or of proof nodes does not match.
+pub fn root_from_range_proof<L: AsRef<[u8]>>(
+    leaves: &[L],
+    range: Range<usize>,
+    n: usize,
+    proof: &[[u8; 32]],
+) -> Option<[u8; 32]> {
+    if range.is_empty() || range.end > n || leaves.len() != range.len() {
+        return None;
+    }
+    let mut leaves = leaves.iter();
+    let mut proof = proof.iter();
+    let root = compute_range_root(n, 0, &range, &mut leaves, &mut proof)?;
+    // Every proof node must be used.
+    proof.next().is_none().then_some(root)
+}
+
+fn compute_range_root<'a, L: AsRef<[u8]> + 'a>(
+    n: usize,
+    offset: usize,
+    range: &Range<usize>,
+    leaves: &mut impl Iterator<Item = &'a L>,
+    proof: &mut impl Iterator<Item = &'a [u8; 32]>,
+) -> Option<[u8; 32]> {
+    if offset + n <= range.start || range.end <= offset {
+        return proof.next().copied();
+    }
+    if n == 1 {
+        return Some(leaf_hash(leaves.next()?.as_ref()));
+    }
+    let k = split_point(n);
+    let left = compute_range_root(k, offset, range, leaves, proof)?;
+    let right = compute_range_root(n - k, offset + k, range, leaves, proof)?;
+    Some(node_hash(&left, &right))
+}
@@ -26,6 +26,25 @@ impl NamespaceProof {
         if blob_hashes.len() != self.leaves.len() {
             return Err(VerifyErr::BlobCountMismatch);
         }
+        let leaves = self.verify_leaves(&root, namespace)?;
+        for (i, leaf) in leaves.iter().enumerate() {
+            if leaf.blob_hash != blob_hashes[i] {
+                return Err(VerifyErr::BlobHashMismatch(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies the proof and returns the leaves of all of the blobs of the namespace.
+    ///
+    /// The leaves are of the version of the root. Those of version 1 commit to the shares of the
+    /// blobs, against which a [`crate::ShareRangeProof`] verifies a part of a blob.
+    pub fn verify_leaves(
+        &self,
+        root: &TreeRoot,
+        namespace: Namespace,
+    ) -> Result<Vec<NmtLeaf>, VerifyErr> {
+        let leaf_version = root.leaf_version;
         let root = nmt_rs::NamespacedHash::<NS_ID_SIZE>::new(
             root.min_ns.nmt_namespace_id(),
             root.max_ns.nmt_namespace_id(),
@@ -34,17 +53,12 @@ impl NamespaceProof {
         self.proof
             .verify_complete_namespace(&root, &self.leaves, namespace.nmt_namespace_id())
             .map_err(|_| VerifyErr::VerifyProof)?;
-        for (i, leaf) in self.leaves.iter().enumerate() {
-            if leaf.len() != 68 {
-                return Err(VerifyErr::MalformedLeaf(i));
-            }
-            let leaf = NmtLeaf::from_raw_bytes(
-                leaf.as_slice().try_into().expect("verified to be 68 bytes"),
-            );
-            if leaf.blob_hash != blob_hashes[i] {
-                return Err(VerifyErr::BlobHashMismatch(i));
-            }
-        }
-        Ok(())
+        self.leaves
+            .iter()
+            .enumerate()
+            .map(|(i, leaf)| {
+                NmtLeaf::from_raw_bytes(leaf, leaf_version).ok_or(VerifyErr::MalformedLeaf(i))
+            })
+            .collect()
     }
 }
@@ -1,4 +1,6 @@
 pub use crate::ns::Namespace;
+use crate::LeafVersion;
+use alloc::vec::Vec;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -7,10 +9,13 @@ pub struct TreeRoot {
     pub root: [u8; 32],
     pub min_ns: Namespace,
     pub max_ns: Namespace,
+    /// The version of the leaves of the tree. Not part of the raw bytes, but of the digest tag.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leaf_version: LeafVersion,
 }
 
 impl TreeRoot {
-    pub fn from_raw_bytes(raw: &[u8; 68]) -> Self {
+    pub fn from_raw_bytes(raw: &[u8; 68], leaf_version: LeafVersion) -> Self {
         let mut root = [0u8; 32];
         root.copy_from_slice(&raw[0..32]);
 
@@ -21,6 +26,7 @@ impl TreeRoot {
             root,
             min_ns,
             max_ns,
+            leaf_version,
         }
     }
 
@@ -31,4 +37,25 @@ impl TreeRoot {
         raw[48..64].copy_from_slice(&self.max_ns.to_raw_bytes());
         raw
     }
+
+    /// Read the tree root from the content of a header digest item, tagged with the version of
+    /// the leaves.
+    ///
+    /// Returns `None` if the tag is unknown or the root is malformed.
+    pub fn from_digest(digest: &[u8]) -> Option<Self> {
+        if digest.len() < 4 {
+            return None;
+        }
+        let leaf_version = LeafVersion::from_digest_tag(&digest[0..4])?;
+        let raw: &[u8; 68] = digest[4..].try_into().ok()?;
+        Some(Self::from_raw_bytes(raw, leaf_version))
+    }
+
+    /// The content of the header digest item holding the tree root.
+    pub fn to_digest(&self) -> Vec<u8> {
+        let mut digest = Vec::with_capacity(4 + 68);
+        digest.extend_from_slice(self.leaf_version.digest_tag());
+        digest.extend_from_slice(&self.to_raw_bytes());
+        digest
+    }
 }
@@ -0,0 +1,107 @@
+//! Commitments to the shares of a blob.
+//!
+//! A blob is split into shares of [`SHARE_SIZE`] bytes, the last one possibly shorter, and the
+//! version 1 leaves of the namespaced merkle tree commit to the merkle root over those shares.
+//! A contiguous range of shares can then be proven on its own, without the rest of the blob.
+
+use crate::{
+    merkle::{merkle_root, range_proof, root_from_range_proof},
+    SHARE_SIZE,
+};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// The commitment to the shares of a blob.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShareCommitment {
+    /// The length of the blob, in bytes.
+    pub blob_len: u32,
+    /// The merkle root over the shares of the blob.
+    #[cfg_attr(feature = "serde", serde(with = "ikura_serde_util::bytes32_hex"))]
+    pub share_root: [u8; 32],
+}
+
+impl ShareCommitment {
+    /// Commits to the shares of the given blob.
+    pub fn new(blob: &[u8]) -> Self {
+        let shares: Vec<&[u8]> = blob.chunks(SHARE_SIZE).collect();
+        Self {
+            blob_len: blob.len() as u32,
+            share_root: merkle_root(&shares),
+        }
+    }
+
+    /// The number of shares of the blob.
+    pub fn share_count(&self) -> usize {
+        (self.blob_len as usize).div_ceil(SHARE_SIZE)
+    }
+
+    /// The range of bytes of the blob covered by the given range of shares.
+    pub fn byte_range(&self, shares: Range<usize>) -> Range<usize> {
+        let blob_len = self.blob_len as usize;
+        (shares.start * SHARE_SIZE).min(blob_len)..(shares.end * SHARE_SIZE).min(blob_len)
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErr {
+    EmptyRange,
+    OutOfBlob,
+    DataLengthMismatch,
+    VerifyProof,
+}
+
+/// The proof of a contiguous range of shares of a blob against its [`ShareCommitment`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShareRangeProof {
+    /// The index of the first share of the range.
+    pub start: u32,
+    /// The index past the last share of the range.
+    pub end: u32,
+    /// The roots of the subtrees of the shares outside of the range, from left to right.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl ShareRangeProof {
+    /// Proves the given non-empty range of shares of the blob.
+    ///
+    /// Returns `None` if the range is empty or exceeds the shares of the blob.
+    pub fn new(blob: &[u8], shares: Range<usize>) -> Option<Self> {
+        let all_shares: Vec<&[u8]> = blob.chunks(SHARE_SIZE).collect();
+        if shares.is_empty() || shares.end > all_shares.len() {
+            return None;
+        }
+        Some(Self {
+            start: shares.start as u32,
+            end: shares.end as u32,
+            siblings: range_proof(&all_shares, shares),
+        })
+    }
+
+    /// The range of shares proven.
+    pub fn shares(&self) -> Range<usize> {
+        self.start as usize..self.end as usize
+    }
+
+    /// Verifies that `data` is the content of the proven range of shares of the blob, i.e. the
+    /// bytes of the blob in [`ShareCommitment::byte_range`].
+    pub fn verify(&self, data: &[u8], commitment: &ShareCommitment) -> Result<(), VerifyErr> {
+        let shares = self.shares();
+        if shares.is_empty() {
+            return Err(VerifyErr::EmptyRange);
+        }
+        if shares.end > commitment.share_count() {
+            return Err(VerifyErr::OutOfBlob);
+        }
+        if data.len() != commitment.byte_range(shares.clone()).len() {
+            return Err(VerifyErr::DataLengthMismatch);
+        }
+        let leaves: Vec<&[u8]> = data.chunks(SHARE_SIZE).collect();
+        match root_from_range_proof(&leaves, shares, commitment.share_count(), &self.siblings) {
+            Some(root) if root == commitment.share_root => Ok(()),
+            _ => Err(VerifyErr::VerifyProof),
+        }
+    }
+}
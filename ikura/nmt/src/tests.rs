@@ -1,5 +1,8 @@
-use crate::{tree_from_blobs, BlobMetadata, Namespace, NmtLeaf, TreeBuilder};
-use alloc::vec::Vec;
+use crate::{
+    data_square_from_blobs, tree_from_blobs, BlobMetadata, DataSquare, DataSquareRoot, LeafVersion,
+    Namespace, NmtLeaf, ShareCommitment, ShareRangeProof, TreeBuilder, TreeRoot, SHARE_SIZE,
+};
+use alloc::{vec, vec::Vec};
 
 struct MockBuilder {
     blobs: Vec<BlobMetadata>,
@@ -18,12 +21,13 @@ impl MockBuilder {
                 extrinsic_index: index as u32,
                 who,
                 blob_hash,
+                shares: None,
             },
         });
     }
 
     fn tree(&self) -> TreeBuilder {
-        tree_from_blobs(self.blobs.clone())
+        tree_from_blobs(self.blobs.clone(), LeafVersion::V0)
     }
 }
 
@@ -82,3 +86,198 @@ fn wrong_namespace_id() {
         .verify(&[], tree.root(), Namespace::from_u128_be(1))
         .is_err());
 }
+
+/// Pseudo-random blob data, so that the shares differ from each other.
+fn blob(len: usize, seed: u32) -> Vec<u8> {
+    let mut x = seed.wrapping_add(1);
+    (0..len)
+        .map(|_| {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            (x >> 16) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn data_square_width() {
+    assert_eq!(DataSquare::new([]).original_width(), 1);
+    assert_eq!(DataSquare::new([&[1u8][..]]).original_width(), 1);
+    let data = blob(4 * SHARE_SIZE + 1, 0);
+    assert_eq!(DataSquare::new([&data[..]]).original_width(), 3);
+}
+
+#[test]
+fn data_square_extension_is_consistent() {
+    // The parity rows extended to the right match the parity computed from the columns.
+    let data = blob(7 * SHARE_SIZE + 100, 1);
+    let square = DataSquare::new([&data[..]]);
+    let k = square.original_width();
+    let width = square.extended_width();
+    let encoder = crate::erasure::Encoder::new(k);
+    for row in 0..width {
+        let left: Vec<&[u8]> = (0..k).map(|col| square.share(row, col)).collect();
+        let right: Vec<&[u8]> = (k..width).map(|col| square.share(row, col)).collect();
+        assert_eq!(encoder.encode(&left), right);
+    }
+}
+
+#[test]
+fn data_square_samples() {
+    let (a, b) = (blob(1000, 2), blob(3000, 3));
+    let square = data_square_from_blobs(vec![
+        (Namespace::from_u128_be(2), &a[..]),
+        (Namespace::from_u128_be(1), &b[..]),
+    ]);
+    // The blobs are laid out in namespace order.
+    assert_eq!(square.share(0, 0), &b[..SHARE_SIZE]);
+
+    let root = square.root();
+    let width = square.extended_width();
+    for row in 0..width {
+        for col in 0..width {
+            let sample = square.sample(row, col).unwrap();
+            assert!(sample.verify(&root).is_ok());
+        }
+    }
+    assert!(square.sample(width, 0).is_none());
+
+    let mut sample = square.sample(1, 2).unwrap();
+    sample.share[0] ^= 1;
+    assert!(sample.verify(&root).is_err());
+
+    let mut sample = square.sample(1, 2).unwrap();
+    sample.col = 3;
+    assert!(sample.verify(&root).is_err());
+
+    let raw = root.to_raw_bytes();
+    assert_eq!(DataSquareRoot::from_raw_bytes(&raw), root);
+}
+
+fn v1_blob_metadata(extrinsic_index: u32, namespace: u128, blob: &[u8]) -> BlobMetadata {
+    use sha2::Digest;
+    BlobMetadata {
+        namespace: Namespace::from_u128_be(namespace),
+        leaf: NmtLeaf {
+            extrinsic_index,
+            who: [extrinsic_index as u8; 32],
+            blob_hash: sha2::Sha256::digest(blob).into(),
+            shares: Some(ShareCommitment::new(blob)),
+        },
+    }
+}
+
+#[test]
+fn leaf_raw_bytes_round_trip() {
+    let v0 = NmtLeaf {
+        extrinsic_index: 7,
+        who: [1; 32],
+        blob_hash: [2; 32],
+        shares: None,
+    };
+    let raw = v0.to_raw_bytes();
+    assert_eq!(raw.len(), 68);
+    assert!(NmtLeaf::from_raw_bytes(&raw, LeafVersion::V1).is_none());
+    let decoded = NmtLeaf::from_raw_bytes(&raw, LeafVersion::V0).unwrap();
+    assert_eq!(decoded.to_raw_bytes(), raw);
+
+    let v1 = v1_blob_metadata(7, 1, &blob(1000, 0)).leaf;
+    let raw = v1.to_raw_bytes();
+    assert_eq!(raw.len(), 104);
+    assert!(NmtLeaf::from_raw_bytes(&raw, LeafVersion::V0).is_none());
+    let decoded = NmtLeaf::from_raw_bytes(&raw, LeafVersion::V1).unwrap();
+    assert_eq!(decoded.shares, v1.shares);
+    assert_eq!(decoded.to_raw_bytes(), raw);
+}
+
+#[test]
+fn root_digest_tagged_with_leaf_version() {
+    let v0 = TreeBuilder::new().root();
+    let digest = v0.to_digest();
+    assert!(digest.starts_with(b"snmt"));
+    assert_eq!(digest[4..], v0.to_raw_bytes());
+    assert_eq!(TreeRoot::from_digest(&digest), Some(v0));
+
+    let v1 = TreeBuilder::with_leaf_version(LeafVersion::V1).root();
+    let digest = v1.to_digest();
+    assert!(digest.starts_with(b"snm1"));
+    assert_eq!(TreeRoot::from_digest(&digest), Some(v1));
+
+    assert_eq!(TreeRoot::from_digest(b"sdsq"), None);
+}
+
+#[test]
+fn mixed_leaf_versions_rejected() {
+    let mut tree = TreeBuilder::with_leaf_version(LeafVersion::V1);
+    let leaf = NmtLeaf {
+        extrinsic_index: 0,
+        who: [1; 32],
+        blob_hash: [2; 32],
+        shares: None,
+    };
+    assert!(tree.push_leaf(Namespace::from_u128_be(1), leaf).is_err());
+}
+
+#[test]
+fn v1_namespace_proof_and_share_range() {
+    let (a, b, c) = (blob(3 * SHARE_SIZE + 10, 1), blob(100, 2), blob(5, 3));
+    let mut tree = tree_from_blobs(
+        vec![
+            v1_blob_metadata(0, 2, &a),
+            v1_blob_metadata(1, 1, &b),
+            v1_blob_metadata(2, 2, &c),
+        ],
+        LeafVersion::V1,
+    );
+    let root = tree.root();
+    let namespace = Namespace::from_u128_be(2);
+    let proof = tree.proof(namespace);
+
+    let leaves = proof.verify_leaves(&root, namespace).unwrap();
+    assert_eq!(leaves.len(), 2);
+    let commitment = leaves[0].shares.clone().unwrap();
+    assert_eq!(commitment.share_count(), 4);
+
+    // Only the middle shares of the first blob are retrieved.
+    let range_proof = ShareRangeProof::new(&a, 1..3).unwrap();
+    let data = &a[commitment.byte_range(1..3)];
+    assert!(range_proof.verify(data, &commitment).is_ok());
+
+    let mut tampered = data.to_vec();
+    tampered[SHARE_SIZE] ^= 1;
+    assert!(range_proof.verify(&tampered, &commitment).is_err());
+    assert!(range_proof.verify(&data[1..], &commitment).is_err());
+
+    // The same proof does not hold against another blob.
+    let other = leaves[1].shares.clone().unwrap();
+    assert!(range_proof.verify(data, &other).is_err());
+
+    // The hashes of the blobs are still checked against the version 1 leaves.
+    let hashes = [leaves[0].blob_hash, leaves[1].blob_hash];
+    assert!(proof.verify(&hashes, root, namespace).is_ok());
+}
+
+#[test]
+fn share_range_proofs() {
+    for len in [1, SHARE_SIZE, 5 * SHARE_SIZE + 1, 8 * SHARE_SIZE] {
+        let data = blob(len, len as u32);
+        let commitment = ShareCommitment::new(&data);
+        let n = commitment.share_count();
+        for start in 0..n {
+            for end in start + 1..=n {
+                let proof = ShareRangeProof::new(&data, start..end).unwrap();
+                let bytes = &data[commitment.byte_range(start..end)];
+                assert!(proof.verify(bytes, &commitment).is_ok());
+            }
+        }
+        assert!(ShareRangeProof::new(&data, 0..0).is_none());
+        assert!(ShareRangeProof::new(&data, 0..n + 1).is_none());
+
+        // A proof with a missing sibling is rejected.
+        let mut proof = ShareRangeProof::new(&data, 0..1).unwrap();
+        if proof.siblings.pop().is_some() {
+            assert!(proof
+                .verify(&data[commitment.byte_range(0..1)], &commitment)
+                .is_err());
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{leaf::NmtLeaf, ns::Namespace, ns_proof::NamespaceProof, root::TreeRoot, NS_ID_SIZE};
+use crate::{
+    leaf::{LeafVersion, NmtLeaf},
+    ns::Namespace,
+    ns_proof::NamespaceProof,
+    root::TreeRoot,
+    NS_ID_SIZE,
+};
 
 use nmt_rs::{simple_merkle::db::MemDb, NamespaceMerkleTree, NamespacedHash, NamespacedSha2Hasher};
 
@@ -6,6 +12,8 @@ use nmt_rs::{simple_merkle::db::MemDb, NamespaceMerkleTree, NamespacedHash, Name
 pub enum PushLeafErr {
     /// The namespace is not in ascending order.
     AscendingOrder,
+    /// The leaf is not of the version of the tree.
+    LeafVersion,
 }
 
 pub struct TreeBuilder {
@@ -15,13 +23,20 @@ pub struct TreeBuilder {
         NS_ID_SIZE,
     >,
     last_namespace: Namespace,
+    leaf_version: LeafVersion,
 }
 
 impl TreeBuilder {
     pub fn new() -> Self {
+        Self::with_leaf_version(LeafVersion::V0)
+    }
+
+    /// Creates a tree with leaves of the given version.
+    pub fn with_leaf_version(leaf_version: LeafVersion) -> Self {
         Self {
             tree: NamespaceMerkleTree::new(),
             last_namespace: Namespace::from_u128_be(0),
+            leaf_version,
         }
     }
 
@@ -33,6 +48,9 @@ impl TreeBuilder {
         if namespace < self.last_namespace {
             return Err(PushLeafErr::AscendingOrder);
         }
+        if nmt_leaf.version() != self.leaf_version {
+            return Err(PushLeafErr::LeafVersion);
+        }
         self.last_namespace = namespace;
         let leaf = nmt_leaf.to_raw_bytes();
         self.tree
@@ -50,6 +68,7 @@ impl TreeBuilder {
             root: root.hash(),
             min_ns,
             max_ns,
+            leaf_version: self.leaf_version,
         }
     }
 
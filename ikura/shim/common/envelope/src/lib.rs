@@ -0,0 +1,389 @@
+//! The envelope format of the blobs submitted by the shim.
+//!
+//! Blob bytes are paid for per byte, and rollup batches usually compress well. The shim may
+//! therefore compress a blob before submitting it and wrap the result into an envelope: a header
+//! followed by the payload.
+//!
+//! ```text
+//! +-------+---------+-------+-------------+---------+
+//! | magic | version | codec | decoded len | payload |
+//! |  4 B  |   1 B   |  1 B  |  4 B (BE)   |   ...   |
+//! +-------+---------+-------+-------------+---------+
+//! ```
+//!
+//! A blob not starting with the magic is not in an envelope and is taken as is. This keeps the
+//! blobs submitted before the envelope was introduced, or by the submitters not using it, readable.
+//! A blob that happens to start with the magic is always put into an envelope, even if it does
+//! not compress.
+//!
+//! The payload may also be encrypted with XChaCha20-Poly1305, so that only the holders of the key
+//! can read the blob. The payload of an encrypted envelope is the 24-byte nonce followed by the
+//! ciphertext, and the plaintext is itself a blob that may be in an envelope, e.g. compressed. The
+//! header and the caller-provided associated data, e.g. the namespace, are authenticated along
+//! with the ciphertext.
+//!
+//! The decoding is implemented in pure Rust and does not need `std`, so that it can be performed
+//! inside of a zkVM.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{borrow::Cow, vec::Vec};
+
+/// The magic bytes the envelope starts with.
+pub const MAGIC: [u8; 4] = *b"ikev";
+
+/// The current version of the envelope format.
+pub const VERSION: u8 = 1;
+
+/// The length of the envelope header.
+pub const HEADER_LEN: usize = 10;
+
+/// The maximum length of the decoded payload. Limits the memory a malicious blob can make the
+/// decoder allocate.
+pub const MAX_DECODED_LEN: u32 = 16 * 1024 * 1024;
+
+/// The encoding of the payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Codec {
+    /// The payload is stored as is.
+    None = 0,
+    /// The payload is a zstd frame.
+    Zstd = 1,
+    /// The payload is encrypted with XChaCha20-Poly1305.
+    XChaCha20Poly1305 = 2,
+}
+
+impl Codec {
+    fn from_u8(byte: u8) -> Option<Codec> {
+        match byte {
+            0 => Some(Codec::None),
+            1 => Some(Codec::Zstd),
+            2 => Some(Codec::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+/// The reason a blob could not be put into or taken out of an envelope.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The envelope was created by a newer version of the format.
+    UnsupportedVersion(u8),
+    /// The codec is not known.
+    UnknownCodec(u8),
+    /// The envelope is too short to contain the header.
+    Truncated,
+    /// The payload could not be decoded.
+    Malformed,
+    /// The decoded payload does not have the length declared in the header.
+    LengthMismatch { declared: u32 },
+    /// The blob is longer than [`MAX_DECODED_LEN`].
+    TooLarge,
+    /// The compression failed.
+    Compression,
+    /// The blob is encrypted and must be decrypted with [`decrypt`] first.
+    Encrypted,
+    /// The decryption failed: the key is wrong or the blob was tampered with.
+    Decryption,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported envelope version {}", version)
+            }
+            Error::UnknownCodec(codec) => write!(f, "unknown envelope codec {}", codec),
+            Error::Truncated => write!(f, "truncated envelope header"),
+            Error::Malformed => write!(f, "malformed envelope payload"),
+            Error::LengthMismatch { declared } => write!(
+                f,
+                "the envelope payload does not decode to the declared {} bytes",
+                declared
+            ),
+            Error::TooLarge => write!(f, "the blob exceeds {} bytes", MAX_DECODED_LEN),
+            Error::Compression => write!(f, "compression failed"),
+            Error::Encrypted => write!(f, "the blob is encrypted"),
+            Error::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+/// The header of an envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub codec: Codec,
+    /// The length of the decoded payload.
+    pub decoded_len: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = self.version;
+        header[5] = self.codec as u8;
+        header[6..].copy_from_slice(&self.decoded_len.to_be_bytes());
+        header
+    }
+
+    /// Parses the header of the given blob. Returns `Ok(None)` if the blob is not in an envelope.
+    pub fn decode(blob: &[u8]) -> Result<Option<Header>, Error> {
+        if !blob.starts_with(&MAGIC) {
+            return Ok(None);
+        }
+        if blob.len() < HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+        let version = blob[4];
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let codec = Codec::from_u8(blob[5]).ok_or(Error::UnknownCodec(blob[5]))?;
+        // unwrap: the slice is 4 bytes long.
+        let decoded_len = u32::from_be_bytes(blob[6..HEADER_LEN].try_into().unwrap());
+        if decoded_len > MAX_DECODED_LEN {
+            return Err(Error::TooLarge);
+        }
+        Ok(Some(Header {
+            version,
+            codec,
+            decoded_len,
+        }))
+    }
+}
+
+/// Compresses the blob with zstd at the given level and puts it into an envelope.
+///
+/// If the compression does not make the blob smaller, the blob is returned as is, unless it starts
+/// with the [`MAGIC`], in which case it is put into an envelope without compression.
+#[cfg(feature = "compress")]
+pub fn compress(blob: &[u8], level: i32) -> Result<Vec<u8>, Error> {
+    let decoded_len = u32::try_from(blob.len())
+        .ok()
+        .filter(|len| *len <= MAX_DECODED_LEN)
+        .ok_or(Error::TooLarge)?;
+    let compressed = zstd::bulk::compress(blob, level).map_err(|_| Error::Compression)?;
+    let (codec, payload) = if HEADER_LEN + compressed.len() < blob.len() {
+        (Codec::Zstd, &compressed[..])
+    } else if blob.starts_with(&MAGIC) {
+        (Codec::None, blob)
+    } else {
+        return Ok(blob.to_vec());
+    };
+    let header = Header {
+        version: VERSION,
+        codec,
+        decoded_len,
+    };
+    let mut enveloped = Vec::with_capacity(HEADER_LEN + payload.len());
+    enveloped.extend_from_slice(&header.encode());
+    enveloped.extend_from_slice(payload);
+    Ok(enveloped)
+}
+
+/// The length of the XChaCha20-Poly1305 nonce.
+pub const NONCE_LEN: usize = 24;
+
+/// Encrypts the blob with the given key and puts it into an envelope. The nonce must never be
+/// reused with the same key, which is practically guaranteed if it is random.
+///
+/// The `associated_data` is not stored in the envelope, but the same data must be provided to
+/// [`decrypt`].
+#[cfg(feature = "encryption")]
+pub fn encrypt(
+    blob: &[u8],
+    key: &[u8; 32],
+    nonce: [u8; NONCE_LEN],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    use chacha20poly1305::{
+        aead::{Aead, KeyInit, Payload},
+        XChaCha20Poly1305, XNonce,
+    };
+    let decoded_len = u32::try_from(blob.len())
+        .ok()
+        .filter(|len| *len <= MAX_DECODED_LEN)
+        .ok_or(Error::TooLarge)?;
+    let header = Header {
+        version: VERSION,
+        codec: Codec::XChaCha20Poly1305,
+        decoded_len,
+    }
+    .encode();
+    let aad = [&header[..], associated_data].concat();
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: blob,
+                aad: &aad,
+            },
+        )
+        // The encryption only fails if the blob exceeds the limit of the cipher.
+        .map_err(|_| Error::TooLarge)?;
+    let mut enveloped = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len());
+    enveloped.extend_from_slice(&header);
+    enveloped.extend_from_slice(&nonce);
+    enveloped.extend_from_slice(&ciphertext);
+    Ok(enveloped)
+}
+
+/// Decrypts the blob in an encrypted envelope with the given key. The result may itself be in an
+/// envelope, see [`open`]. A blob not in an encrypted envelope is returned as is.
+#[cfg(feature = "encryption")]
+pub fn decrypt<'a>(
+    blob: &'a [u8],
+    key: &[u8; 32],
+    associated_data: &[u8],
+) -> Result<Cow<'a, [u8]>, Error> {
+    use chacha20poly1305::{
+        aead::{Aead, KeyInit, Payload},
+        XChaCha20Poly1305, XNonce,
+    };
+    match Header::decode(blob)? {
+        Some(Header {
+            codec: Codec::XChaCha20Poly1305,
+            decoded_len,
+            ..
+        }) => {
+            let (header, payload) = blob.split_at(HEADER_LEN);
+            if payload.len() < NONCE_LEN {
+                return Err(Error::Truncated);
+            }
+            let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+            let aad = [header, associated_data].concat();
+            let plaintext = XChaCha20Poly1305::new(key.into())
+                .decrypt(
+                    XNonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| Error::Decryption)?;
+            if plaintext.len() != decoded_len as usize {
+                return Err(Error::LengthMismatch {
+                    declared: decoded_len,
+                });
+            }
+            Ok(Cow::Owned(plaintext))
+        }
+        _ => Ok(Cow::Borrowed(blob)),
+    }
+}
+
+/// Takes the blob out of its envelope, decompressing it if needed. A blob not in an envelope is
+/// returned as is. An encrypted blob must be decrypted with [`decrypt`] first.
+pub fn open(blob: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    let Some(header) = Header::decode(blob)? else {
+        return Ok(Cow::Borrowed(blob));
+    };
+    let payload = &blob[HEADER_LEN..];
+    let length_mismatch = Error::LengthMismatch {
+        declared: header.decoded_len,
+    };
+    match header.codec {
+        Codec::None => {
+            if payload.len() != header.decoded_len as usize {
+                return Err(length_mismatch);
+            }
+            Ok(Cow::Borrowed(payload))
+        }
+        Codec::Zstd => {
+            use ruzstd::io::Read as _;
+            let mut decoder =
+                ruzstd::StreamingDecoder::new(payload).map_err(|_| Error::Malformed)?;
+            let mut decoded = Vec::with_capacity(header.decoded_len as usize);
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = decoder.read(&mut buf).map_err(|_| Error::Malformed)?;
+                if n == 0 {
+                    break;
+                }
+                // Stop early instead of decoding a bomb to the end.
+                if decoded.len() + n > header.decoded_len as usize {
+                    return Err(length_mismatch);
+                }
+                decoded.extend_from_slice(&buf[..n]);
+            }
+            if decoded.len() != header.decoded_len as usize {
+                return Err(length_mismatch);
+            }
+            Ok(Cow::Owned(decoded))
+        }
+        Codec::XChaCha20Poly1305 => Err(Error::Encrypted),
+    }
+}
+
+#[cfg(all(test, feature = "compress"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn compressible_round_trip() {
+        let blob = vec![42u8; 10_000];
+        let enveloped = compress(&blob, 3).unwrap();
+        assert!(enveloped.len() < blob.len());
+        assert_eq!(
+            Header::decode(&enveloped).unwrap().unwrap().codec,
+            Codec::Zstd
+        );
+        assert_eq!(open(&enveloped).unwrap(), &blob[..]);
+    }
+
+    #[test]
+    fn incompressible_stays_raw() {
+        let blob = b"short".to_vec();
+        assert_eq!(compress(&blob, 3).unwrap(), blob);
+        assert_eq!(open(&blob).unwrap(), &blob[..]);
+    }
+
+    #[test]
+    fn magic_is_escaped() {
+        let blob = b"ikev, but not an envelope".to_vec();
+        let enveloped = compress(&blob, 3).unwrap();
+        assert_eq!(
+            Header::decode(&enveloped).unwrap().unwrap().codec,
+            Codec::None
+        );
+        assert_eq!(open(&enveloped).unwrap(), &blob[..]);
+    }
+
+    #[test]
+    fn declared_length_is_enforced() {
+        let mut enveloped = compress(&vec![42u8; 10_000], 3).unwrap();
+        enveloped[6..HEADER_LEN].copy_from_slice(&100u32.to_be_bytes());
+        assert_eq!(
+            open(&enveloped),
+            Err(Error::LengthMismatch { declared: 100 })
+        );
+        assert_eq!(open(b"ikev\x02"), Err(Error::Truncated));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypted_round_trip() {
+        let blob = vec![42u8; 10_000];
+        let key = [7; 32];
+        let namespace = [1; 16];
+        let compressed = compress(&blob, 3).unwrap();
+        let encrypted = encrypt(&compressed, &key, [9; NONCE_LEN], &namespace).unwrap();
+        assert_eq!(open(&encrypted), Err(Error::Encrypted));
+
+        let decrypted = decrypt(&encrypted, &key, &namespace).unwrap();
+        assert_eq!(open(&decrypted).unwrap(), &blob[..]);
+
+        // Neither a wrong key nor a different namespace can decrypt the blob.
+        assert_eq!(
+            decrypt(&encrypted, &[8; 32], &namespace),
+            Err(Error::Decryption)
+        );
+        assert_eq!(decrypt(&encrypted, &key, &[2; 16]), Err(Error::Decryption));
+    }
+}
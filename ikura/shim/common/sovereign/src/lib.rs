@@ -36,10 +36,15 @@ pub trait SovereignRPC {
         namespace: ikura_nmt::Namespace,
     ) -> Result<Block, JsonRPCError>;
 
+    /// Submits the blob into the namespace.
+    ///
+    /// The `auth_token` is required only if the shim is configured to demand a token for the
+    /// namespace.
     #[method(name = "sovereign_submitBlob")]
     async fn submit_blob(
         &self,
         blob: Vec<u8>,
         namespace: ikura_nmt::Namespace,
+        auth_token: Option<String>,
     ) -> Result<(), JsonRPCError>;
 }
@@ -29,6 +29,18 @@ use clap::{Parser, Subcommand};
 const ENV_IKURA_SHIM_PORT: &str = "IKURA_SHIM_PORT";
 const ENV_IKURA_NAMESPACE: &str = "IKURA_NAMESPACE";
 const ENV_IKURA_NODE_URL: &str = "IKURA_NODE_URL";
+const ENV_IKURA_SHIM_API_TOKEN: &str = "IKURA_SHIM_API_TOKEN";
+pub const ENV_IKURA_SUBMIT_KEY: &str = "IKURA_SUBMIT_KEY";
+
+// The defaults of the parameters that can also be specified in the config file. Those parameters
+// are optional on the command line, so that it is possible to tell whether they were given or
+// should be taken from the config file.
+pub const DEFAULT_NODE_URL: &str = "ws://localhost:9988";
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1";
+pub const DEFAULT_SHIM_PORT: u16 = 10995;
+/// The port of the Rollkit dock when running several docks at once, so that it does not clash
+/// with the Sovereign SDK dock listening on [`DEFAULT_SHIM_PORT`].
+pub const DEFAULT_ROLLKIT_PORT: u16 = 10996;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -41,14 +53,14 @@ pub struct Cli {
 // TODO: for docks, this should not be required and for query submit it should
 // be. Unfortunately, clap doesn't support this easily so it is handled manually
 // within the command execution for submit.
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Default)]
 #[group(multiple = false)]
 pub struct KeyManagementParams {
     /// Use the Alice development key to sign blob transactions.
     ///
     /// This key is enabled when running ikura-node in the local development mode.
     ///
-    /// Cannot be used in conjunction with the `--submit-private-key` flag.
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
     #[arg(long)]
     pub submit_dev_alice: bool,
 
@@ -57,42 +69,212 @@ pub struct KeyManagementParams {
     /// The keyfile should be 32 bytes of unencrypted, hex-encoded sr25519
     /// seed material.
     ///
-    /// Cannot be used in conjunction with the `--submit-dev-alice` flag.
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
     #[arg(long, value_name = "PATH")]
     pub submit_private_key: Option<std::path::PathBuf>,
+
+    /// Use the secret URI in the file at the provided path to sign blob transactions.
+    ///
+    /// The secret URI is a BIP-39 mnemonic phrase, optionally followed by a derivation path and
+    /// a password, e.g. `<mnemonic>//rollup1` or `<mnemonic>//rollup1///password`. See
+    /// `ikura-shim key generate`.
+    ///
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
+    #[arg(long, value_name = "PATH")]
+    pub submit_mnemonic: Option<std::path::PathBuf>,
+
+    /// Use the JSON keystore at the provided path to sign blob transactions.
+    ///
+    /// The keystore is expected in the format exported by polkadot-js. The password is read from
+    /// the `IKURA_KEYSTORE_PASSWORD` environment variable or, if it is not set, prompted for.
+    ///
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
+    #[arg(long, value_name = "PATH")]
+    pub submit_keystore: Option<std::path::PathBuf>,
+
+    /// Use the secret URI found in the `IKURA_SUBMIT_KEY` environment variable to sign blob
+    /// transactions.
+    ///
+    /// The format is the same as for `--submit-mnemonic`.
+    ///
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
+    #[arg(long)]
+    pub submit_key_from_env: bool,
+
+    /// Use the pool of keys in the file at the provided path to sign blob transactions.
+    ///
+    /// The file contains one secret URI per line in the same format as for `--submit-mnemonic`.
+    /// Empty lines and lines starting with `#` are ignored. The submissions are spread across
+    /// the keys in a round-robin fashion, each key tracking its own nonce.
+    ///
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
+    #[arg(long, value_name = "PATH")]
+    pub submit_key_pool: Option<std::path::PathBuf>,
+
+    /// Delegate signing of blob transactions to the remote signer at the provided URL.
+    ///
+    /// The remote signer is an external process serving JSON-RPC over HTTP. All the keys it holds
+    /// are used as a pool, see `--submit-key-pool`. See `ikura-shim key serve-signer` for a local
+    /// stand-in.
+    ///
+    /// Cannot be used in conjunction with the other `--submit-*` key flags.
+    #[arg(long, value_name = "URL")]
+    pub submit_remote_signer: Option<String>,
+}
+
+impl KeyManagementParams {
+    /// Returns true if any of the key sources was specified.
+    pub fn is_specified(&self) -> bool {
+        self.submit_dev_alice
+            || self.submit_private_key.is_some()
+            || self.submit_mnemonic.is_some()
+            || self.submit_keystore.is_some()
+            || self.submit_key_from_env
+            || self.submit_key_pool.is_some()
+            || self.submit_remote_signer.is_some()
+    }
+}
+
+/// Common parameters for monitoring the balances of the submission keys.
+#[derive(clap::Args, Debug, Default)]
+pub struct BalanceMonitorParams {
+    /// Log a warning when the free balance of any of the submission keys drops below the given
+    /// amount, in the smallest units.
+    ///
+    /// The balances are always reported in the metrics. If not specified, no warnings are logged.
+    #[clap(long, value_name = "AMOUNT")]
+    pub balance_warn_threshold: Option<u128>,
 }
 
 /// Common parameters for the subcommands that run docks.
 #[derive(clap::Args, Debug)]
 pub struct DockParams {
     /// The address on which the shim should listen for incoming connections from the rollup nodes.
-    #[clap(short, long, default_value = "127.0.0.1", group = "listen")]
-    pub address: String,
+    ///
+    /// Defaults to 127.0.0.1.
+    #[clap(short, long, group = "listen")]
+    pub address: Option<String>,
 
     /// The port on which the shim should listen for incoming connections from the rollup nodes.
-    #[clap(
-        short,
-        long,
-        env = ENV_IKURA_SHIM_PORT,
-        default_value = "10995",
-        group = "listen"
-    )]
-    pub port: u16,
-    // TODO: e.g. prometheus stuff, enabled docks, etc.
+    ///
+    /// Defaults to 10995.
+    #[clap(short, long, env = ENV_IKURA_SHIM_PORT, group = "listen")]
+    pub port: Option<u16>,
+
+    #[clap(flatten)]
+    pub metrics: MetricsParams,
+
+    #[clap(flatten)]
+    pub transport: TransportParams,
+
+    #[clap(flatten)]
+    pub rate_limit: RateLimitParams,
+
+    #[clap(flatten)]
+    pub envelope: EnvelopeParams,
+}
+
+/// Common parameters for the envelopes the submitted blobs are put into.
+#[derive(clap::Args, Debug, Default)]
+pub struct EnvelopeParams {
+    /// Compress the submitted blobs with zstd at the given level, from 1 to 22.
+    ///
+    /// The compressed blobs are put into an envelope with a versioned header. The docks take the
+    /// retrieved blobs out of their envelopes regardless of this setting.
+    #[clap(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub compression_level: Option<i32>,
+}
+
+/// Common parameters for limiting the rate of submissions to the docks.
+///
+/// The submissions exceeding the limits are rejected. The limits are not enforced unless
+/// specified.
+#[derive(clap::Args, Debug, Default)]
+pub struct RateLimitParams {
+    /// The maximum number of blob bytes submitted into a single namespace per minute.
+    #[clap(long, value_name = "BYTES")]
+    pub namespace_bytes_per_minute: Option<u64>,
+
+    /// The maximum number of blobs submitted into a single namespace per block.
+    #[clap(long, value_name = "BLOBS")]
+    pub namespace_blobs_per_block: Option<u32>,
+
+    /// The maximum number of blob bytes submitted by a single client IP address per minute.
+    #[clap(long, value_name = "BYTES")]
+    pub client_bytes_per_minute: Option<u64>,
+
+    /// The maximum number of blobs submitted by a single client IP address per block.
+    #[clap(long, value_name = "BLOBS")]
+    pub client_blobs_per_block: Option<u32>,
+}
+
+/// Common parameters for securing the connections to the docks.
+#[derive(clap::Args, Debug, Default)]
+pub struct TransportParams {
+    /// Serve TLS using the PEM-encoded certificate chain at the given path.
+    ///
+    /// Requires `--tls-key`.
+    #[clap(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<std::path::PathBuf>,
+
+    /// The PEM-encoded private key for the certificate given by `--tls-cert`.
+    #[clap(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<std::path::PathBuf>,
+
+    /// Require the clients to present a certificate signed by one of the PEM-encoded CA
+    /// certificates at the given path (mutual TLS).
+    #[clap(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_client_ca: Option<std::path::PathBuf>,
+
+    /// Require the clients to present the given token in the `x-api-token` header (or the gRPC
+    /// metadata).
+    #[clap(long, env = ENV_IKURA_SHIM_API_TOKEN, hide_env_values = true)]
+    pub api_token: Option<String>,
+}
+
+/// Common parameters for the Prometheus metrics server.
+#[derive(clap::Args, Debug, Default)]
+pub struct MetricsParams {
+    /// The address on which the Prometheus metrics server should listen.
+    ///
+    /// Defaults to 127.0.0.1.
+    #[clap(long)]
+    pub metrics_address: Option<String>,
+
+    /// The port on which the Prometheus metrics server should listen.
+    ///
+    /// The metrics are served under `/metrics`. If not specified, the metrics server is not
+    /// started.
+    #[clap(long, value_name = "PORT")]
+    pub metrics_port: Option<u16>,
 }
 
 /// Common parameters for that commands that connect to the ikura-node.
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Default)]
 pub struct IkuraRpcParams {
     /// The address of the ikura-node to connect to.
-    #[clap(long, default_value = "ws://localhost:9988", env = ENV_IKURA_NODE_URL)]
-    pub node_url: String,
+    ///
+    /// Can be specified multiple times (or as a comma-separated list) to provide several nodes.
+    /// The shim connects to the node with the highest finalized block and fails over to the
+    /// others in case of connection errors.
+    ///
+    /// Defaults to ws://localhost:9988.
+    #[clap(long, env = ENV_IKURA_NODE_URL, value_delimiter = ',')]
+    pub node_url: Vec<String>,
+
+    /// When multiple nodes are specified, make sure that the selected node agrees with the other
+    /// nodes on the finalized blocks before connecting to it.
+    ///
+    /// Pass `--cross-check=false` to override the value in the config file.
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub cross_check: Option<bool>,
 
     /// By default the first connection to the node is retried until it is properly connected.
     ///
-    /// This flag avoids this behavior by attempting to connect only once
-    #[clap(long)]
-    pub no_retry: bool,
+    /// This flag avoids this behavior by attempting to connect only once. Pass `--no-retry=false`
+    /// to override the value in the config file.
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub no_retry: Option<bool>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -103,18 +285,36 @@ pub enum Commands {
     Simulate,
     /// Allows running queries locally. Useful for debugging.
     Query(query::Params),
+    /// Manages the configuration file.
+    Config(config::Params),
+    /// Manages the keys used for signing blob transactions.
+    Key(key::Params),
+    /// Verifies the artifacts produced by the queries offline.
+    Verify(verify::Params),
 }
 
 pub mod serve {
     //! CLI definition for the `serve` subcommand.
 
-    use super::{DockParams, IkuraRpcParams, KeyManagementParams, ENV_IKURA_NAMESPACE};
+    use super::{
+        BalanceMonitorParams, DockParams, EnvelopeParams, IkuraRpcParams, KeyManagementParams,
+        MetricsParams, RateLimitParams, TransportParams, ENV_IKURA_NAMESPACE,
+    };
     use clap::{Args, Subcommand};
 
     #[derive(Debug, Args)]
     pub struct Params {
+        /// Read the parameters from the given configuration file.
+        ///
+        /// The parameters passed on the command line take precedence over the values in the file.
+        /// If no dock is specified on the command line, the docks enabled in the file are run.
+        ///
+        /// See `ikura-shim config init` for the template.
+        #[arg(long, global = true, value_name = "PATH")]
+        pub config: Option<std::path::PathBuf>,
+
         #[command(subcommand)]
-        pub dock: Dock,
+        pub dock: Option<Dock>,
     }
 
     #[derive(Subcommand, Debug)]
@@ -123,12 +323,16 @@ pub mod serve {
         Sov(sov::Params),
         /// Serve requests of the Rollkit SDK rollups.
         Rollkit(rollkit::Params),
+        /// Serve requests of several kinds of rollups at once, each dock on its own port.
+        ///
+        /// All the docks share the connection to the ikura node and the submission keys.
+        Multi(multi::Params),
     }
 
     pub mod sov {
         //! CLI definition for the `serve sov` subcommand.
 
-        use super::{DockParams, IkuraRpcParams, KeyManagementParams};
+        use super::{BalanceMonitorParams, DockParams, IkuraRpcParams, KeyManagementParams};
         use clap::Args;
 
         #[derive(Debug, Args)]
@@ -141,13 +345,27 @@ pub mod serve {
 
             #[clap(flatten)]
             pub key_management: KeyManagementParams,
+
+            #[clap(flatten)]
+            pub balance_monitor: BalanceMonitorParams,
+
+            /// Only accept the submissions into the given namespaces.
+            ///
+            /// Can be specified multiple times (or as a comma-separated list). If not specified,
+            /// the submissions into any namespace are accepted. The tokens required for submitting
+            /// into particular namespaces can be set in the config file.
+            #[clap(long, value_name = "NAMESPACE", value_delimiter = ',')]
+            pub allow_namespace: Vec<String>,
         }
     }
 
     pub mod rollkit {
         //! CLI definition for the `serve rollkit` subcommand.
 
-        use super::{DockParams, IkuraRpcParams, KeyManagementParams, ENV_IKURA_NAMESPACE};
+        use super::{
+            BalanceMonitorParams, DockParams, IkuraRpcParams, KeyManagementParams,
+            ENV_IKURA_NAMESPACE,
+        };
         use clap::Args;
 
         #[derive(Debug, Args)]
@@ -161,6 +379,9 @@ pub mod serve {
             #[clap(flatten)]
             pub key_management: KeyManagementParams,
 
+            #[clap(flatten)]
+            pub balance_monitor: BalanceMonitorParams,
+
             /// The namespace to submit the blobs into.
             ///
             /// The namespace can be specified either as a 16-byte vector, or as an unsigned 128-bit
@@ -170,6 +391,76 @@ pub mod serve {
             pub namespace: Option<String>,
         }
     }
+
+    pub mod multi {
+        //! CLI definition for the `serve multi` subcommand.
+
+        use super::{
+            BalanceMonitorParams, EnvelopeParams, IkuraRpcParams, KeyManagementParams,
+            MetricsParams, RateLimitParams, TransportParams, ENV_IKURA_NAMESPACE,
+        };
+        use clap::Args;
+
+        #[derive(Debug, Args, Default)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub rpc: IkuraRpcParams,
+
+            #[clap(flatten)]
+            pub key_management: KeyManagementParams,
+
+            #[clap(flatten)]
+            pub balance_monitor: BalanceMonitorParams,
+
+            #[clap(flatten)]
+            pub metrics: MetricsParams,
+
+            // The TLS and API token settings are applied to all the docks.
+            #[clap(flatten)]
+            pub transport: TransportParams,
+
+            // The rate limits are applied to each of the docks separately.
+            #[clap(flatten)]
+            pub rate_limit: RateLimitParams,
+
+            #[clap(flatten)]
+            pub envelope: EnvelopeParams,
+
+            /// The address on which the docks should listen for incoming connections from the
+            /// rollup nodes.
+            ///
+            /// Defaults to 127.0.0.1.
+            #[clap(short, long)]
+            pub address: Option<String>,
+
+            /// The port on which the Sovereign SDK dock should listen.
+            ///
+            /// If not specified, the Sovereign SDK dock is not started.
+            #[clap(long, value_name = "PORT")]
+            pub sov_port: Option<u16>,
+
+            /// The port on which the Rollkit dock should listen.
+            ///
+            /// If not specified, the Rollkit dock is not started. If the dock is enabled in the
+            /// config file without a port, it listens on 10996.
+            #[clap(long, value_name = "PORT")]
+            pub rollkit_port: Option<u16>,
+
+            /// The namespace the Rollkit dock submits the blobs into.
+            ///
+            /// The namespace can be specified either as a 16-byte vector, or as an unsigned 128-bit
+            /// big-endian integer. To distinguish between the two, the byte vector must be prefixed
+            /// with `0x`.
+            #[clap(long, env = ENV_IKURA_NAMESPACE)]
+            pub rollkit_namespace: Option<String>,
+
+            /// Only accept the submissions into the given namespaces in the Sovereign SDK dock.
+            ///
+            /// See `serve sov --allow-namespace`.
+            #[clap(long, value_name = "NAMESPACE", value_delimiter = ',')]
+            pub sov_allow_namespace: Vec<String>,
+        }
+    }
 }
 
 pub mod query {
@@ -194,6 +485,18 @@ pub mod query {
         Block(block::Params),
         /// Queries information about a specific blob.
         Blob(blob::Params),
+        /// Lists the blobs submitted into a namespace within a range of finalized blocks.
+        Namespace(namespace::Params),
+        /// Produces a proof of the blobs submitted into a namespace in a block.
+        ///
+        /// The proof is printed as JSON and can be checked offline with `verify proof`.
+        Proof(proof::Params),
+        /// Checks the availability of the data of a block by sampling its erasure-coded data
+        /// square.
+        ///
+        /// Random shares of the extended data square are requested along with their proofs, which
+        /// are verified against the data square root in the block header.
+        Sample(sample::Params),
     }
 
     /// A reference to a block to query.
@@ -294,6 +597,12 @@ pub mod query {
             /// any other details intended for human consumption.
             #[arg(long)]
             pub raw: bool,
+
+            /// Decrypt the blob with the key from the given file, containing 32 hex-encoded bytes.
+            ///
+            /// The blob is also taken out of its envelope, e.g. decompressed.
+            #[arg(long, value_name = "PATH")]
+            pub decrypt_key: Option<std::path::PathBuf>,
         }
     }
 
@@ -318,6 +627,107 @@ pub mod query {
         }
     }
 
+    pub mod namespace {
+        //! CLI definition for the `query namespace` subcommand.
+
+        use std::path::PathBuf;
+
+        use clap::{Args, ValueEnum};
+
+        use super::IkuraRpcParams;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub rpc: IkuraRpcParams,
+
+            /// The namespace to list the blobs of.
+            ///
+            /// The namespace can be specified either as a 16-byte vector, or as an unsigned 128-bit
+            /// big-endian integer. To distinguish between the two, the byte vector must be prefixed
+            ///  with `0x`.
+            #[arg(value_name = "NAMESPACE")]
+            pub namespace: String,
+
+            /// The number of the first block to scan.
+            #[clap(long, default_value_t = 0)]
+            pub from: u64,
+
+            /// The number of the last block to scan, inclusive. Defaults to the last finalized
+            /// block.
+            ///
+            /// If the block is not finalized yet, the shim waits until it is.
+            #[clap(long)]
+            pub to: Option<u64>,
+
+            /// The format of the listing printed to stdout.
+            #[clap(long, value_enum, default_value_t = Format::Text)]
+            pub format: Format,
+
+            /// Save the data of every listed blob into the given directory, as
+            /// `<block number>-<extrinsic index>.bin`. The directory is created if needed.
+            #[clap(long, value_name = "PATH")]
+            pub download_dir: Option<PathBuf>,
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+        pub enum Format {
+            /// Human-readable text.
+            Text,
+            /// A JSON array with an object per blob.
+            Json,
+            /// CSV with a header row.
+            Csv,
+        }
+    }
+
+    pub mod proof {
+        //! CLI definition for the `query proof` subcommand.
+
+        use super::{BlockParams, IkuraRpcParams, ENV_IKURA_NAMESPACE};
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub rpc: IkuraRpcParams,
+
+            #[clap(flatten)]
+            pub block: BlockParams,
+
+            /// The namespace to prove the blobs of.
+            ///
+            /// The namespace can be specified either as a 16-byte vector, or as an unsigned 128-bit
+            /// big-endian integer. To distinguish between the two, the byte vector must be prefixed
+            ///  with `0x`.
+            #[clap(long, short, env = ENV_IKURA_NAMESPACE)]
+            pub namespace: String,
+        }
+    }
+
+    pub mod sample {
+        //! CLI definition for the `query sample` subcommand.
+
+        use super::{BlockParams, IkuraRpcParams};
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub rpc: IkuraRpcParams,
+
+            #[clap(flatten)]
+            pub block: BlockParams,
+
+            /// The number of distinct shares to sample.
+            ///
+            /// Making the data unrecoverable requires withholding more than a quarter of the
+            /// extended data square, so that each sample detects it with a probability above 1/4.
+            #[clap(long, default_value_t = 16)]
+            pub samples: u32,
+        }
+    }
+
     pub mod submit {
         //! CLI definition for the `query submit` subcommand.
 
@@ -345,3 +755,154 @@ pub mod query {
         }
     }
 }
+
+pub mod config {
+    //! CLI definition for the `config` subcommand.
+
+    use clap::{Args, Subcommand};
+
+    #[derive(Debug, Args)]
+    pub struct Params {
+        #[command(subcommand)]
+        pub command: Commands,
+    }
+
+    #[derive(Subcommand, Debug)]
+    pub enum Commands {
+        /// Writes a documented configuration file template.
+        Init(init::Params),
+    }
+
+    pub mod init {
+        //! CLI definition for the `config init` subcommand.
+
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            /// The path to write the template to. Pass `-` to write to stdout.
+            #[arg(value_name = "PATH", default_value = "ikura-shim.toml")]
+            pub path: String,
+
+            /// Overwrite the file if it already exists.
+            #[arg(long)]
+            pub force: bool,
+        }
+    }
+}
+
+pub mod verify {
+    //! CLI definition for the `verify` subcommand.
+
+    use clap::{Args, Subcommand};
+
+    #[derive(Debug, Args)]
+    pub struct Params {
+        #[command(subcommand)]
+        pub command: Commands,
+    }
+
+    #[derive(Subcommand, Debug)]
+    pub enum Commands {
+        /// Verifies a namespace proof produced by `query proof`.
+        ///
+        /// Checks that the header in the file hashes to the block hash and commits to the tree
+        /// root, and that the blob hashes in the file are exactly the blobs of the namespace under
+        /// that tree root. The block hash itself is taken from the file, so it must be checked
+        /// against a trusted source, e.g. the finalized chain, separately.
+        Proof(proof::Params),
+    }
+
+    pub mod proof {
+        //! CLI definition for the `verify proof` subcommand.
+
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            /// The path to the JSON file produced by `query proof`. Pass `-` to read from stdin.
+            #[arg(value_name = "PATH")]
+            pub path: String,
+        }
+    }
+}
+
+pub mod key {
+    //! CLI definition for the `key` subcommand.
+
+    use super::KeyManagementParams;
+    use clap::{Args, Subcommand};
+
+    #[derive(Debug, Args)]
+    pub struct Params {
+        #[command(subcommand)]
+        pub command: Commands,
+    }
+
+    #[derive(Subcommand, Debug)]
+    pub enum Commands {
+        /// Generates a new key from a random 24-word mnemonic phrase.
+        Generate(generate::Params),
+        /// Prints the public key and the address of the given key.
+        Inspect(inspect::Params),
+        /// Runs a remote signer holding the given keys.
+        ///
+        /// The keys are held in the memory of this process, so this is only a stand-in for an
+        /// external signer, useful for testing `--submit-remote-signer`.
+        ServeSigner(serve_signer::Params),
+    }
+
+    pub mod generate {
+        //! CLI definition for the `key generate` subcommand.
+
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            /// The derivation path appended to the mnemonic phrase, e.g. `//rollup1`.
+            #[arg(long, value_name = "PATH")]
+            pub derivation_path: Option<String>,
+
+            /// Write the secret URI into the given file instead of printing it to stdout.
+            ///
+            /// The file can then be used with `--submit-mnemonic`. The file is not overwritten if
+            /// it already exists.
+            #[arg(long, short, value_name = "FILE")]
+            pub output: Option<std::path::PathBuf>,
+        }
+    }
+
+    pub mod inspect {
+        //! CLI definition for the `key inspect` subcommand.
+
+        use super::KeyManagementParams;
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub key_management: KeyManagementParams,
+        }
+    }
+
+    pub mod serve_signer {
+        //! CLI definition for the `key serve-signer` subcommand.
+
+        use super::KeyManagementParams;
+        use clap::Args;
+
+        #[derive(Debug, Args)]
+        pub struct Params {
+            #[clap(flatten)]
+            pub key_management: KeyManagementParams,
+
+            /// The address on which the signer should listen.
+            #[clap(short, long, default_value = "127.0.0.1")]
+            pub address: String,
+
+            /// The port on which the signer should listen.
+            #[clap(short, long, default_value = "10997")]
+            pub port: u16,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::cli::config::{init, Commands, Params};
+use anyhow::Context as _;
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    match params.command {
+        Commands::Init(params) => run_init(params),
+    }
+}
+
+fn run_init(params: init::Params) -> anyhow::Result<()> {
+    let init::Params { path, force } = params;
+    if path == "-" {
+        print!("{}", crate::config::TEMPLATE);
+        return Ok(());
+    }
+    if !force && std::path::Path::new(&path).exists() {
+        anyhow::bail!("'{}' already exists. Pass --force to overwrite it.", path);
+    }
+    std::fs::write(&path, crate::config::TEMPLATE)
+        .with_context(|| format!("cannot write config file '{}'", path))?;
+    tracing::info!("wrote config file template to '{}'", path);
+    Ok(())
+}
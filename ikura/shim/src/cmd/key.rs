@@ -0,0 +1,91 @@
+use crate::cli::key::{generate, inspect, serve_signer, Commands, Params};
+use crate::key::{self, Keypair};
+use anyhow::Context as _;
+use tracing::info;
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    match params.command {
+        Commands::Generate(params) => run_generate(params),
+        Commands::Inspect(params) => run_inspect(params).await,
+        Commands::ServeSigner(params) => run_serve_signer(params).await,
+    }
+}
+
+fn run_generate(params: generate::Params) -> anyhow::Result<()> {
+    let generate::Params {
+        derivation_path,
+        output,
+    } = params;
+    let mnemonic = key::generate_mnemonic()?;
+    let uri = format!("{}{}", mnemonic, derivation_path.unwrap_or_default());
+    let keypair = Keypair::from_uri(&uri)?;
+
+    match output {
+        Some(path) => {
+            write_secret(&path, &uri)
+                .with_context(|| format!("cannot write the key to '{}'", path.display()))?;
+            println!("Secret URI written to {}", path.display());
+        }
+        None => println!("Secret URI: {}", uri),
+    }
+    println!("Public key: 0x{}", hex::encode(keypair.public_key()));
+    println!("Address: {}", keypair.address());
+    Ok(())
+}
+
+async fn run_inspect(params: inspect::Params) -> anyhow::Result<()> {
+    let signers = crate::cmd::load_signers(params.key_management).await?;
+    if signers.is_empty() {
+        anyhow::bail!("no key specified. Pass one of the --submit-* key flags.");
+    }
+    for (i, signer) in signers.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("Public key: 0x{}", hex::encode(signer.public_key()));
+        println!("Address: {}", signer.address());
+    }
+    Ok(())
+}
+
+async fn run_serve_signer(params: serve_signer::Params) -> anyhow::Result<()> {
+    let serve_signer::Params {
+        key_management,
+        address,
+        port,
+    } = params;
+    if key_management.submit_remote_signer.is_some() {
+        anyhow::bail!("the signer can only hold local keys");
+    }
+    let keypairs = crate::cmd::load_keys(key_management)?;
+    if keypairs.is_empty() {
+        anyhow::bail!("no key specified. Pass one of the --submit-* key flags.");
+    }
+    let Some(listen_on) = tokio::net::lookup_host((address.as_str(), port))
+        .await?
+        .next()
+    else {
+        anyhow::bail!("failed to resolve address: {}:{}", address, port)
+    };
+    for keypair in &keypairs {
+        info!("serving the key {}", keypair.address());
+    }
+    let (local_addr, server) = crate::signer::serve_local(keypairs, listen_on).await?;
+    info!("starting the remote signer on {}", local_addr);
+    server.await
+}
+
+/// Creates a new file readable only by the current user and writes the secret into it.
+fn write_secret(path: &std::path::Path, secret: &str) -> anyhow::Result<()> {
+    use std::io::Write as _;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt as _;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", secret)?;
+    Ok(())
+}
@@ -1,10 +1,15 @@
 use crate::cli::{Cli, Commands};
 use crate::key;
+use crate::signer::{BlobSigner, RemoteSigner};
 use anyhow::Context as _;
 use clap::Parser;
+use std::sync::Arc;
 
+pub mod config;
+pub mod key;
 pub mod query;
 pub mod serve;
+pub mod verify;
 
 pub async fn dispatch() -> anyhow::Result<()> {
     init_logging()?;
@@ -15,6 +20,9 @@ pub async fn dispatch() -> anyhow::Result<()> {
             anyhow::bail!("simulate subcommand not yet implemented")
         }
         Commands::Query(params) => query::run(params).await?,
+        Commands::Config(params) => config::run(params).await?,
+        Commands::Key(params) => key::run(params).await?,
+        Commands::Verify(params) => verify::run(params).await?,
     }
     Ok(())
 }
@@ -32,11 +40,66 @@ fn init_logging() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Loads the signers specified by the parameters.
+///
+/// Returns an empty vector if no key was specified.
+async fn load_signers(
+    params: crate::cli::KeyManagementParams,
+) -> anyhow::Result<Vec<Arc<dyn BlobSigner>>> {
+    if let Some(url) = params.submit_remote_signer {
+        let signers = RemoteSigner::connect(&url).await?;
+        return Ok(signers
+            .into_iter()
+            .map(|signer| Arc::new(signer) as Arc<dyn BlobSigner>)
+            .collect());
+    }
+    Ok(load_keys(params)?
+        .into_iter()
+        .map(|keypair| Arc::new(keypair) as Arc<dyn BlobSigner>)
+        .collect())
+}
+
+/// Loads the local keys specified by the parameters.
+///
+/// Returns an empty vector if no key was specified. Only `--submit-key-pool` can result in more
+/// than one key.
+fn load_keys(params: crate::cli::KeyManagementParams) -> anyhow::Result<Vec<key::Keypair>> {
+    if let Some(path) = params.submit_key_pool {
+        return key::load_pool(&path)
+            .with_context(|| format!("cannot load the key pool from '{}'", path.display()));
+    }
+    Ok(load_key(params)?.into_iter().collect())
+}
+
 fn load_key(params: crate::cli::KeyManagementParams) -> anyhow::Result<Option<key::Keypair>> {
     if params.submit_dev_alice {
         Ok(Some(key::alice()))
     } else if let Some(path) = params.submit_private_key {
         Ok(Some(key::load(path)?))
+    } else if let Some(path) = params.submit_mnemonic {
+        let keypair = key::load_uri(&path)
+            .with_context(|| format!("cannot load the key from '{}'", path.display()))?;
+        Ok(Some(keypair))
+    } else if let Some(path) = params.submit_keystore {
+        let password = match std::env::var(key::ENV_IKURA_KEYSTORE_PASSWORD) {
+            Ok(password) => password,
+            Err(_) => rpassword::prompt_password(format!(
+                "Password for the keystore '{}': ",
+                path.display()
+            ))
+            .context("cannot read the keystore password")?,
+        };
+        let keypair = key::load_keystore(&path, &password)
+            .with_context(|| format!("cannot load the keystore '{}'", path.display()))?;
+        Ok(Some(keypair))
+    } else if params.submit_key_from_env {
+        let uri = std::env::var(crate::cli::ENV_IKURA_SUBMIT_KEY).with_context(|| {
+            format!(
+                "the secret URI must be provided in {}",
+                crate::cli::ENV_IKURA_SUBMIT_KEY
+            )
+        })?;
+        Ok(Some(key::Keypair::from_uri(uri.trim())?))
     } else {
         Ok(None)
     }
@@ -70,3 +133,46 @@ fn read_namespace(namespace: &str) -> anyhow::Result<ikura_nmt::Namespace> {
         .map_err(|e| anyhow::anyhow!("cannot validate the namespace, {}", e))?;
     Ok(namespace)
 }
+
+/// The namespace proof produced by `query proof` and checked by `verify proof`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NamespaceProofFile {
+    block_number: u64,
+    #[serde(with = "ikura_serde_util::bytes32_hex")]
+    block_hash: [u8; 32],
+    namespace: ikura_nmt::Namespace,
+    tree_root: ikura_nmt::TreeRoot,
+    proof: ikura_nmt::NamespaceProof,
+    /// The SHA-256 hashes of the blobs of the namespace, in the order of the proof.
+    blob_hashes: Vec<BlobHash>,
+    /// The SCALE-encoded header of the block, which commits to the tree root.
+    #[serde(with = "ikura_serde_util::bytes_hex")]
+    header: Vec<u8>,
+}
+
+impl NamespaceProofFile {
+    /// Proves the blobs of the given namespace in the given block.
+    fn new(block: &crate::ikura_rpc::Block, namespace: ikura_nmt::Namespace) -> Self {
+        // The leaves of the tree are sorted by namespace with a stable sort, so the blobs of the
+        // namespace appear in the proof in the order of their extrinsic indices.
+        let blob_hashes = block
+            .blobs
+            .iter()
+            .filter(|blob| blob.namespace == namespace)
+            .map(|blob| BlobHash(blob.sha2_hash()))
+            .collect();
+        Self {
+            block_number: block.number,
+            block_hash: block.hash,
+            namespace,
+            tree_root: block.tree_root.clone(),
+            proof: block.namespace_proof(namespace),
+            blob_hashes,
+            header: block.header.clone(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+struct BlobHash(#[serde(with = "ikura_serde_util::bytes32_hex")] [u8; 32]);
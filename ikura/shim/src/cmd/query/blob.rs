@@ -1,6 +1,7 @@
 use super::{connect_rpc, get_block_at};
 use crate::cli::query::blob::Params;
 
+use anyhow::Context as _;
 use std::io::Write;
 
 pub async fn run(params: Params) -> anyhow::Result<()> {
@@ -9,8 +10,17 @@ pub async fn run(params: Params) -> anyhow::Result<()> {
         block,
         index,
         raw,
+        decrypt_key,
     } = params;
 
+    let decrypt_key = decrypt_key
+        .map(|path| {
+            crate::key::load_encryption_key(&path).with_context(|| {
+                format!("cannot load the encryption key from '{}'", path.display())
+            })
+        })
+        .transpose()?;
+
     let client = connect_rpc(rpc).await?;
     let block = get_block_at(&client, block).await?;
 
@@ -21,16 +31,31 @@ pub async fn run(params: Params) -> anyhow::Result<()> {
 
     let blob = block.blobs.get(i).expect("verified to exist above; qed");
 
+    let data = match decrypt_key {
+        Some(key) => {
+            let plaintext = ikura_shim_common_envelope::decrypt(
+                &blob.data,
+                &key,
+                &blob.namespace.to_raw_bytes(),
+            )
+            .map_err(|e| anyhow::anyhow!("cannot decrypt the blob: {}", e))?;
+            ikura_shim_common_envelope::open(&plaintext)
+                .map_err(|e| anyhow::anyhow!("cannot open the envelope: {}", e))?
+                .into_owned()
+        }
+        None => blob.data.clone(),
+    };
+
     if raw {
-        std::io::stdout().write_all(&blob.data)?;
+        std::io::stdout().write_all(&data)?;
     } else {
         println!(
             " Blob #{}, Namespace {}, {} bytes",
             i,
             &blob.namespace,
-            blob.data.len()
+            data.len()
         );
-        println!("{}", hex::encode(&blob.data));
+        println!("{}", hex::encode(&data));
     }
 
     Ok(())
@@ -17,6 +17,14 @@ pub async fn run(params: Params) -> anyhow::Result<()> {
         println!("  Blobs Root: 0x{}", hex::encode(&block.tree_root.root[..]));
         println!("  Min Namespace: {}", block.tree_root.min_ns);
         println!("  Max Namespace: {}", block.tree_root.max_ns);
+        if let Some(root) = &block.data_square_root {
+            println!("  Data Square Root: 0x{}", hex::encode(&root.root[..]));
+            println!(
+                "  Data Square Width: {} (extended: {})",
+                root.original_width,
+                root.extended_width()
+            );
+        }
         println!("  Timestamp: {}", block.timestamp);
         println!(
             "  Blob Count: {} ({} bytes)",
@@ -1,10 +1,14 @@
 use crate::{
     cli::query::{BlockParams, BlockRef, Commands, Params},
     ikura_rpc,
+    metrics::Metrics,
 };
 
 mod blob;
 mod block;
+mod namespace;
+mod proof;
+mod sample;
 mod submit;
 
 pub async fn run(params: Params) -> anyhow::Result<()> {
@@ -12,6 +16,9 @@ pub async fn run(params: Params) -> anyhow::Result<()> {
         Commands::Submit(params) => submit::run(params).await?,
         Commands::Block(params) => block::run(params).await?,
         Commands::Blob(params) => blob::run(params).await?,
+        Commands::Namespace(params) => namespace::run(params).await?,
+        Commands::Proof(params) => proof::run(params).await?,
+        Commands::Sample(params) => sample::run(params).await?,
     }
     Ok(())
 }
@@ -41,5 +48,16 @@ pub async fn get_block_at(
 }
 
 async fn connect_rpc(conn_params: crate::cli::IkuraRpcParams) -> anyhow::Result<ikura_rpc::Client> {
-    ikura_rpc::Client::new(conn_params.node_url, conn_params.no_retry).await
+    let mut node_url = conn_params.node_url;
+    if node_url.is_empty() {
+        node_url.push(crate::cli::DEFAULT_NODE_URL.to_string());
+    }
+    // The queries are short-lived, so the metrics are collected but never exposed.
+    ikura_rpc::Client::new(
+        node_url,
+        conn_params.cross_check.unwrap_or(false),
+        conn_params.no_retry.unwrap_or(false),
+        Metrics::new()?,
+    )
+    .await
 }
@@ -0,0 +1,105 @@
+use anyhow::Context as _;
+
+use super::connect_rpc;
+use crate::{
+    cli::query::namespace::{Format, Params},
+    cmd::read_namespace,
+};
+
+/// A blob found in the namespace.
+#[derive(serde::Serialize)]
+struct Entry {
+    block_number: u64,
+    extrinsic_index: u32,
+    #[serde(with = "ikura_serde_util::bytes32_hex")]
+    sender: [u8; 32],
+    size: usize,
+    /// The SHA-256 hash of the blob data.
+    #[serde(with = "ikura_serde_util::bytes32_hex")]
+    hash: [u8; 32],
+}
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    let Params {
+        rpc,
+        namespace,
+        from,
+        to,
+        format,
+        download_dir,
+    } = params;
+
+    let namespace = read_namespace(&namespace)?;
+    if let Some(ref dir) = download_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create the directory '{}'", dir.display()))?;
+    }
+
+    let client = connect_rpc(rpc).await?;
+    let to = match to {
+        Some(to) => to,
+        None => {
+            // Make sure that the finalized head has been observed before reading it.
+            client.await_finalized_height(from).await;
+            client.finalized_height().await
+        }
+    };
+    if to < from {
+        anyhow::bail!("--to ({}) must not be less than --from ({})", to, from);
+    }
+
+    match format {
+        Format::Text => println!("Namespace {}, blocks #{}..=#{}", namespace, from, to),
+        Format::Csv => println!("block_number,extrinsic_index,sender,size,hash"),
+        Format::Json => {}
+    }
+
+    let mut entries = Vec::new();
+    for block_number in from..=to {
+        let block_hash = client.await_finalized_height(block_number).await;
+        let block = client.await_block_at(Some(block_hash)).await?;
+        for blob in block.blobs {
+            if blob.namespace != namespace {
+                continue;
+            }
+            let entry = Entry {
+                block_number,
+                extrinsic_index: blob.extrinsic_index,
+                sender: blob.sender,
+                size: blob.data.len(),
+                hash: blob.sha2_hash(),
+            };
+            if let Some(ref dir) = download_dir {
+                let path = dir.join(format!("{}-{}.bin", block_number, blob.extrinsic_index));
+                std::fs::write(&path, &blob.data)
+                    .with_context(|| format!("cannot write the blob to '{}'", path.display()))?;
+            }
+            // The text and CSV listings are printed as the blocks are scanned, JSON at the end.
+            match format {
+                Format::Text => {
+                    println!(
+                        " Block #{}, Extrinsic Index {}",
+                        block_number, entry.extrinsic_index
+                    );
+                    println!("    Sender: 0x{}", hex::encode(entry.sender));
+                    println!("    Size: {}", entry.size);
+                    println!("    Hash: 0x{}", hex::encode(entry.hash));
+                }
+                Format::Csv => println!(
+                    "{},{},0x{},{},0x{}",
+                    entry.block_number,
+                    entry.extrinsic_index,
+                    hex::encode(entry.sender),
+                    entry.size,
+                    hex::encode(entry.hash)
+                ),
+                Format::Json => entries.push(entry),
+            }
+        }
+    }
+
+    if format == Format::Json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use super::{connect_rpc, get_block_at};
+use crate::{
+    cli::query::proof::Params,
+    cmd::{read_namespace, NamespaceProofFile},
+};
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    let Params {
+        rpc,
+        block,
+        namespace,
+    } = params;
+
+    let namespace = read_namespace(&namespace)?;
+    let client = connect_rpc(rpc).await?;
+    let block = get_block_at(&client, block).await?;
+
+    let file = NamespaceProofFile::new(&block, namespace);
+    println!("{}", serde_json::to_string_pretty(&file)?);
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use super::{connect_rpc, get_block_at};
+use crate::cli::query::sample::Params;
+use std::collections::HashSet;
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    let Params {
+        rpc,
+        block,
+        samples,
+    } = params;
+
+    let client = connect_rpc(rpc).await?;
+    let block = get_block_at(&client, block).await?;
+    let root = block.data_square_root.ok_or_else(|| {
+        anyhow::anyhow!(
+            "no data square root found in the header of block #{}",
+            block.number
+        )
+    })?;
+
+    let width = root.extended_width() as u32;
+    let coordinates = random_coordinates(width, samples)?;
+    for &(row, col) in &coordinates {
+        let sample = client.sample(block.hash, row, col).await?;
+        anyhow::ensure!(
+            (sample.row, sample.col) == (row, col),
+            "requested ({}, {}) but got the sample ({}, {})",
+            row,
+            col,
+            sample.row,
+            sample.col
+        );
+        sample
+            .verify(&root)
+            .map_err(|e| anyhow::anyhow!("the sample ({}, {}) is invalid: {:?}", row, col, e))?;
+    }
+
+    println!(
+        "Block #{}: {} samples of the {}x{} data square verified",
+        block.number,
+        coordinates.len(),
+        width,
+        width
+    );
+    println!(
+        "  Chance of unrecoverable data passing the check: below {:.2e}",
+        0.75f64.powi(coordinates.len() as i32)
+    );
+    Ok(())
+}
+
+/// Picks `n` distinct random coordinates in the square of the given width, or all of them if the
+/// square has fewer.
+fn random_coordinates(width: u32, n: u32) -> anyhow::Result<Vec<(u32, u32)>> {
+    let n = n.min(width * width) as usize;
+    let mut picked = HashSet::with_capacity(n);
+    let mut coordinates = Vec::with_capacity(n);
+    while coordinates.len() < n {
+        let mut bytes = [0u8; 8];
+        getrandom::getrandom(&mut bytes)?;
+        let index = u64::from_le_bytes(bytes) % (width as u64 * width as u64);
+        let coordinate = ((index / width as u64) as u32, (index % width as u64) as u32);
+        if picked.insert(coordinate) {
+            coordinates.push(coordinate);
+        }
+    }
+    Ok(coordinates)
+}
+
+#[test]
+fn random_coordinates_are_distinct() {
+    let coordinates = random_coordinates(4, 100).unwrap();
+    assert_eq!(coordinates.len(), 16);
+    let distinct: HashSet<_> = coordinates.iter().collect();
+    assert_eq!(distinct.len(), 16);
+    assert!(coordinates.iter().all(|&(row, col)| row < 4 && col < 4));
+}
@@ -13,16 +13,20 @@ pub async fn run(params: Params) -> anyhow::Result<()> {
     let blob = read_blob(&blob_path)
         .with_context(|| format!("cannot read blob file path '{}'", blob_path))?;
 
-    let key = crate::cmd::load_key(key_management)
+    // A single submission needs only one key, so the first key of a pool is used.
+    let key = crate::cmd::load_signers(key_management)
+        .await
         .with_context(|| format!("cannot load submission signing key"))?
-        .ok_or_else(|| anyhow::anyhow!("submission signing key required. Specify the key with --submit-private-key or use the dev key with --submit-dev-alice"))?;
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("submission signing key required. Specify the key with one of --submit-private-key, --submit-mnemonic, --submit-keystore, --submit-key-from-env, --submit-key-pool, --submit-remote-signer or use the dev key with --submit-dev-alice"))?;
 
     let namespace = read_namespace(&namespace)?;
     let client = connect_rpc(rpc).await?;
     tracing::info!("submitting blob to namespace {}", namespace);
-    let nonce = client.get_last_nonce(&key).await?;
+    let nonce = client.get_last_nonce(&*key).await?;
     let blob_extrinsic = client
-        .make_blob_extrinsic(blob, namespace, &key, nonce)
+        .make_blob_extrinsic(blob, namespace, &*key, nonce)
         .await?;
     let (block_hash, _) = client.submit_blob(&blob_extrinsic).await?;
     tracing::info!("submitted blob to block hash 0x{}", hex::encode(block_hash));
@@ -1,71 +1,494 @@
 use crate::{
-    cli::serve::{self, Dock, Params},
+    cli::{
+        serve::{self, Dock, Params},
+        BalanceMonitorParams, EnvelopeParams, IkuraRpcParams, KeyManagementParams, MetricsParams,
+        RateLimitParams, TransportParams, DEFAULT_ADDRESS, DEFAULT_NODE_URL, DEFAULT_ROLLKIT_PORT,
+        DEFAULT_SHIM_PORT,
+    },
     cmd::read_namespace,
-    dock,
+    config::{self, Config, RollkitDockConfig, SovDockConfig},
+    dock::{
+        self, EncryptionKeys, EnvelopeConfig, Limits, NamespacePolicy, RateLimits, SubmissionQueue,
+        SubmitKeyPool, TlsConfig,
+    },
     ikura_rpc::Client,
+    metrics::{self, Metrics},
 };
+use anyhow::Context as _;
+use futures::{future::LocalBoxFuture, FutureExt as _};
+use std::collections::BTreeMap;
 use tracing::info;
 
-pub async fn run(Params { dock }: Params) -> anyhow::Result<()> {
+pub async fn run(Params { config, dock }: Params) -> anyhow::Result<()> {
+    let file_config = match config {
+        Some(ref path) => crate::config::load(path)?,
+        None => Config::default(),
+    };
     match dock {
-        Dock::Sov(params) => run_sov(params).await,
-        Dock::Rollkit(params) => run_rollkit(params).await,
+        Some(Dock::Sov(params)) => run_sov(params, &file_config).await,
+        Some(Dock::Rollkit(params)) => run_rollkit(params, &file_config).await,
+        Some(Dock::Multi(params)) => run_multi(params, &file_config).await,
+        None if config.is_some() => {
+            // Run whatever docks are enabled in the config file.
+            run_multi(serve::multi::Params::default(), &file_config).await
+        }
+        None => anyhow::bail!("no dock specified. Specify the dock to run or pass --config."),
     }
 }
 
-async fn connect_client(url: &str, no_retry: bool) -> anyhow::Result<Client> {
-    let client = Client::new(url.to_string(), no_retry).await?;
+async fn connect_client(
+    params: IkuraRpcParams,
+    file_config: &Config,
+    metrics: Metrics,
+) -> anyhow::Result<Client> {
+    let mut params = file_config.merge_rpc(params);
+    if params.node_url.is_empty() {
+        params.node_url.push(DEFAULT_NODE_URL.to_string());
+    }
+    let client = Client::new(
+        params.node_url,
+        params.cross_check.unwrap_or(false),
+        params.no_retry.unwrap_or(false),
+        metrics,
+    )
+    .await?;
     Ok(client)
 }
 
-fn load_submit_key(
-    params: crate::cli::KeyManagementParams,
-) -> Result<Option<subxt_signer::sr25519::Keypair>, anyhow::Error> {
-    let submit_key = crate::cmd::load_key(params)?;
-    if submit_key.is_none() {
+/// Creates the metrics and, if requested, spawns the server exposing them.
+async fn start_metrics(params: MetricsParams, file_config: &Config) -> anyhow::Result<Metrics> {
+    let metrics = Metrics::new()?;
+    let params = file_config.merge_metrics(params);
+    let Some(port) = params.metrics_port else {
+        return Ok(metrics);
+    };
+    let address = params
+        .metrics_address
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let Some(listen_on) = tokio::net::lookup_host((address.as_str(), port))
+        .await?
+        .next()
+    else {
+        anyhow::bail!("failed to resolve metrics address: {}:{}", address, port)
+    };
+    info!("starting Prometheus metrics server on {}", listen_on);
+    let server = metrics::serve(metrics.clone(), listen_on)?;
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            tracing::error!(?err, "metrics server terminated");
+        }
+    });
+    Ok(metrics)
+}
+
+async fn load_submit_keys(
+    params: KeyManagementParams,
+    file_config: &Config,
+) -> Result<Option<SubmitKeyPool>, anyhow::Error> {
+    let signers = crate::cmd::load_signers(file_config.merge_key(params)).await?;
+    let submit_keys = SubmitKeyPool::new(signers);
+    if submit_keys.is_none() {
         tracing::info!(
             "no submit key provided, will not be able to submit blobs. \
-Pass --submit-dev-alice or --submit-private-key=<..> to fix."
+Pass --submit-dev-alice, --submit-private-key=<..>, --submit-mnemonic=<..>, \
+--submit-keystore=<..>, --submit-key-from-env, --submit-key-pool=<..> or \
+--submit-remote-signer=<..> to fix."
+        );
+    }
+    Ok(submit_keys)
+}
+
+/// Spawns the task monitoring the balances of the submission keys, if there are any.
+fn start_balance_monitor(
+    params: BalanceMonitorParams,
+    file_config: &Config,
+    submit_keys: &Option<SubmitKeyPool>,
+    client: &Client,
+    metrics: &Metrics,
+) {
+    let Some(submit_keys) = submit_keys.clone() else {
+        return;
+    };
+    let params = file_config.merge_balance_monitor(params);
+    tokio::spawn(submit_keys.monitor_balances(
+        client.clone(),
+        metrics.clone(),
+        params.balance_warn_threshold,
+    ));
+}
+
+/// Starts the queue the docks submit the blobs through, if there are any keys to sign them with.
+fn start_submission_queue(
+    submit_keys: Option<SubmitKeyPool>,
+    client: &Client,
+    metrics: &Metrics,
+) -> Option<SubmissionQueue> {
+    submit_keys
+        .map(|submit_keys| SubmissionQueue::start(client.clone(), submit_keys, metrics.clone()))
+}
+
+/// Builds the namespace policy of the Sovereign SDK dock. The allowlist given on the command line
+/// replaces the one in the file.
+fn sov_policy(
+    allow_namespace: Vec<String>,
+    file_dock: Option<&SovDockConfig>,
+) -> anyhow::Result<NamespacePolicy> {
+    let allowlist = if allow_namespace.is_empty() {
+        file_dock.and_then(|d| d.namespaces.clone())
+    } else {
+        Some(allow_namespace)
+    };
+    let allowlist = allowlist
+        .map(|namespaces| {
+            namespaces
+                .iter()
+                .map(|ns| read_namespace(ns))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .transpose()?;
+    let mut tokens = BTreeMap::new();
+    for (ns, token) in file_dock.iter().flat_map(|d| d.tokens.iter()) {
+        tokens.insert(read_namespace(ns)?, token.clone());
+    }
+    Ok(NamespacePolicy::new(allowlist, tokens))
+}
+
+/// Builds the namespace policy of the Rollkit dock, which only ever submits into its namespace.
+fn rollkit_policy(
+    namespace: Option<ikura_nmt::Namespace>,
+    file_dock: Option<&RollkitDockConfig>,
+) -> NamespacePolicy {
+    match (namespace, file_dock.and_then(|d| d.token.clone())) {
+        (Some(namespace), Some(token)) => {
+            NamespacePolicy::new(None, [(namespace, token)].into_iter().collect())
+        }
+        _ => NamespacePolicy::default(),
+    }
+}
+
+/// Resolves the TLS configuration and the API token of a dock. The values given on the command
+/// line take precedence over the ones in the file.
+fn transport(
+    params: &TransportParams,
+    file_tls: Option<&config::TlsConfig>,
+    file_api_token: Option<&String>,
+) -> (Option<TlsConfig>, Option<String>) {
+    let tls = match (&params.tls_cert, &params.tls_key) {
+        (Some(cert), Some(key)) => Some(TlsConfig {
+            cert: cert.clone(),
+            key: key.clone(),
+            client_ca: params.tls_client_ca.clone(),
+        }),
+        _ => file_tls.map(|tls| TlsConfig {
+            cert: tls.cert.clone(),
+            key: tls.key.clone(),
+            client_ca: tls.client_ca.clone(),
+        }),
+    };
+    if let Some(tls) = &tls {
+        info!(
+            "TLS enabled, client certificates are {}",
+            if tls.client_ca.is_some() {
+                "required"
+            } else {
+                "not required"
+            }
         );
     }
-    Ok(submit_key)
+    let api_token = params.api_token.clone().or_else(|| file_api_token.cloned());
+    (tls, api_token)
+}
+
+/// Resolves the rate limits of a dock. Each of the limits given on the command line takes
+/// precedence over the one in the file.
+fn rate_limits(params: &RateLimitParams, file: Option<&config::RateLimitConfig>) -> RateLimits {
+    RateLimits {
+        per_namespace: Limits {
+            bytes_per_minute: params
+                .namespace_bytes_per_minute
+                .or_else(|| file.and_then(|f| f.namespace_bytes_per_minute)),
+            blobs_per_block: params
+                .namespace_blobs_per_block
+                .or_else(|| file.and_then(|f| f.namespace_blobs_per_block)),
+        },
+        per_client: Limits {
+            bytes_per_minute: params
+                .client_bytes_per_minute
+                .or_else(|| file.and_then(|f| f.client_bytes_per_minute)),
+            blobs_per_block: params
+                .client_blobs_per_block
+                .or_else(|| file.and_then(|f| f.client_blobs_per_block)),
+        },
+    }
 }
 
-async fn run_sov(params: serve::sov::Params) -> anyhow::Result<()> {
+/// Loads the encryption keys listed in the `[encryption]` section of the file.
+fn load_encryption_keys(file_config: &Config) -> anyhow::Result<EncryptionKeys> {
+    let mut keys = Vec::new();
+    for (namespace, path) in &file_config.encryption.keys {
+        let namespace = read_namespace(namespace)?;
+        let key = crate::key::load_encryption_key(path)
+            .with_context(|| format!("cannot load the encryption key from '{}'", path.display()))?;
+        info!("encrypting the blobs of namespace {}", namespace);
+        keys.push((namespace, key));
+    }
+    Ok(EncryptionKeys::new(keys))
+}
+
+/// Resolves the envelope configuration of a dock. The compression level given on the command line
+/// takes precedence over the one in the file.
+fn envelope(
+    params: &EnvelopeParams,
+    file_compression_level: Option<i32>,
+    encryption_keys: &EncryptionKeys,
+) -> EnvelopeConfig {
+    let compression_level = params.compression_level.or(file_compression_level);
+    if let Some(level) = compression_level {
+        info!("compressing the submitted blobs at level {}", level);
+    }
+    EnvelopeConfig {
+        compression_level,
+        encryption_keys: encryption_keys.clone(),
+    }
+}
+
+async fn run_sov(params: serve::sov::Params, file_config: &Config) -> anyhow::Result<()> {
+    let file_dock = file_config.docks.sov.as_ref();
+    let address = params
+        .dock
+        .address
+        .or_else(|| file_dock.and_then(|d| d.address.clone()))
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let port = params
+        .dock
+        .port
+        .or_else(|| file_dock.and_then(|d| d.port))
+        .unwrap_or(DEFAULT_SHIM_PORT);
     info!(
         "starting Sovereign SDK JSON-RPC ikura-shim server on {}:{}",
-        params.dock.address, params.dock.port
+        address, port
     );
-    let submit_key = load_submit_key(params.key_management)?;
-    let client = connect_client(&params.rpc.node_url, params.rpc.no_retry).await?;
+    let submit_keys = load_submit_keys(params.key_management, file_config).await?;
+    let policy = sov_policy(params.allow_namespace, file_dock)?;
+    let (tls, api_token) = transport(
+        &params.dock.transport,
+        file_dock.and_then(|d| d.tls.as_ref()),
+        file_dock.and_then(|d| d.api_token.as_ref()),
+    );
+    let rate_limits = rate_limits(&params.dock.rate_limit, file_dock.map(|d| &d.rate_limit));
+    let encryption_keys = load_encryption_keys(file_config)?;
+    let metrics = start_metrics(params.dock.metrics, file_config).await?;
+    let client = connect_client(params.rpc, file_config, metrics.clone()).await?;
+    start_balance_monitor(
+        params.balance_monitor,
+        file_config,
+        &submit_keys,
+        &client,
+        &metrics,
+    );
+    let submit_queue = start_submission_queue(submit_keys, &client, &metrics);
     let config = dock::sovereign::Config {
         client,
-        submit_key,
-        address: params.dock.address,
-        port: params.dock.port,
+        metrics,
+        submit_queue,
+        policy,
+        rate_limits,
+        envelope: envelope(
+            &params.dock.envelope,
+            file_dock.and_then(|d| d.compression_level),
+            &encryption_keys,
+        ),
+        tls,
+        api_token,
+        address,
+        port,
     };
     dock::sovereign::run(config).await?;
     Ok(())
 }
 
-async fn run_rollkit(params: serve::rollkit::Params) -> anyhow::Result<()> {
+async fn run_rollkit(params: serve::rollkit::Params, file_config: &Config) -> anyhow::Result<()> {
+    let file_dock = file_config.docks.rollkit.as_ref();
+    let address = params
+        .dock
+        .address
+        .or_else(|| file_dock.and_then(|d| d.address.clone()))
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let port = params
+        .dock
+        .port
+        .or_else(|| file_dock.and_then(|d| d.port))
+        .unwrap_or(DEFAULT_SHIM_PORT);
     info!(
         "starting Rollkit SDK gRPC ikura-shim server on {}:{}",
-        params.dock.address, params.dock.port
+        address, port
     );
-    let submit_key = load_submit_key(params.key_management)?;
-    let namespace = params.namespace.map(|ns| read_namespace(&ns)).transpose()?;
+    let submit_keys = load_submit_keys(params.key_management, file_config).await?;
+    let namespace = params
+        .namespace
+        .or_else(|| file_dock.and_then(|d| d.namespace.clone()))
+        .map(|ns| read_namespace(&ns))
+        .transpose()?;
     if namespace.is_none() {
         tracing::info!("no namespace provided, will not be able to submit blobs");
     }
-    let client = connect_client(&params.rpc.node_url, params.rpc.no_retry).await?;
+    let policy = rollkit_policy(namespace, file_dock);
+    let (tls, api_token) = transport(
+        &params.dock.transport,
+        file_dock.and_then(|d| d.tls.as_ref()),
+        file_dock.and_then(|d| d.api_token.as_ref()),
+    );
+    let rate_limits = rate_limits(&params.dock.rate_limit, file_dock.map(|d| &d.rate_limit));
+    let encryption_keys = load_encryption_keys(file_config)?;
+    let metrics = start_metrics(params.dock.metrics, file_config).await?;
+    let client = connect_client(params.rpc, file_config, metrics.clone()).await?;
+    start_balance_monitor(
+        params.balance_monitor,
+        file_config,
+        &submit_keys,
+        &client,
+        &metrics,
+    );
+    let submit_queue = start_submission_queue(submit_keys, &client, &metrics);
     let config = dock::rollkit::Config {
         client,
-        submit_key,
-        address: params.dock.address,
-        port: params.dock.port,
+        metrics,
+        submit_queue,
+        address,
+        port,
         namespace,
+        policy,
+        rate_limits,
+        envelope: envelope(
+            &params.dock.envelope,
+            file_dock.and_then(|d| d.compression_level),
+            &encryption_keys,
+        ),
+        tls,
+        api_token,
     };
     dock::rollkit::run(config).await?;
     Ok(())
 }
+
+async fn run_multi(params: serve::multi::Params, file_config: &Config) -> anyhow::Result<()> {
+    let file_sov = file_config.docks.sov.as_ref();
+    let file_rollkit = file_config.docks.rollkit.as_ref();
+
+    // A dock is enabled if its port is given on the command line or its section is present in
+    // the config file.
+    let sov_port = params
+        .sov_port
+        .or_else(|| file_sov.map(|d| d.port.unwrap_or(DEFAULT_SHIM_PORT)));
+    let rollkit_port = params
+        .rollkit_port
+        .or_else(|| file_rollkit.map(|d| d.port.unwrap_or(DEFAULT_ROLLKIT_PORT)));
+    match (sov_port, rollkit_port) {
+        (None, None) => {
+            anyhow::bail!("no docks enabled. Pass --sov-port and/or --rollkit-port to enable them.")
+        }
+        (Some(sov_port), Some(rollkit_port)) if sov_port == rollkit_port => anyhow::bail!(
+            "the Sovereign SDK and Rollkit docks cannot both listen on port {}",
+            sov_port
+        ),
+        _ => (),
+    }
+
+    let submit_keys = load_submit_keys(params.key_management, file_config).await?;
+    let rollkit_namespace = params
+        .rollkit_namespace
+        .or_else(|| file_rollkit.and_then(|d| d.namespace.clone()))
+        .map(|ns| read_namespace(&ns))
+        .transpose()?;
+    let sov_policy = sov_policy(params.sov_allow_namespace, file_sov)?;
+    let rollkit_policy = rollkit_policy(rollkit_namespace, file_rollkit);
+    let encryption_keys = load_encryption_keys(file_config)?;
+    let metrics = start_metrics(params.metrics, file_config).await?;
+    let client = connect_client(params.rpc, file_config, metrics.clone()).await?;
+    start_balance_monitor(
+        params.balance_monitor,
+        file_config,
+        &submit_keys,
+        &client,
+        &metrics,
+    );
+    let submit_queue = start_submission_queue(submit_keys, &client, &metrics);
+
+    let mut docks: Vec<LocalBoxFuture<anyhow::Result<()>>> = vec![];
+    if let Some(port) = sov_port {
+        let address = params
+            .address
+            .clone()
+            .or_else(|| file_sov.and_then(|d| d.address.clone()))
+            .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+        info!(
+            "starting Sovereign SDK JSON-RPC ikura-shim server on {}:{}",
+            address, port
+        );
+        let (tls, api_token) = transport(
+            &params.transport,
+            file_sov.and_then(|d| d.tls.as_ref()),
+            file_sov.and_then(|d| d.api_token.as_ref()),
+        );
+        let config = dock::sovereign::Config {
+            client: client.clone(),
+            metrics: metrics.clone(),
+            submit_queue: submit_queue.clone(),
+            policy: sov_policy,
+            rate_limits: rate_limits(&params.rate_limit, file_sov.map(|d| &d.rate_limit)),
+            envelope: envelope(
+                &params.envelope,
+                file_sov.and_then(|d| d.compression_level),
+                &encryption_keys,
+            ),
+            tls,
+            api_token,
+            address,
+            port,
+        };
+        docks.push(dock::sovereign::run(config).boxed_local());
+    }
+    if let Some(port) = rollkit_port {
+        let address = params
+            .address
+            .clone()
+            .or_else(|| file_rollkit.and_then(|d| d.address.clone()))
+            .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+        info!(
+            "starting Rollkit SDK gRPC ikura-shim server on {}:{}",
+            address, port
+        );
+        if rollkit_namespace.is_none() {
+            tracing::info!(
+                "no namespace provided, the Rollkit dock will not be able to submit blobs"
+            );
+        }
+        let (tls, api_token) = transport(
+            &params.transport,
+            file_rollkit.and_then(|d| d.tls.as_ref()),
+            file_rollkit.and_then(|d| d.api_token.as_ref()),
+        );
+        let config = dock::rollkit::Config {
+            client: client.clone(),
+            metrics: metrics.clone(),
+            submit_queue: submit_queue.clone(),
+            address,
+            port,
+            namespace: rollkit_namespace,
+            policy: rollkit_policy,
+            rate_limits: rate_limits(&params.rate_limit, file_rollkit.map(|d| &d.rate_limit)),
+            envelope: envelope(
+                &params.envelope,
+                file_rollkit.and_then(|d| d.compression_level),
+                &encryption_keys,
+            ),
+            tls,
+            api_token,
+        };
+        docks.push(dock::rollkit::run(config).boxed_local());
+    }
+
+    // The docks are not expected to terminate. If any of them does, bring the whole process down.
+    let (res, _, _) = futures::future::select_all(docks).await;
+    res
+}
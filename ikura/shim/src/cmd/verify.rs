@@ -0,0 +1,173 @@
+use crate::{
+    cli::verify::{proof, Commands, Params},
+    cmd::NamespaceProofFile,
+};
+use anyhow::Context as _;
+
+pub async fn run(params: Params) -> anyhow::Result<()> {
+    match params.command {
+        Commands::Proof(params) => run_proof(params),
+    }
+}
+
+fn run_proof(params: proof::Params) -> anyhow::Result<()> {
+    let proof::Params { path } = params;
+    let contents = if path == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("cannot read proof file '{}'", path))?
+    };
+    let file: NamespaceProofFile = serde_json::from_str(&contents)
+        .with_context(|| format!("cannot parse proof file '{}'", path))?;
+    let (namespace, block_number, block_hash) =
+        (file.namespace, file.block_number, file.block_hash);
+    let blob_hashes = verify_proof(file)?;
+
+    println!(
+        "Valid: namespace {} has {} blob(s) in block #{} (0x{})",
+        namespace,
+        blob_hashes.len(),
+        block_number,
+        hex::encode(block_hash)
+    );
+    for hash in &blob_hashes {
+        println!("  0x{}", hex::encode(hash));
+    }
+    Ok(())
+}
+
+/// Verifies the proof against the tree root committed in the header in the file, and the header
+/// against the block hash in the file. Returns the proven blob hashes.
+fn verify_proof(file: NamespaceProofFile) -> anyhow::Result<Vec<[u8; 32]>> {
+    use subxt::config::Header as _;
+
+    let header: ikura_subxt::Header = subxt::ext::codec::Decode::decode(&mut &file.header[..])
+        .map_err(|e| anyhow::anyhow!("cannot decode the block header: {}", e))?;
+    anyhow::ensure!(
+        header.hash().0 == file.block_hash,
+        "the header does not hash to the block hash 0x{}",
+        hex::encode(file.block_hash)
+    );
+    anyhow::ensure!(
+        header.number as u64 == file.block_number,
+        "the header is of block #{}, not #{}",
+        header.number,
+        file.block_number
+    );
+    let tree_root = crate::ikura_rpc::tree_root(&header)
+        .ok_or_else(|| anyhow::anyhow!("the header does not commit to a tree root"))?;
+    anyhow::ensure!(
+        tree_root == file.tree_root,
+        "the tree root in the file is not the one committed in the header"
+    );
+
+    let blob_hashes = file
+        .blob_hashes
+        .iter()
+        .map(|hash| hash.0)
+        .collect::<Vec<_>>();
+    file.proof
+        .verify(&blob_hashes, tree_root, file.namespace)
+        .map_err(|e| anyhow::anyhow!("the proof is invalid: {:?}", e))?;
+    Ok(blob_hashes)
+}
+
+#[test]
+fn proof_file_round_trip() {
+    use crate::ikura_rpc::{Blob, Block};
+    use ikura_nmt::{LeafVersion, Namespace};
+    use subxt::config::{
+        substrate::{Digest, DigestItem},
+        Header as _,
+    };
+
+    let blob = |extrinsic_index, namespace, data: &[u8]| Blob {
+        extrinsic_index,
+        namespace: Namespace::from_u128_be(namespace),
+        sender: [extrinsic_index as u8; 32],
+        data: data.to_vec(),
+    };
+    // The blocks from before the share commitments have V0 leaves and an `snmt` digest, the later
+    // ones V1 leaves and an `snm1` digest.
+    for (leaf_version, digest_tag) in [(LeafVersion::V0, b"snmt"), (LeafVersion::V1, b"snm1")] {
+        let mut block = Block {
+            number: 1,
+            hash: [1; 32],
+            parent_hash: [0; 32],
+            tree_root: ikura_nmt::TreeBuilder::new().root(),
+            data_square_root: None,
+            timestamp: 0,
+            blobs: vec![blob(1, 2, b"a"), blob(2, 1, b"b"), blob(3, 2, b"c")],
+            header: vec![],
+        };
+        let blob_metadata = block
+            .blobs
+            .iter()
+            .map(|blob| ikura_nmt::BlobMetadata {
+                namespace: blob.namespace,
+                leaf: blob.nmt_leaf(leaf_version),
+            })
+            .collect();
+        block.tree_root = ikura_nmt::tree_from_blobs(blob_metadata, leaf_version).root();
+        let digest = block.tree_root.to_digest();
+        assert!(digest.starts_with(digest_tag));
+        let header = ikura_subxt::Header {
+            parent_hash: Default::default(),
+            number: 1,
+            state_root: Default::default(),
+            extrinsics_root: Default::default(),
+            digest: Digest {
+                logs: vec![DigestItem::Other(digest)],
+            },
+        };
+        block.hash = header.hash().0;
+        block.header = subxt::ext::codec::Encode::encode(&header);
+
+        let namespace = Namespace::from_u128_be(2);
+        let json = serde_json::to_string(&NamespaceProofFile::new(&block, namespace)).unwrap();
+        let file: NamespaceProofFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(file.tree_root.leaf_version, leaf_version);
+        let leaves = file
+            .proof
+            .verify_leaves(&file.tree_root, namespace)
+            .unwrap();
+        let hashes = verify_proof(file).unwrap();
+        assert_eq!(
+            hashes,
+            vec![block.blobs[0].sha2_hash(), block.blobs[2].sha2_hash()]
+        );
+        // The V1 leaves also commit to the shares of the blobs.
+        let shares = |data: &[u8]| match leaf_version {
+            LeafVersion::V0 => None,
+            LeafVersion::V1 => Some(ikura_nmt::ShareCommitment::new(data)),
+        };
+        assert_eq!(
+            leaves
+                .into_iter()
+                .map(|leaf| leaf.shares)
+                .collect::<Vec<_>>(),
+            vec![shares(b"a"), shares(b"c")]
+        );
+
+        // Omitting a blob of the namespace is detected.
+        let mut file: NamespaceProofFile = serde_json::from_str(&json).unwrap();
+        file.blob_hashes.pop();
+        assert!(verify_proof(file).is_err());
+
+        // A file that does not match the header is rejected.
+        let mut file: NamespaceProofFile = serde_json::from_str(&json).unwrap();
+        file.block_hash = [2; 32];
+        assert!(verify_proof(file).is_err());
+        let mut file: NamespaceProofFile = serde_json::from_str(&json).unwrap();
+        file.tree_root = ikura_nmt::TreeBuilder::new().root();
+        assert!(verify_proof(file).is_err());
+        // So is a file claiming the leaves of the other version.
+        let mut file: NamespaceProofFile = serde_json::from_str(&json).unwrap();
+        file.tree_root.leaf_version = match leaf_version {
+            LeafVersion::V0 => LeafVersion::V1,
+            LeafVersion::V1 => LeafVersion::V0,
+        };
+        assert!(verify_proof(file).is_err());
+    }
+}
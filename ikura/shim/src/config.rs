@@ -0,0 +1,334 @@
+//! The configuration file of the shim.
+//!
+//! The configuration file is an alternative way of providing the parameters of the `serve`
+//! subcommand. The values given on the command line or through the environment variables take
+//! precedence over the values found in the file.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+
+use crate::cli::{BalanceMonitorParams, IkuraRpcParams, KeyManagementParams, MetricsParams};
+
+/// The root of the configuration file.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub node: NodeConfig,
+    pub key: KeyConfig,
+    pub metrics: MetricsConfig,
+    pub encryption: EncryptionConfig,
+    pub docks: DocksConfig,
+}
+
+/// The `[node]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    pub urls: Vec<String>,
+    pub cross_check: Option<bool>,
+    pub no_retry: Option<bool>,
+}
+
+/// The `[key]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyConfig {
+    pub dev_alice: bool,
+    pub private_key: Option<PathBuf>,
+    pub mnemonic: Option<PathBuf>,
+    pub keystore: Option<PathBuf>,
+    pub from_env: bool,
+    pub pool: Option<PathBuf>,
+    pub remote_signer: Option<String>,
+    pub balance_warn_threshold: Option<u128>,
+}
+
+/// The `[metrics]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// The `[encryption]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// The paths to the files with the hex-encoded 32-byte keys, keyed by the namespace.
+    pub keys: BTreeMap<String, PathBuf>,
+}
+
+/// The `[docks]` section. A dock is enabled if its subsection is present.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocksConfig {
+    pub sov: Option<SovDockConfig>,
+    pub rollkit: Option<RollkitDockConfig>,
+}
+
+/// The `[docks.sov]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SovDockConfig {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    pub namespaces: Option<Vec<String>>,
+    /// The tokens required for submitting into the namespaces, keyed by the namespace.
+    pub tokens: BTreeMap<String, String>,
+    pub api_token: Option<String>,
+    pub tls: Option<TlsConfig>,
+    pub rate_limit: RateLimitConfig,
+    /// Compress the submitted blobs with zstd at this level.
+    pub compression_level: Option<i32>,
+}
+
+/// The `[docks.rollkit]` section.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RollkitDockConfig {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    pub namespace: Option<String>,
+    pub token: Option<String>,
+    pub api_token: Option<String>,
+    pub tls: Option<TlsConfig>,
+    pub rate_limit: RateLimitConfig,
+    /// Compress the submitted blobs with zstd at this level.
+    pub compression_level: Option<i32>,
+}
+
+/// The `[docks.*.tls]` sections.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub client_ca: Option<PathBuf>,
+}
+
+/// The `[docks.*.rate_limit]` sections.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub namespace_bytes_per_minute: Option<u64>,
+    pub namespace_blobs_per_block: Option<u32>,
+    pub client_bytes_per_minute: Option<u64>,
+    pub client_blobs_per_block: Option<u32>,
+}
+
+/// Reads and parses the configuration file at the given path.
+pub fn load(path: &Path) -> anyhow::Result<Config> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read config file '{}'", path.display()))?;
+    let config: Config = toml::from_str(&contents)
+        .with_context(|| format!("cannot parse config file '{}'", path.display()))?;
+    let key = &config.key;
+    let key_sources = [
+        key.dev_alice,
+        key.private_key.is_some(),
+        key.mnemonic.is_some(),
+        key.keystore.is_some(),
+        key.from_env,
+        key.pool.is_some(),
+        key.remote_signer.is_some(),
+    ];
+    if key_sources.into_iter().filter(|&source| source).count() > 1 {
+        anyhow::bail!("config file: at most one key source can be specified in `[key]`");
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// Fills the node connection parameters not given on the command line from the file.
+    pub fn merge_rpc(&self, mut params: IkuraRpcParams) -> IkuraRpcParams {
+        if params.node_url.is_empty() {
+            params.node_url = self.node.urls.clone();
+        }
+        params.cross_check = params.cross_check.or(self.node.cross_check);
+        params.no_retry = params.no_retry.or(self.node.no_retry);
+        params
+    }
+
+    /// Takes the key from the file unless a key was given on the command line.
+    pub fn merge_key(&self, params: KeyManagementParams) -> KeyManagementParams {
+        if params.is_specified() {
+            return params;
+        }
+        KeyManagementParams {
+            submit_dev_alice: self.key.dev_alice,
+            submit_private_key: self.key.private_key.clone(),
+            submit_mnemonic: self.key.mnemonic.clone(),
+            submit_keystore: self.key.keystore.clone(),
+            submit_key_from_env: self.key.from_env,
+            submit_key_pool: self.key.pool.clone(),
+            submit_remote_signer: self.key.remote_signer.clone(),
+        }
+    }
+
+    /// Fills the balance monitoring parameters not given on the command line from the file.
+    pub fn merge_balance_monitor(&self, mut params: BalanceMonitorParams) -> BalanceMonitorParams {
+        params.balance_warn_threshold = params
+            .balance_warn_threshold
+            .or(self.key.balance_warn_threshold);
+        params
+    }
+
+    /// Fills the metrics parameters not given on the command line from the file.
+    pub fn merge_metrics(&self, mut params: MetricsParams) -> MetricsParams {
+        params.metrics_address = params
+            .metrics_address
+            .or_else(|| self.metrics.address.clone());
+        params.metrics_port = params.metrics_port.or(self.metrics.port);
+        params
+    }
+}
+
+/// The template written by `ikura-shim config init`.
+pub const TEMPLATE: &str = r#"# Configuration file for ikura-shim.
+#
+# Use it with `ikura-shim serve --config <PATH>`. The values passed on the command line or through
+# the environment variables take precedence over the values in this file.
+
+[node]
+# The ikura nodes to connect to. The shim connects to the node with the highest finalized block
+# and fails over to the others in case of connection errors.
+urls = ["ws://localhost:9988"]
+# Make sure that the selected node agrees with the other nodes on the finalized blocks.
+cross_check = false
+# Attempt to connect to the node only once at startup instead of retrying until it succeeds.
+no_retry = false
+
+[key]
+# The key used to sign blob transactions. Specify at most one of the following.
+#
+# Use the Alice development key. Only useful with ikura-node running in the development mode.
+# dev_alice = true
+#
+# Path to a file with 32 bytes of unencrypted, hex-encoded sr25519 seed material.
+# private_key = "/path/to/keyfile"
+#
+# Path to a file with a secret URI: a BIP-39 mnemonic phrase optionally followed by a derivation
+# path, e.g. `<mnemonic>//rollup1`. See `ikura-shim key generate`.
+# mnemonic = "/path/to/mnemonic"
+#
+# Path to a JSON keystore exported from polkadot-js. The password is read from the
+# IKURA_KEYSTORE_PASSWORD environment variable or prompted for.
+# keystore = "/path/to/keystore.json"
+#
+# Read the secret URI from the IKURA_SUBMIT_KEY environment variable.
+# from_env = true
+#
+# Path to a file with one secret URI per line. The submissions are spread across the keys in a
+# round-robin fashion.
+# pool = "/path/to/pool"
+#
+# The URL of a remote signer holding the keys, so that no secrets are kept on the shim host.
+# See `ikura-shim key serve-signer` for a local stand-in.
+# remote_signer = "http://127.0.0.1:10997"
+
+# Log a warning when the free balance of any of the keys drops below the given amount, in the
+# smallest units.
+# balance_warn_threshold = 1000000000000
+
+[metrics]
+# Serve the Prometheus metrics under `/metrics`. The server is started only if the port is set.
+# address = "127.0.0.1"
+# port = 9616
+
+[encryption]
+# Encrypt the blobs submitted into the namespaces with XChaCha20-Poly1305, so that only the holders
+# of the key can read them. Each file contains a hex-encoded 32-byte key. The Rollkit dock
+# decrypts the retrieved blobs and serves a blob it fails to decrypt as stored on chain. The
+# Sovereign SDK adapter has no decryption keys and passes the ciphertext to the rollup as is, so
+# do not enable encryption for the namespaces of a Sovereign SDK rollup. Use
+# `ikura-shim query blob --decrypt-key` to inspect the blobs.
+# [encryption.keys]
+# "0x00000000000000000000000000000001" = "/path/to/keyfile"
+
+# The docks to run. A dock is enabled if its section is present.
+
+[docks.sov]
+# Serves the Sovereign SDK rollups.
+address = "127.0.0.1"
+port = 10995
+# Only accept the submissions into these namespaces. If omitted, any namespace is accepted.
+# namespaces = ["0x00000000000000000000000000000001"]
+#
+# Require the clients to present this token in the `x-api-token` header.
+# api_token = "secret"
+#
+# Compress the submitted blobs with zstd at the given level, from 1 to 22, and put them into an
+# envelope with a versioned header. The adapter takes the blobs out of their envelopes.
+# compression_level = 3
+#
+# Serve TLS. If `client_ca` is specified, the clients must present a certificate signed by it.
+# [docks.sov.tls]
+# cert = "/path/to/cert.pem"
+# key = "/path/to/key.pem"
+# client_ca = "/path/to/ca.pem"
+#
+# Require the rollups to present a token when submitting into a namespace. A namespace with
+# a token is accepted even if it is not listed in `namespaces`.
+# [docks.sov.tokens]
+# "0x00000000000000000000000000000002" = "secret"
+#
+# Reject the submissions exceeding the limits, per namespace and per client IP address. A block is
+# counted as the time between two finalized blocks. No limits are enforced unless specified.
+# [docks.sov.rate_limit]
+# namespace_bytes_per_minute = 10485760
+# namespace_blobs_per_block = 10
+# client_bytes_per_minute = 1048576
+# client_blobs_per_block = 2
+
+# [docks.rollkit]
+# Serves the Rollkit rollups.
+# address = "127.0.0.1"
+# port = 10996
+#
+# The namespace to submit the blobs into, either as a 16-byte hex vector prefixed with `0x` or as
+# an unsigned 128-bit big-endian integer.
+# namespace = "0x00000000000000000000000000000001"
+#
+# Require the rollup to present the token as `authorization: Bearer <token>` in the metadata of
+# the submit requests.
+# token = "secret"
+#
+# Require the clients to present this token in the `x-api-token` metadata.
+# api_token = "secret"
+#
+# Compress the submitted blobs. The retrieved blobs are taken out of their envelopes by the dock.
+# compression_level = 3
+#
+# [docks.rollkit.tls]
+# cert = "/path/to/cert.pem"
+# key = "/path/to/key.pem"
+# client_ca = "/path/to/ca.pem"
+#
+# [docks.rollkit.rate_limit]
+# namespace_bytes_per_minute = 10485760
+# client_blobs_per_block = 2
+"#;
+
+#[test]
+fn template_is_valid() {
+    let config: Config = toml::from_str(TEMPLATE).unwrap();
+    assert_eq!(config.node.urls, vec!["ws://localhost:9988".to_string()]);
+    assert!(config.docks.sov.is_some());
+    assert!(config.docks.rollkit.is_none());
+}
+
+#[test]
+fn command_line_overrides_file_flags() {
+    let config: Config = toml::from_str("[node]\ncross_check = true\nno_retry = true\n").unwrap();
+    let params = config.merge_rpc(IkuraRpcParams {
+        cross_check: Some(false),
+        ..Default::default()
+    });
+    assert_eq!(params.cross_check, Some(false));
+    assert_eq!(params.no_retry, Some(true));
+}
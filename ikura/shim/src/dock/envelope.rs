@@ -0,0 +1,104 @@
+//! Wrapping of the submitted blobs into envelopes and unwrapping of the retrieved ones.
+//!
+//! See [`ikura_shim_common_envelope`] for the format.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use ikura_nmt::Namespace;
+use ikura_shim_common_envelope as envelope;
+
+/// How the blobs submitted through a dock are put into envelopes.
+///
+/// The default is to submit the blobs as is.
+#[derive(Clone, Debug, Default)]
+pub struct EnvelopeConfig {
+    /// If specified, the blobs are compressed with zstd at this level.
+    pub compression_level: Option<i32>,
+    /// The blobs submitted into the namespaces with a key are encrypted with it.
+    pub encryption_keys: EncryptionKeys,
+}
+
+/// The XChaCha20-Poly1305 keys, per namespace.
+///
+/// # Clone
+///
+/// The clones share the keys.
+#[derive(Clone, Default)]
+pub struct EncryptionKeys(Arc<BTreeMap<[u8; 16], [u8; 32]>>);
+
+impl EncryptionKeys {
+    pub fn new(keys: impl IntoIterator<Item = (Namespace, [u8; 32])>) -> Self {
+        Self(Arc::new(
+            keys.into_iter()
+                .map(|(namespace, key)| (namespace.to_raw_bytes(), key))
+                .collect(),
+        ))
+    }
+
+    /// Returns the key of the given namespace, if there is one.
+    pub fn get(&self, namespace: Namespace) -> Option<&[u8; 32]> {
+        self.0.get(&namespace.to_raw_bytes())
+    }
+}
+
+impl std::fmt::Debug for EncryptionKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Only the namespaces, never the keys.
+        f.debug_set()
+            .entries(
+                self.0
+                    .keys()
+                    .map(|namespace| Namespace::from_raw_bytes(*namespace)),
+            )
+            .finish()
+    }
+}
+
+impl EnvelopeConfig {
+    /// Prepares the blob for the submission into the given namespace.
+    ///
+    /// The blob is compressed first, since the ciphertext does not compress.
+    pub fn seal(&self, blob: Vec<u8>, namespace: Namespace) -> Result<Vec<u8>, envelope::Error> {
+        let blob = match self.compression_level {
+            Some(level) => envelope::compress(&blob, level)?,
+            None => blob,
+        };
+        match self.encryption_keys.get(namespace) {
+            Some(key) => {
+                let mut nonce = [0u8; envelope::NONCE_LEN];
+                getrandom::getrandom(&mut nonce)
+                    .expect("the OS random number generator is available; qed");
+                envelope::encrypt(&blob, key, nonce, &namespace.to_raw_bytes())
+            }
+            None => Ok(blob),
+        }
+    }
+
+    /// Takes the blob retrieved from the given namespace out of its envelope, if it is in one.
+    ///
+    /// The blobs are unwrapped regardless of the compression level of the dock, since they may
+    /// have been submitted by another shim. An encrypted blob can only be unwrapped if there is
+    /// a key for the namespace.
+    pub fn open(&self, blob: Vec<u8>, namespace: Namespace) -> Result<Vec<u8>, envelope::Error> {
+        let blob = match self.encryption_keys.get(namespace) {
+            Some(key) => decrypt(blob, key, namespace)?,
+            None => blob,
+        };
+        match envelope::open(&blob)? {
+            std::borrow::Cow::Borrowed(contents) if contents.len() == blob.len() => Ok(blob),
+            contents => Ok(contents.into_owned()),
+        }
+    }
+}
+
+/// Decrypts the blob retrieved from the given namespace, if it is encrypted.
+pub fn decrypt(
+    blob: Vec<u8>,
+    key: &[u8; 32],
+    namespace: Namespace,
+) -> Result<Vec<u8>, envelope::Error> {
+    match envelope::decrypt(&blob, key, &namespace.to_raw_bytes())? {
+        std::borrow::Cow::Borrowed(_) => Ok(blob),
+        std::borrow::Cow::Owned(plaintext) => Ok(plaintext),
+    }
+}
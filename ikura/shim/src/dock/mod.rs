@@ -1,6 +1,141 @@
 //! A dock is a component that provides an ad-hoc API consumed by the corresponding adapter in the
 //! rollup.
 
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+use crate::{ikura_rpc, metrics::Metrics, signer::BlobSigner};
+
+/// How often the balances of the submission keys are checked.
+const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+mod envelope;
+mod policy;
+mod queue;
+mod rate_limit;
 pub mod rollkit;
 mod rpc_error;
 pub mod sovereign;
+mod transport;
+
+pub use envelope::{EncryptionKeys, EnvelopeConfig};
+pub use policy::NamespacePolicy;
+pub use queue::SubmissionQueue;
+pub use rate_limit::{Limits, RateLimits};
+pub use transport::TlsConfig;
+
+/// The key used for signing blob submissions along with the tracking of its nonce.
+///
+/// # Clone
+///
+/// The clones share the nonce.
+#[derive(Clone)]
+pub struct SubmitKey {
+    signer: Arc<dyn BlobSigner>,
+    cur_nonce: Arc<Mutex<Option<u64>>>,
+}
+
+impl SubmitKey {
+    pub fn new(signer: Arc<dyn BlobSigner>) -> Self {
+        Self {
+            signer,
+            cur_nonce: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns the signer of the submissions.
+    pub fn signer(&self) -> &dyn BlobSigner {
+        &*self.signer
+    }
+
+    /// Generates a new nonce suitable for signing an extrinsic with this key.
+    pub async fn gen_nonce(&self, client: &ikura_rpc::Client) -> anyhow::Result<u64> {
+        let mut cur_nonce = self.cur_nonce.lock().await;
+        let nonce = match *cur_nonce {
+            Some(nonce) => nonce,
+            None => client.get_last_nonce(self.signer()).await?,
+        };
+        cur_nonce.replace(nonce + 1);
+        Ok(nonce)
+    }
+}
+
+/// A pool of keys used for signing blob submissions.
+///
+/// Each account is subject to the transaction pool limits of the node and the submissions signed
+/// by the same account are ordered by the nonce. Spreading the submissions across several accounts
+/// allows more of them to be in flight at the same time.
+///
+/// # Clone
+///
+/// The clones share the keys and their nonces. Docks that sign with the same keys must use clones
+/// of the same `SubmitKeyPool`, otherwise their submissions would compete for the same nonces.
+#[derive(Clone)]
+pub struct SubmitKeyPool {
+    keys: Arc<[SubmitKey]>,
+    next: Arc<AtomicUsize>,
+}
+
+impl SubmitKeyPool {
+    /// Creates a pool out of the given keys. Returns `None` if there are no keys.
+    pub fn new(signers: Vec<Arc<dyn BlobSigner>>) -> Option<Self> {
+        if signers.is_empty() {
+            return None;
+        }
+        Some(Self {
+            keys: signers.into_iter().map(SubmitKey::new).collect(),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Picks the key to sign the next submission with. The keys are picked in a round-robin
+    /// fashion.
+    pub fn next(&self) -> &SubmitKey {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.keys.len();
+        &self.keys[index]
+    }
+
+    /// Periodically checks the free balances of the keys, reporting them in the metrics.
+    ///
+    /// If `warn_threshold` is specified, a warning is logged for every key with the balance below
+    /// it. Never returns.
+    pub async fn monitor_balances(
+        self,
+        client: ikura_rpc::Client,
+        metrics: Metrics,
+        warn_threshold: Option<u128>,
+    ) {
+        let mut interval = tokio::time::interval(BALANCE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            for key in self.keys.iter() {
+                let address = key.signer().address();
+                let free = match client.get_free_balance(key.signer()).await {
+                    Ok(free) => free,
+                    Err(err) => {
+                        tracing::warn!(?err, %address, "failed to fetch the balance");
+                        continue;
+                    }
+                };
+                metrics.on_key_balance(&address, free);
+                match warn_threshold {
+                    Some(threshold) if free < threshold => {
+                        tracing::warn!(
+                            %address,
+                            free,
+                            threshold,
+                            "the balance of the submission key is running low"
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
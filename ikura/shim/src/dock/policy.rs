@@ -0,0 +1,132 @@
+//! The rules for which namespaces a dock accepts blob submissions into.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ikura_nmt::Namespace;
+
+/// Decides whether a submission into a namespace is allowed.
+///
+/// A shim may be shared by several rollups, and each submission is paid for with the shim's keys.
+/// The policy allows restricting the namespaces the dock submits into, and requiring a bearer
+/// token for submitting into a particular namespace.
+///
+/// The default policy allows submissions into any namespace without a token.
+#[derive(Clone, Debug, Default)]
+pub struct NamespacePolicy {
+    /// If specified, only the submissions into these namespaces are accepted.
+    allowlist: Option<BTreeSet<Namespace>>,
+    /// The tokens required for submitting into the namespaces. The namespaces not in the map do
+    /// not require a token.
+    tokens: BTreeMap<Namespace, String>,
+}
+
+/// The reason a submission was rejected by the [`NamespacePolicy`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The namespace is not in the allowlist.
+    NamespaceNotAllowed(Namespace),
+    /// The token is missing or does not match the token of the namespace.
+    Unauthorized(Namespace),
+}
+
+impl NamespacePolicy {
+    /// Creates a new policy. If `allowlist` is `None`, any namespace is allowed.
+    ///
+    /// The namespaces with a token are allowed even if they are not in the allowlist.
+    pub fn new(
+        allowlist: Option<Vec<Namespace>>,
+        tokens: BTreeMap<Namespace, String>,
+    ) -> NamespacePolicy {
+        let allowlist = allowlist.map(|allowlist| {
+            allowlist
+                .into_iter()
+                .chain(tokens.keys().copied())
+                .collect()
+        });
+        NamespacePolicy { allowlist, tokens }
+    }
+
+    /// Checks whether the submission into the given namespace presenting the given token is
+    /// allowed.
+    pub fn check(&self, namespace: Namespace, token: Option<&str>) -> Result<(), PolicyViolation> {
+        if let Some(allowlist) = &self.allowlist {
+            if !allowlist.contains(&namespace) {
+                return Err(PolicyViolation::NamespaceNotAllowed(namespace));
+            }
+        }
+        if let Some(expected) = self.tokens.get(&namespace) {
+            match token {
+                Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {}
+                _ => return Err(PolicyViolation::Unauthorized(namespace)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyViolation::NamespaceNotAllowed(namespace) => {
+                write!(
+                    f,
+                    "submissions into namespace {} are not allowed",
+                    namespace
+                )
+            }
+            PolicyViolation::Unauthorized(namespace) => {
+                write!(
+                    f,
+                    "a valid token is required to submit into namespace {}",
+                    namespace
+                )
+            }
+        }
+    }
+}
+
+/// Compares the byte strings in time independent of the position of the first mismatch, so that
+/// the token cannot be guessed byte by byte.
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Extracts the token from the value of an `Authorization` header, e.g. `Bearer <token>`.
+pub fn parse_bearer(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then_some(token.trim())
+}
+
+#[test]
+fn namespace_policy() {
+    let ns1 = Namespace::from_u128_be(1);
+    let ns2 = Namespace::from_u128_be(2);
+    let ns3 = Namespace::from_u128_be(3);
+
+    let open = NamespacePolicy::default();
+    assert_eq!(open.check(ns1, None), Ok(()));
+
+    let tokens = [(ns2, "secret".to_string())].into_iter().collect();
+    let policy = NamespacePolicy::new(Some(vec![ns1]), tokens);
+    assert_eq!(policy.check(ns1, None), Ok(()));
+    assert_eq!(policy.check(ns2, Some("secret")), Ok(()));
+    assert_eq!(
+        policy.check(ns2, Some("guess")),
+        Err(PolicyViolation::Unauthorized(ns2))
+    );
+    assert_eq!(
+        policy.check(ns2, None),
+        Err(PolicyViolation::Unauthorized(ns2))
+    );
+    assert_eq!(
+        policy.check(ns3, None),
+        Err(PolicyViolation::NamespaceNotAllowed(ns3))
+    );
+    assert_eq!(parse_bearer("Bearer secret"), Some("secret"));
+    assert_eq!(parse_bearer("Basic secret"), None);
+}
@@ -0,0 +1,507 @@
+//! The queue of the blob submissions.
+//!
+//! A block can only fit up to `MaxBlobs` blobs of up to `MaxTotalBlobSize` bytes in total. The
+//! transactions that don't fit are rejected with `ExhaustsResources` and linger in the transaction
+//! pool, holding back the submissions with the later nonces of the same account. Instead of
+//! submitting the blobs as soon as they arrive, the docks put them into the queue, which releases
+//! only as many of them as are expected to fit into the next block.
+//!
+//! The runtime resets `TotalBlobs` and `TotalBlobSize` at the end of every block, so they cannot be
+//! observed in the state. Instead, the queue follows the best blocks and counts the blobs in them
+//! itself. The space taken by the blobs of other submitters in the last block is taken as the
+//! estimate of how much they will take in the next one.
+//!
+//! A blob larger than `MaxTotalBlobSize` would never fit, so it is rejected right away.
+
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+};
+
+use ikura_nmt::Namespace;
+use tokio::sync::{oneshot, Notify};
+
+use super::{SubmitKey, SubmitKeyPool};
+use crate::{ikura_rpc, metrics::Metrics};
+
+/// The maximum number of blobs waiting in the queue. Further submissions are rejected.
+const MAX_PENDING: usize = 1024;
+
+/// The reason a submission through the [`SubmissionQueue`] failed.
+pub enum SubmitError {
+    /// The queue is full.
+    QueueFull,
+    /// The blob is larger than the blobs of a whole block can be.
+    TooLarge { blob_len: usize, max: u32 },
+    /// Failed to obtain the limits on the blobs in a block.
+    Limits(anyhow::Error),
+    /// Failed to obtain the nonce for the submission.
+    Nonce(anyhow::Error),
+    /// Failed to create the submission extrinsic.
+    MakeExtrinsic(anyhow::Error),
+    /// Failed to submit the extrinsic or to get it finalized.
+    Submit(anyhow::Error),
+}
+
+/// The queue of the blob submissions, shared by the docks.
+///
+/// The blobs are released in the order of their priority and, among the blobs of the same
+/// priority, in the order of arrival. A blob that does not fit into the space left in the next
+/// block is skipped in favor of the smaller ones behind it.
+///
+/// The nonce is only assigned when a blob is released, one blob after another, so that the order
+/// of the nonces of an account matches the order the blobs are released in.
+///
+/// # Clone
+///
+/// The clones share the queue.
+#[derive(Clone)]
+pub struct SubmissionQueue(Arc<Inner>);
+
+struct Inner {
+    client: ikura_rpc::Client,
+    submit_keys: SubmitKeyPool,
+    metrics: Metrics,
+    state: Mutex<State>,
+    /// Notified when a blob is added to the queue.
+    enqueued: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    pending: BinaryHeap<Pending>,
+    /// The blobs released but not yet seen in a best block.
+    in_flight: Vec<InFlight>,
+    /// The space left in the next block.
+    budget: Budget,
+    /// The limits on the blobs in a block, as of the last best block.
+    limits: Option<ikura_rpc::BlockLimits>,
+    next_seq: u64,
+    next_in_flight_id: u64,
+}
+
+#[derive(Default)]
+struct Budget {
+    blobs: u32,
+    bytes: u32,
+}
+
+impl Budget {
+    fn fits(&self, blob_len: u32) -> bool {
+        self.blobs >= 1 && self.bytes >= blob_len
+    }
+
+    fn take(&mut self, blob_len: u32) {
+        self.blobs -= 1;
+        self.bytes -= blob_len;
+    }
+}
+
+struct Pending {
+    priority: f64,
+    seq: u64,
+    blob: Vec<u8>,
+    /// The length of the `blob`, which is known to fit into `u32`.
+    blob_len: u32,
+    namespace: Namespace,
+    result_tx: oneshot::Sender<Result<([u8; 32], u32), SubmitError>>,
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The higher priority goes first, then the earlier arrival.
+        self.priority
+            .total_cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+struct InFlight {
+    id: u64,
+    sender: [u8; 32],
+    blob_hash: [u8; 32],
+    blob_len: u32,
+    /// Whether the blob was seen in the last best block.
+    included: bool,
+}
+
+/// A blob released from the queue, to be signed with the `submit_key`.
+struct Released {
+    id: u64,
+    submit_key: SubmitKey,
+    pending: Pending,
+}
+
+impl State {
+    /// Takes the pending blobs fitting into the budget, in the order they are released in, and
+    /// records them as in flight. The rest are kept pending.
+    fn release(&mut self, submit_keys: &SubmitKeyPool) -> Vec<Released> {
+        let mut released = Vec::new();
+        let mut kept = Vec::new();
+        // The sorted vector is in the ascending order, so it is walked from the back.
+        for pending in std::mem::take(&mut self.pending)
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+        {
+            if !self.budget.fits(pending.blob_len) {
+                kept.push(pending);
+                continue;
+            }
+            self.budget.take(pending.blob_len);
+            let submit_key = submit_keys.next().clone();
+            let id = self.next_in_flight_id;
+            self.next_in_flight_id += 1;
+            self.in_flight.push(InFlight {
+                id,
+                sender: submit_key.signer().public_key(),
+                blob_hash: sha2_hash(&pending.blob),
+                blob_len: pending.blob_len,
+                included: false,
+            });
+            released.push(Released {
+                id,
+                submit_key,
+                pending,
+            });
+        }
+        self.pending = kept.into();
+        released
+    }
+}
+
+/// Checks that a blob of the given length can ever be released under the given limits. Returns
+/// the length as `u32`.
+fn check_blob_len(blob_len: usize, limits: &ikura_rpc::BlockLimits) -> Result<u32, SubmitError> {
+    match u32::try_from(blob_len) {
+        Ok(len) if len <= limits.max_total_blob_size => Ok(len),
+        _ => Err(SubmitError::TooLarge {
+            blob_len,
+            max: limits.max_total_blob_size,
+        }),
+    }
+}
+
+impl SubmissionQueue {
+    /// Creates the queue signing the submissions with the given keys, and spawns the task
+    /// following the best blocks.
+    pub fn start(client: ikura_rpc::Client, submit_keys: SubmitKeyPool, metrics: Metrics) -> Self {
+        let queue = SubmissionQueue(Arc::new(Inner {
+            client,
+            submit_keys,
+            metrics,
+            state: Mutex::new(State::default()),
+            enqueued: Notify::new(),
+        }));
+        tokio::spawn(queue.clone().run());
+        queue
+    }
+
+    /// Submits the blob into the given namespace once there is space for it. Returns the block
+    /// hash in which the blob was included and the extrinsic index.
+    pub async fn submit(
+        &self,
+        blob: Vec<u8>,
+        namespace: Namespace,
+        priority: f64,
+    ) -> Result<([u8; 32], u32), SubmitError> {
+        let blob_len = check_blob_len(blob.len(), &self.limits().await?)?;
+        let (result_tx, result_rx) = oneshot::channel();
+        {
+            let mut state = self.0.state.lock().unwrap();
+            if state.pending.len() >= MAX_PENDING {
+                return Err(SubmitError::QueueFull);
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.pending.push(Pending {
+                priority,
+                seq,
+                blob,
+                blob_len,
+                namespace,
+                result_tx,
+            });
+            self.0.metrics.on_submission_queue_len(state.pending.len());
+        }
+        self.0.enqueued.notify_one();
+        result_rx.await.unwrap_or_else(|_| {
+            Err(SubmitError::Submit(anyhow::anyhow!(
+                "the submission was dropped"
+            )))
+        })
+    }
+
+    /// Returns the limits on the blobs in a block, fetching them if no best block was seen yet.
+    async fn limits(&self) -> Result<ikura_rpc::BlockLimits, SubmitError> {
+        let known_limits = self.0.state.lock().unwrap().limits;
+        if let Some(limits) = known_limits {
+            return Ok(limits);
+        }
+        self.0
+            .client
+            .block_limits()
+            .await
+            .map_err(SubmitError::Limits)
+    }
+
+    /// Follows the best blocks, replenishing the budget, and releases the blobs. Never returns.
+    async fn run(self) {
+        let mut best_hash = None;
+        loop {
+            tokio::select! {
+                new_best_hash = self.0.client.await_best_block_change(best_hash) => {
+                    best_hash = Some(new_best_hash);
+                    if let Err(err) = self.on_best_block(new_best_hash).await {
+                        tracing::warn!(?err, "failed to account for the new best block");
+                    }
+                }
+                _ = self.0.enqueued.notified() => {}
+            }
+            self.release().await;
+        }
+    }
+
+    /// Recomputes the budget for the next block, given the new best block.
+    async fn on_best_block(&self, block_hash: [u8; 32]) -> anyhow::Result<()> {
+        let limits = self.0.client.block_limits().await?;
+        let block = self.0.client.get_block_at(Some(block_hash)).await?;
+
+        let mut state = self.0.state.lock().unwrap();
+        let (mut others_blobs, mut others_bytes) = (0u32, 0u32);
+        for blob in &block.blobs {
+            let blob_hash = blob.sha2_hash();
+            let ours = state.in_flight.iter_mut().find(|in_flight| {
+                !in_flight.included
+                    && in_flight.sender == blob.sender
+                    && in_flight.blob_hash == blob_hash
+            });
+            match ours {
+                Some(in_flight) => in_flight.included = true,
+                None => {
+                    let blob_len = u32::try_from(blob.data.len()).unwrap_or(u32::MAX);
+                    others_blobs = others_blobs.saturating_add(1);
+                    others_bytes = others_bytes.saturating_add(blob_len);
+                }
+            }
+        }
+        let (in_flight_blobs, in_flight_bytes) = state
+            .in_flight
+            .iter()
+            .filter(|in_flight| !in_flight.included)
+            .fold((0u32, 0u32), |(blobs, bytes), in_flight| {
+                (
+                    blobs.saturating_add(1),
+                    bytes.saturating_add(in_flight.blob_len),
+                )
+            });
+        state.budget = Budget {
+            blobs: limits
+                .max_blobs
+                .saturating_sub(others_blobs)
+                .saturating_sub(in_flight_blobs),
+            bytes: limits
+                .max_total_blob_size
+                .saturating_sub(others_bytes)
+                .saturating_sub(in_flight_bytes),
+        };
+        state.limits = Some(limits);
+        Ok(())
+    }
+
+    /// Releases the pending blobs that fit into the budget.
+    ///
+    /// The nonces are assigned here, one released blob after another, rather than by the tasks
+    /// submitting them, so that they follow the order of release.
+    async fn release(&self) {
+        let released = {
+            let mut state = self.0.state.lock().unwrap();
+            let released = state.release(&self.0.submit_keys);
+            self.0.metrics.on_submission_queue_len(state.pending.len());
+            released
+        };
+        for Released {
+            id,
+            submit_key,
+            pending,
+        } in released
+        {
+            match submit_key.gen_nonce(&self.0.client).await {
+                Ok(nonce) => {
+                    tokio::spawn(self.clone().submit_now(id, submit_key, nonce, pending));
+                }
+                Err(err) => self.finish(id, pending, Err(SubmitError::Nonce(err))),
+            }
+        }
+    }
+
+    async fn submit_now(self, id: u64, submit_key: SubmitKey, nonce: u64, pending: Pending) {
+        let client = &self.0.client;
+        let res = async {
+            let extrinsic = client
+                .make_blob_extrinsic(
+                    pending.blob.clone(),
+                    pending.namespace,
+                    submit_key.signer(),
+                    nonce,
+                )
+                .await
+                .map_err(SubmitError::MakeExtrinsic)?;
+            client
+                .submit_blob(&extrinsic)
+                .await
+                .map_err(SubmitError::Submit)
+        }
+        .await;
+        self.finish(id, pending, res);
+    }
+
+    /// Forgets the in-flight blob and reports the result of its submission.
+    fn finish(&self, id: u64, pending: Pending, res: Result<([u8; 32], u32), SubmitError>) {
+        self.0
+            .state
+            .lock()
+            .unwrap()
+            .in_flight
+            .retain(|in_flight| in_flight.id != id);
+        let _ = pending.result_tx.send(res);
+    }
+}
+
+fn sha2_hash(data: &[u8]) -> [u8; 32] {
+    use sha2::Digest;
+    sha2::Sha256::digest(data).into()
+}
+
+#[test]
+fn pending_order() {
+    let pending = |priority, seq| Pending {
+        priority,
+        seq,
+        blob: vec![],
+        blob_len: 0,
+        namespace: Namespace::from_u128_be(1),
+        result_tx: oneshot::channel().0,
+    };
+    let mut heap: BinaryHeap<Pending> = [
+        pending(0.0, 0),
+        pending(1.0, 1),
+        pending(0.0, 2),
+        pending(2.5, 3),
+    ]
+    .into_iter()
+    .collect();
+    let order: Vec<u64> = std::iter::from_fn(|| heap.pop().map(|p| p.seq)).collect();
+    assert_eq!(order, vec![3, 1, 0, 2]);
+}
+
+#[cfg(test)]
+fn test_pending(priority: f64, seq: u64, blob_len: u32) -> Pending {
+    Pending {
+        priority,
+        seq,
+        blob: vec![seq as u8; blob_len as usize],
+        blob_len,
+        namespace: Namespace::from_u128_be(1),
+        result_tx: oneshot::channel().0,
+    }
+}
+
+#[test]
+fn release_order() {
+    let submit_keys = SubmitKeyPool::new(vec![
+        Arc::new(crate::key::alice()) as Arc<dyn crate::signer::BlobSigner>
+    ])
+    .unwrap();
+    let mut state = State {
+        budget: Budget {
+            blobs: 3,
+            bytes: 100,
+        },
+        ..State::default()
+    };
+    state.pending = [
+        test_pending(0.0, 0, 10),
+        test_pending(1.0, 1, 80),
+        test_pending(1.0, 2, 30),
+        test_pending(0.0, 3, 10),
+        test_pending(0.0, 4, 10),
+    ]
+    .into_iter()
+    .collect();
+
+    // The blob 2 does not fit after the blob 1 and is skipped in favor of the smaller ones. The
+    // budget allows for three blobs only.
+    let released: Vec<u64> = state
+        .release(&submit_keys)
+        .into_iter()
+        .map(|released| released.pending.seq)
+        .collect();
+    assert_eq!(released, vec![1, 0, 3]);
+    assert_eq!(state.in_flight.len(), 3);
+    assert_eq!(state.in_flight[0].blob_len, 80);
+
+    // The rest are released in the same order once there is space.
+    state.budget = Budget {
+        blobs: 10,
+        bytes: 100,
+    };
+    let released: Vec<u64> = state
+        .release(&submit_keys)
+        .into_iter()
+        .map(|released| released.pending.seq)
+        .collect();
+    assert_eq!(released, vec![2, 4]);
+    assert!(state.pending.is_empty());
+}
+
+#[test]
+fn oversize_blobs_rejected() {
+    let limits = ikura_rpc::BlockLimits {
+        max_blobs: 10,
+        max_total_blob_size: 100,
+    };
+    assert!(matches!(check_blob_len(100, &limits), Ok(100)));
+    assert!(matches!(
+        check_blob_len(101, &limits),
+        Err(SubmitError::TooLarge {
+            blob_len: 101,
+            max: 100
+        })
+    ));
+    assert!(matches!(
+        check_blob_len(usize::MAX, &limits),
+        Err(SubmitError::TooLarge { .. })
+    ));
+
+    // A blob within the limits but larger than the space left in the block is kept pending.
+    let submit_keys = SubmitKeyPool::new(vec![
+        Arc::new(crate::key::alice()) as Arc<dyn crate::signer::BlobSigner>
+    ])
+    .unwrap();
+    let mut state = State {
+        budget: Budget {
+            blobs: 10,
+            bytes: 50,
+        },
+        ..State::default()
+    };
+    state.pending.push(test_pending(0.0, 0, 100));
+    assert!(state.release(&submit_keys).is_empty());
+    assert_eq!(state.pending.len(), 1);
+}
@@ -0,0 +1,409 @@
+//! Limits on the rate of blob submissions.
+//!
+//! Every submission is paid for with the shim's keys, so a misbehaving rollup node could drain the
+//! accounts by spamming submissions. The limits are applied per namespace and per client IP
+//! address, each of them on the number of bytes per minute and the number of blobs per block.
+//!
+//! The usage of the namespaces and the clients idle for [`IDLE_TTL`] is forgotten, and at most
+//! [`MAX_CLIENTS`] clients are tracked at once, so that the memory used is bounded.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ikura_nmt::Namespace;
+
+use crate::metrics::Metrics;
+
+/// The maximum number of clients tracked at once. The submissions of new clients are rejected
+/// while this many clients were active within the last [`IDLE_TTL`].
+pub const MAX_CLIENTS: usize = 65_536;
+
+/// The time after which the usage of an idle namespace or client is forgotten. By then, its byte
+/// allowance is fully replenished.
+pub const IDLE_TTL: Duration = Duration::from_secs(60);
+
+/// The limits applied to a single namespace or a single client.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// The maximum number of blob bytes submitted within a minute.
+    ///
+    /// Enforced with a token bucket, so a burst of up to a minute worth of bytes is allowed.
+    pub bytes_per_minute: Option<u64>,
+    /// The maximum number of blobs submitted per block.
+    ///
+    /// The block is the last finalized block observed by the shim, i.e. the counter is reset
+    /// every time a new block is finalized.
+    pub blobs_per_block: Option<u32>,
+}
+
+impl Limits {
+    fn is_unlimited(&self) -> bool {
+        self.bytes_per_minute.is_none() && self.blobs_per_block.is_none()
+    }
+}
+
+/// The limits of a dock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RateLimits {
+    pub per_namespace: Limits,
+    pub per_client: Limits,
+}
+
+impl RateLimits {
+    /// Returns true if any of the per-client limits is set.
+    pub fn limits_clients(&self) -> bool {
+        !self.per_client.is_unlimited()
+    }
+}
+
+/// The reason a submission was rejected by the [`RateLimiter`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum RateLimitExceeded {
+    NamespaceBytes(Namespace),
+    NamespaceBlobs(Namespace),
+    ClientBytes(IpAddr),
+    ClientBlobs(IpAddr),
+    TooManyClients(IpAddr),
+}
+
+impl RateLimitExceeded {
+    /// The scope of the limit, as reported in the metrics.
+    fn scope(&self) -> &'static str {
+        match self {
+            RateLimitExceeded::NamespaceBytes(_) | RateLimitExceeded::NamespaceBlobs(_) => {
+                "namespace"
+            }
+            RateLimitExceeded::ClientBytes(_)
+            | RateLimitExceeded::ClientBlobs(_)
+            | RateLimitExceeded::TooManyClients(_) => "client",
+        }
+    }
+
+    /// The kind of the limit, as reported in the metrics.
+    fn kind(&self) -> &'static str {
+        match self {
+            RateLimitExceeded::NamespaceBytes(_) | RateLimitExceeded::ClientBytes(_) => {
+                "bytes_per_minute"
+            }
+            RateLimitExceeded::NamespaceBlobs(_) | RateLimitExceeded::ClientBlobs(_) => {
+                "blobs_per_block"
+            }
+            RateLimitExceeded::TooManyClients(_) => "clients",
+        }
+    }
+}
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitExceeded::NamespaceBytes(namespace) => {
+                write!(
+                    f,
+                    "bytes per minute limit exceeded for namespace {}",
+                    namespace
+                )
+            }
+            RateLimitExceeded::NamespaceBlobs(namespace) => {
+                write!(
+                    f,
+                    "blobs per block limit exceeded for namespace {}",
+                    namespace
+                )
+            }
+            RateLimitExceeded::ClientBytes(ip) => {
+                write!(f, "bytes per minute limit exceeded for client {}", ip)
+            }
+            RateLimitExceeded::ClientBlobs(ip) => {
+                write!(f, "blobs per block limit exceeded for client {}", ip)
+            }
+            RateLimitExceeded::TooManyClients(ip) => {
+                write!(f, "too many clients, rejecting the new client {}", ip)
+            }
+        }
+    }
+}
+
+/// Tracks the submissions and enforces the [`RateLimits`].
+///
+/// # Clone
+///
+/// The clones share the usage.
+#[derive(Clone)]
+pub struct RateLimiter(Arc<Inner>);
+
+struct Inner {
+    limits: RateLimits,
+    dock: &'static str,
+    metrics: Metrics,
+    usage: Mutex<Usage>,
+}
+
+struct Usage {
+    namespaces: HashMap<[u8; 16], Counters>,
+    clients: HashMap<IpAddr, Counters>,
+    /// When the idle namespaces and clients were last forgotten.
+    swept_at: Instant,
+}
+
+impl Usage {
+    fn new(now: Instant) -> Self {
+        Self {
+            namespaces: HashMap::new(),
+            clients: HashMap::new(),
+            swept_at: now,
+        }
+    }
+
+    /// Forgets the namespaces and clients idle for [`IDLE_TTL`]. Done at most once per
+    /// [`IDLE_TTL`], or once per second while the clients are at capacity.
+    fn sweep(&mut self, now: Instant) {
+        let interval = if self.clients.len() < MAX_CLIENTS {
+            IDLE_TTL
+        } else {
+            Duration::from_secs(1)
+        };
+        if now.duration_since(self.swept_at) < interval {
+            return;
+        }
+        self.swept_at = now;
+        let is_active = |counters: &Counters| now.duration_since(counters.seen_at) < IDLE_TTL;
+        self.namespaces.retain(|_, counters| is_active(counters));
+        self.clients.retain(|_, counters| is_active(counters));
+    }
+}
+
+/// The usage of a single namespace or client.
+struct Counters {
+    /// The bytes that can be submitted right now.
+    available_bytes: f64,
+    /// When `available_bytes` was last replenished.
+    refilled_at: Instant,
+    /// The block the `blobs` are counted in.
+    block: u64,
+    /// The number of blobs submitted in the `block`.
+    blobs: u32,
+    /// When the namespace or client last submitted, or attempted to.
+    seen_at: Instant,
+}
+
+impl Counters {
+    fn new(limits: &Limits, now: Instant) -> Self {
+        Self {
+            available_bytes: limits.bytes_per_minute.unwrap_or(0) as f64,
+            refilled_at: now,
+            block: 0,
+            blobs: 0,
+            seen_at: now,
+        }
+    }
+
+    /// Brings the counters up to date with the given time and block. Returns the reason the
+    /// submission of `blobs` blobs with `bytes` bytes in total would be rejected, if any.
+    fn check(
+        &mut self,
+        limits: &Limits,
+        now: Instant,
+        block: u64,
+        bytes: u64,
+        blobs: u32,
+    ) -> Option<Kind> {
+        self.seen_at = now;
+        if let Some(bytes_per_minute) = limits.bytes_per_minute {
+            let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+            self.available_bytes = (self.available_bytes
+                + elapsed * bytes_per_minute as f64 / 60.0)
+                .min(bytes_per_minute as f64);
+            self.refilled_at = now;
+            if (bytes as f64) > self.available_bytes {
+                return Some(Kind::Bytes);
+            }
+        }
+        if block != self.block {
+            self.block = block;
+            self.blobs = 0;
+        }
+        if let Some(blobs_per_block) = limits.blobs_per_block {
+            if self.blobs.saturating_add(blobs) > blobs_per_block {
+                return Some(Kind::Blobs);
+            }
+        }
+        None
+    }
+
+    fn consume(&mut self, bytes: u64, blobs: u32) {
+        self.available_bytes -= bytes as f64;
+        self.blobs = self.blobs.saturating_add(blobs);
+    }
+}
+
+enum Kind {
+    Bytes,
+    Blobs,
+}
+
+impl RateLimiter {
+    /// Creates a new limiter for the given dock. The limits are reported in the metrics right
+    /// away.
+    pub fn new(limits: RateLimits, dock: &'static str, metrics: Metrics) -> Self {
+        for (scope, scope_limits) in [
+            ("namespace", &limits.per_namespace),
+            ("client", &limits.per_client),
+        ] {
+            if let Some(bytes_per_minute) = scope_limits.bytes_per_minute {
+                metrics.on_rate_limit(dock, scope, "bytes_per_minute", bytes_per_minute);
+            }
+            if let Some(blobs_per_block) = scope_limits.blobs_per_block {
+                metrics.on_rate_limit(dock, scope, "blobs_per_block", blobs_per_block as u64);
+            }
+        }
+        Self(Arc::new(Inner {
+            limits,
+            dock,
+            metrics,
+            usage: Mutex::new(Usage::new(Instant::now())),
+        }))
+    }
+
+    /// Accounts for the submission of `blobs` blobs with `bytes` bytes in total into the given
+    /// namespace by the given client, at the given block.
+    ///
+    /// If any of the limits would be exceeded, the submission is not accounted for and an error
+    /// is returned.
+    pub fn check(
+        &self,
+        namespace: Namespace,
+        client: Option<IpAddr>,
+        block: u64,
+        bytes: u64,
+        blobs: u32,
+    ) -> Result<(), RateLimitExceeded> {
+        let res = self.check_at(Instant::now(), namespace, client, block, bytes, blobs);
+        if let Err(ref exceeded) = res {
+            self.0
+                .metrics
+                .on_rate_limited(self.0.dock, exceeded.scope(), exceeded.kind());
+        }
+        res
+    }
+
+    fn check_at(
+        &self,
+        now: Instant,
+        namespace: Namespace,
+        client: Option<IpAddr>,
+        block: u64,
+        bytes: u64,
+        blobs: u32,
+    ) -> Result<(), RateLimitExceeded> {
+        let limits = &self.0.limits;
+        let mut usage = self.0.usage.lock().unwrap();
+        usage.sweep(now);
+        let Usage {
+            namespaces,
+            clients,
+            ..
+        } = &mut *usage;
+
+        let mut namespace_counters = None;
+        if !limits.per_namespace.is_unlimited() {
+            let counters = namespaces
+                .entry(namespace.to_raw_bytes())
+                .or_insert_with(|| Counters::new(&limits.per_namespace, now));
+            match counters.check(&limits.per_namespace, now, block, bytes, blobs) {
+                Some(Kind::Bytes) => return Err(RateLimitExceeded::NamespaceBytes(namespace)),
+                Some(Kind::Blobs) => return Err(RateLimitExceeded::NamespaceBlobs(namespace)),
+                None => namespace_counters = Some(counters),
+            }
+        }
+
+        let mut client_counters = None;
+        if let (Some(ip), false) = (client, limits.per_client.is_unlimited()) {
+            if clients.len() >= MAX_CLIENTS && !clients.contains_key(&ip) {
+                return Err(RateLimitExceeded::TooManyClients(ip));
+            }
+            let counters = clients
+                .entry(ip)
+                .or_insert_with(|| Counters::new(&limits.per_client, now));
+            match counters.check(&limits.per_client, now, block, bytes, blobs) {
+                Some(Kind::Bytes) => return Err(RateLimitExceeded::ClientBytes(ip)),
+                Some(Kind::Blobs) => return Err(RateLimitExceeded::ClientBlobs(ip)),
+                None => client_counters = Some(counters),
+            }
+        }
+
+        for counters in namespace_counters.into_iter().chain(client_counters) {
+            counters.consume(bytes, blobs);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn rate_limiter() {
+    let limits = RateLimits {
+        per_namespace: Limits {
+            bytes_per_minute: Some(1000),
+            blobs_per_block: None,
+        },
+        per_client: Limits {
+            bytes_per_minute: None,
+            blobs_per_block: Some(2),
+        },
+    };
+    let limiter = RateLimiter::new(limits, "test", Metrics::new().unwrap());
+    let ns1 = Namespace::from_u128_be(1);
+    let ns2 = Namespace::from_u128_be(2);
+    let client = Some("10.0.0.1".parse().unwrap());
+
+    assert_eq!(limiter.check(ns1, client, 1, 600, 1), Ok(()));
+    assert_eq!(
+        limiter.check(ns1, client, 1, 600, 1),
+        Err(RateLimitExceeded::NamespaceBytes(ns1))
+    );
+    // The rejected submission did not count against the client.
+    assert_eq!(limiter.check(ns2, client, 1, 600, 1), Ok(()));
+    assert_eq!(
+        limiter.check(ns2, client, 1, 1, 1),
+        Err(RateLimitExceeded::ClientBlobs("10.0.0.1".parse().unwrap()))
+    );
+    // The blob counter is reset in the next block.
+    assert_eq!(limiter.check(ns2, client, 2, 1, 1), Ok(()));
+    // Unknown clients are only subject to the namespace limits.
+    assert_eq!(limiter.check(ns2, None, 2, 1, 100), Ok(()));
+}
+
+#[test]
+fn idle_clients_are_forgotten() {
+    let limits = RateLimits {
+        per_namespace: Limits::default(),
+        per_client: Limits {
+            bytes_per_minute: None,
+            blobs_per_block: Some(1),
+        },
+    };
+    let limiter = RateLimiter::new(limits, "test", Metrics::new().unwrap());
+    let ns = Namespace::from_u128_be(1);
+    let start = Instant::now();
+    let client = |i: u32| Some(IpAddr::from(i.to_be_bytes()));
+
+    for i in 0..MAX_CLIENTS as u32 {
+        assert_eq!(limiter.check_at(start, ns, client(i), 1, 1, 1), Ok(()));
+    }
+    // The known clients are still served, the new ones are rejected while the others are active.
+    assert_eq!(limiter.check_at(start, ns, client(0), 2, 1, 1), Ok(()));
+    assert_eq!(
+        limiter.check_at(start, ns, client(u32::MAX), 2, 1, 1),
+        Err(RateLimitExceeded::TooManyClients(client(u32::MAX).unwrap()))
+    );
+    // Once the clients are idle, they are forgotten and make room for the new ones.
+    let later = start + IDLE_TTL;
+    assert_eq!(
+        limiter.check_at(later, ns, client(u32::MAX), 2, 1, 1),
+        Ok(())
+    );
+    assert_eq!(limiter.0.usage.lock().unwrap().clients.len(), 1);
+}
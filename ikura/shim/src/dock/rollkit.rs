@@ -1,7 +1,6 @@
-use std::{collections::HashMap, fmt, sync::Arc};
-use tokio::sync::Mutex;
+use std::{collections::HashMap, fmt};
 use tonic::{transport::Server, Request, Response, Status};
-use tracing::info;
+use tracing::{debug, info, warn};
 
 use self::pbda::{
     da_service_server, Blob, CommitRequest, CommitResponse, GetIDsRequest, GetIDsResponse,
@@ -9,7 +8,15 @@ use self::pbda::{
     SubmitResponse, ValidateRequest, ValidateResponse,
 };
 
-use crate::{ikura_rpc, key::Keypair};
+use super::{
+    envelope::EnvelopeConfig,
+    policy::{parse_bearer, PolicyViolation},
+    queue::SubmitError,
+    rate_limit::{RateLimitExceeded, RateLimiter, RateLimits},
+    transport::{self, TlsConfig},
+    NamespacePolicy, SubmissionQueue,
+};
+use crate::{ikura_rpc, metrics::Metrics};
 
 pub mod pbda {
     tonic::include_proto!("da");
@@ -20,12 +27,32 @@ pub struct Config {
     /// The RPC client handle to the ikura node.
     pub client: ikura_rpc::Client,
 
-    /// The optional key used for signing when submitting blobs.
-    pub submit_key: Option<Keypair>,
+    /// The handle to the shim metrics.
+    pub metrics: Metrics,
+
+    /// The queue the blobs are submitted through. `None` if there are no keys for signing.
+    pub submit_queue: Option<SubmissionQueue>,
 
     /// The optional namespace to use, in case the namespace is not provided in the request.
     pub namespace: Option<ikura_nmt::Namespace>,
 
+    /// The policy for submissions into the namespace. Allows requiring a bearer token in the
+    /// `authorization` metadata of the submit requests.
+    pub policy: NamespacePolicy,
+
+    /// The limits on the rate of submissions.
+    pub rate_limits: RateLimits,
+
+    /// How the submitted blobs are put into envelopes. The retrieved blobs are always taken out of
+    /// their envelopes, and decrypted if there is a key for their namespace.
+    pub envelope: EnvelopeConfig,
+
+    /// If specified, the dock serves TLS connections only.
+    pub tls: Option<TlsConfig>,
+
+    /// If specified, the clients must present this token in the `x-api-token` metadata.
+    pub api_token: Option<String>,
+
     /// The address to listen on.
     pub address: String,
 
@@ -45,33 +72,56 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
             config.port
         )
     };
-    let dock = RollkitDock::new(config.client, config.submit_key, config.namespace);
-    let service = da_service_server::DaServiceServer::new(dock);
-    Server::builder()
-        .add_service(service)
-        .serve(listen_on)
-        .await?;
+    let limiter = RateLimiter::new(config.rate_limits, "rollkit", config.metrics.clone());
+    let dock = RollkitDock::new(
+        config.client,
+        config.metrics,
+        config.submit_queue,
+        config.namespace,
+        config.policy,
+        limiter,
+        config.envelope,
+    );
+    let service = da_service_server::DaServiceServer::with_interceptor(
+        dock,
+        transport::api_token_interceptor(config.api_token),
+    );
+    let mut builder = Server::builder();
+    if let Some(tls) = &config.tls {
+        builder = builder.tls_config(tls.tonic()?)?;
+    }
+    builder.add_service(service).serve(listen_on).await?;
     Ok(())
 }
 
 struct RollkitDock {
     client: ikura_rpc::Client,
-    submit_key: Option<Keypair>,
+    metrics: Metrics,
+    submit_queue: Option<SubmissionQueue>,
     namespace: Option<ikura_nmt::Namespace>,
-    cur_nonce: Arc<Mutex<Option<u64>>>,
+    policy: NamespacePolicy,
+    limiter: RateLimiter,
+    envelope: EnvelopeConfig,
 }
 
 impl RollkitDock {
     fn new(
         client: ikura_rpc::Client,
-        submit_key: Option<Keypair>,
+        metrics: Metrics,
+        submit_queue: Option<SubmissionQueue>,
         namespace: Option<ikura_nmt::Namespace>,
+        policy: NamespacePolicy,
+        limiter: RateLimiter,
+        envelope: EnvelopeConfig,
     ) -> Self {
         Self {
             client,
-            submit_key,
+            metrics,
+            submit_queue,
             namespace,
-            cur_nonce: Arc::new(Mutex::new(None)),
+            policy,
+            limiter,
+            envelope,
         }
     }
 }
@@ -82,6 +132,7 @@ impl da_service_server::DaService for RollkitDock {
         &self,
         request: Request<MaxBlobSizeRequest>,
     ) -> Result<Response<MaxBlobSizeResponse>, Status> {
+        self.metrics.on_rpc_request("rollkit", "max_blob_size");
         let MaxBlobSizeRequest {} = request.into_inner();
         const MAX_BLOB_SIZE: u64 = 100 * 1024;
         Ok(Response::new(MaxBlobSizeResponse {
@@ -90,6 +141,7 @@ impl da_service_server::DaService for RollkitDock {
     }
 
     async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        self.metrics.on_rpc_request("rollkit", "get");
         let GetRequest { ids } = request.into_inner();
         let mut cache = HashMap::new();
         let mut response = GetResponse { blobs: vec![] };
@@ -114,9 +166,24 @@ impl da_service_server::DaService for RollkitDock {
                 .iter()
                 .find(|b| b.extrinsic_index == blob_id.extrinsic_index)
             {
-                response.blobs.push(Blob {
-                    value: needle.data.clone(),
-                });
+                // A blob that is not in a well-formed envelope is served as stored on chain, the
+                // same as the Sovereign SDK adapter does, since it may come from another client.
+                // So is a blob that cannot be decrypted, rather than failing the whole request.
+                let value = match self.envelope.open(needle.data.clone(), needle.namespace) {
+                    Ok(value) => value,
+                    Err(
+                        err @ (ikura_shim_common_envelope::Error::Encrypted
+                        | ikura_shim_common_envelope::Error::Decryption),
+                    ) => {
+                        warn!("serving the blob at index {index} as stored on chain: {err}");
+                        needle.data.clone()
+                    }
+                    Err(err) => {
+                        debug!("serving the blob at index {index} as stored on chain: {err}");
+                        needle.data.clone()
+                    }
+                };
+                response.blobs.push(Blob { value });
             } else {
                 return Err(RollkitDockError::CantResolveBlobId(blob_id).into());
             }
@@ -129,6 +196,7 @@ impl da_service_server::DaService for RollkitDock {
         &self,
         request: Request<GetIDsRequest>,
     ) -> Result<Response<GetIDsResponse>, Status> {
+        self.metrics.on_rpc_request("rollkit", "get_ids");
         let GetIDsRequest { height } = request.into_inner();
         info!("retrieving IDs at {}", height);
         let block_hash = self.client.await_finalized_height(height).await;
@@ -155,73 +223,75 @@ impl da_service_server::DaService for RollkitDock {
         &self,
         request: Request<SubmitRequest>,
     ) -> Result<Response<SubmitResponse>, Status> {
-        let submit_key = self
-            .submit_key
+        self.metrics.on_rpc_request("rollkit", "submit");
+        let submit_queue = self
+            .submit_queue
             .as_ref()
-            .cloned()
             .ok_or_else(|| RollkitDockError::NoSigningKey)?;
         let namespace = self
             .namespace
             .ok_or_else(|| RollkitDockError::NamespaceNotProvided)?;
-        let SubmitRequest {
-            blobs,
-            gas_price: _,
-        } = request.into_inner();
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_bearer);
+        self.policy
+            .check(namespace, token)
+            .map_err(RollkitDockError::Policy)?;
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
+        let SubmitRequest { blobs, gas_price } = request.into_inner();
         let blob_n = blobs.len();
-
-        // First, prepare a list of extrinsics to submit.
-        let mut extrinsics = vec![];
-        for (i, blob) in blobs.into_iter().enumerate() {
-            let data_hash = sha2_hash(&blob.value);
-            let nonce = self
-                .gen_nonce()
+        let blobs = blobs
+            .into_iter()
+            .enumerate()
+            .map(|(index, blob)| {
+                self.envelope
+                    .seal(blob.value, namespace)
+                    .map_err(|err| RollkitDockError::SealEnvelope { index, err })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The blobs of a request are accepted or rejected together.
+        let bytes = blobs.iter().map(|blob| blob.len() as u64).sum();
+        let block = self.client.finalized_height().await;
+        self.limiter
+            .check(namespace, client_ip, block, bytes, blob_n as u32)
+            .map_err(RollkitDockError::RateLimited)?;
+
+        // Submit the blobs through the queue in parallel and collect the results. The gas price
+        // offered by the rollup is used as the priority in the queue.
+        let futs = blobs.into_iter().enumerate().map(|(i, blob)| async move {
+            let data_hash = sha2_hash(&blob);
+            info!(
+                "submitting blob {i}/{blob_n} (0x{}) to namespace {}",
+                hex::encode(&data_hash),
+                namespace
+            );
+            let (block_hash, extrinsic_index) = submit_queue
+                .submit(blob, namespace, gas_price)
                 .await
-                .map_err(RollkitDockError::NonceGeneration)?;
-            let extrinsic = self
+                .map_err(RollkitDockError::Submit)?;
+            // TODO: getting the whole block is a bit inefficient, consider optimizing.
+            let block_number = match self
                 .client
-                .make_blob_extrinsic(blob.value, namespace, &submit_key, nonce)
+                .await_block_at(Some(block_hash))
                 .await
-                .map_err(RollkitDockError::MakeSubmitBlobExtrinsic)?;
-            extrinsics.push((i, data_hash, extrinsic));
-        }
-
-        // Then, submit the extrinsics in parallel and collect the results.
-        let futs = extrinsics
-            .into_iter()
-            .map(|(i, data_hash, extrinsic)| async move {
-                info!(
-                    "submitting blob {i}/{blob_n} (0x{}) to namespace {}",
-                    hex::encode(&data_hash),
-                    namespace
-                );
-                let (block_hash, extrinsic_index) = self
-                    .client
-                    .submit_blob(&extrinsic)
-                    .await
-                    .map_err(RollkitDockError::SubmitBlob)?;
-                // TODO: getting the whole block is a bit inefficient, consider optimizing.
-                let block_number = match self
-                    .client
-                    .await_block_at(Some(block_hash))
-                    .await
-                    .map(|block| block.number)
-                {
-                    Ok(block_number) => block_number,
-                    Err(err) => {
-                        return Err(RollkitDockError::SubmitRetrieveBlockNumber {
-                            block_hash,
-                            err,
-                        });
-                    }
-                };
-                let blob_id = BlobId {
-                    block_number,
-                    extrinsic_index,
-                    data_hash,
-                };
-                info!("blob landed: {blob_id}");
-                Ok(blob_id.into())
-            });
+                .map(|block| block.number)
+            {
+                Ok(block_number) => block_number,
+                Err(err) => {
+                    return Err(RollkitDockError::SubmitRetrieveBlockNumber { block_hash, err });
+                }
+            };
+            let blob_id = BlobId {
+                block_number,
+                extrinsic_index,
+                data_hash,
+            };
+            info!("blob landed: {blob_id}");
+            Ok(blob_id.into())
+        });
 
         let ids: Vec<_> = futures::future::try_join_all(futs).await?;
         let proofs = ids.iter().map(|_| pbda::Proof { value: vec![] }).collect();
@@ -234,6 +304,7 @@ impl da_service_server::DaService for RollkitDock {
     ) -> Result<Response<ValidateResponse>, Status> {
         // TODO: implement
         // https://github.com/thrumdev/blobs/issues/257
+        self.metrics.on_rpc_request("rollkit", "validate");
         let ValidateRequest { ids, .. } = request.into_inner();
         let response = ValidateResponse {
             results: ids.into_iter().map(|_| true).collect(),
@@ -247,6 +318,7 @@ impl da_service_server::DaService for RollkitDock {
     ) -> Result<Response<CommitResponse>, Status> {
         // TODO: implement
         // https://github.com/thrumdev/blobs/issues/257
+        self.metrics.on_rpc_request("rollkit", "commit");
         let CommitRequest { blobs, .. } = request.into_inner();
         let response = CommitResponse {
             commitments: blobs
@@ -258,23 +330,6 @@ impl da_service_server::DaService for RollkitDock {
     }
 }
 
-impl RollkitDock {
-    /// Generates a new nonce suitable for signing an extrinsic from the signer.
-    async fn gen_nonce(&self) -> anyhow::Result<u64> {
-        let submit_key = self
-            .submit_key
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("no key for signing blobs"))?; // should be unreachable
-        let mut cur_nonce = self.cur_nonce.lock().await;
-        let nonce = match *cur_nonce {
-            Some(nonce) => nonce,
-            None => self.client.get_last_nonce(&submit_key).await?,
-        };
-        cur_nonce.replace(nonce + 1);
-        Ok(nonce)
-    }
-}
-
 fn sha2_hash(data: &[u8]) -> [u8; 32] {
     use sha2::Digest;
     sha2::Sha256::digest(data).into()
@@ -282,9 +337,7 @@ fn sha2_hash(data: &[u8]) -> [u8; 32] {
 
 enum RollkitDockError {
     NoSigningKey,
-    MakeSubmitBlobExtrinsic(anyhow::Error),
-    SubmitBlob(anyhow::Error),
-    NonceGeneration(anyhow::Error),
+    Submit(SubmitError),
     GetInvalidBlobId {
         index: usize,
     },
@@ -297,6 +350,12 @@ enum RollkitDockError {
     },
     CantResolveBlobId(BlobId),
     NamespaceNotProvided,
+    Policy(PolicyViolation),
+    RateLimited(RateLimitExceeded),
+    SealEnvelope {
+        index: usize,
+        err: ikura_shim_common_envelope::Error,
+    },
 }
 
 impl From<RollkitDockError> for Status {
@@ -306,11 +365,24 @@ impl From<RollkitDockError> for Status {
             NoSigningKey => {
                 Status::failed_precondition("the key for signing blobs is not provided")
             }
-            MakeSubmitBlobExtrinsic(err) => {
+            Submit(SubmitError::QueueFull) => {
+                Status::resource_exhausted("the submission queue is full")
+            }
+            Submit(SubmitError::TooLarge { blob_len, max }) => Status::invalid_argument(format!(
+                "the blob of {blob_len} bytes exceeds the maximum of {max} bytes per block"
+            )),
+            Submit(SubmitError::Limits(err)) => {
+                Status::internal(format!("failed to obtain the block limits: {err}"))
+            }
+            Submit(SubmitError::MakeExtrinsic(err)) => {
                 Status::internal(format!("failed to create a submit blob extrinsic: {err}"))
             }
-            SubmitBlob(err) => Status::internal(format!("failed to submit blob: {err}")),
-            NonceGeneration(err) => Status::internal(format!("failed to generate a nonce: {err}")),
+            Submit(SubmitError::Submit(err)) => {
+                Status::internal(format!("failed to submit blob: {err}"))
+            }
+            Submit(SubmitError::Nonce(err)) => {
+                Status::internal(format!("failed to generate a nonce: {err}"))
+            }
             GetInvalidBlobId { index } => {
                 Status::invalid_argument(format!("not a valid blob ID at index {index}"))
             }
@@ -329,6 +401,16 @@ impl From<RollkitDockError> for Status {
                 "no namespace provided, and no default names
             pace set",
             ),
+            Policy(violation @ PolicyViolation::NamespaceNotAllowed(_)) => {
+                Status::permission_denied(violation.to_string())
+            }
+            Policy(violation @ PolicyViolation::Unauthorized(_)) => {
+                Status::unauthenticated(violation.to_string())
+            }
+            RateLimited(exceeded) => Status::resource_exhausted(exceeded.to_string()),
+            SealEnvelope { index, err } => Status::invalid_argument(format!(
+                "failed to put the blob at index {index} into an envelope: {err}"
+            )),
         }
     }
 }
@@ -1,5 +1,16 @@
 use jsonrpsee::types::error::ErrorObjectOwned;
 
+use super::{policy::PolicyViolation, queue::SubmitError, rate_limit::RateLimitExceeded};
+
+/// The submission into the namespace is not allowed by the dock.
+pub const NAMESPACE_NOT_ALLOWED_CODE: i32 = -32010;
+/// The submission into the namespace requires a valid token.
+pub const UNAUTHORIZED_CODE: i32 = -32011;
+/// The submission exceeds one of the rate limits of the dock. The client should retry later.
+pub const RATE_LIMITED_CODE: i32 = -32012;
+/// The submission queue is full. The client should retry later.
+pub const QUEUE_FULL_CODE: i32 = -32013;
+
 pub fn no_signing_key() -> ErrorObjectOwned {
     ErrorObjectOwned::owned(
         jsonrpsee::types::error::INTERNAL_ERROR_CODE,
@@ -34,3 +45,47 @@ pub fn submission_error(e: anyhow::Error) -> ErrorObjectOwned {
         None::<()>,
     )
 }
+
+pub fn policy_violation(violation: PolicyViolation) -> ErrorObjectOwned {
+    let code = match violation {
+        PolicyViolation::NamespaceNotAllowed(_) => NAMESPACE_NOT_ALLOWED_CODE,
+        PolicyViolation::Unauthorized(_) => UNAUTHORIZED_CODE,
+    };
+    ErrorObjectOwned::owned(code, violation.to_string(), None::<()>)
+}
+
+pub fn rate_limited(exceeded: RateLimitExceeded) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(RATE_LIMITED_CODE, exceeded.to_string(), None::<()>)
+}
+
+pub fn submit_error(e: SubmitError) -> ErrorObjectOwned {
+    match e {
+        SubmitError::QueueFull => {
+            ErrorObjectOwned::owned(QUEUE_FULL_CODE, "the submission queue is full", None::<()>)
+        }
+        SubmitError::TooLarge { blob_len, max } => ErrorObjectOwned::owned(
+            jsonrpsee::types::error::INVALID_PARAMS_CODE,
+            format!(
+                "the blob of {} bytes exceeds the maximum of {} bytes per block",
+                blob_len, max
+            ),
+            None::<()>,
+        ),
+        SubmitError::Limits(e) => ErrorObjectOwned::owned(
+            jsonrpsee::types::error::INTERNAL_ERROR_CODE,
+            format!("Internal Error: failed to obtain the block limits: {:?}", e),
+            None::<()>,
+        ),
+        SubmitError::Nonce(e) => nonce_obtain_error(e),
+        SubmitError::MakeExtrinsic(e) => submit_extrinsic_error(e),
+        SubmitError::Submit(e) => submission_error(e),
+    }
+}
+
+pub fn envelope_error(e: ikura_shim_common_envelope::Error) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        jsonrpsee::types::error::INVALID_PARAMS_CODE,
+        format!("failed to put the blob into an envelope: {}", e),
+        None::<()>,
+    )
+}
@@ -1,19 +1,42 @@
-use std::sync::Arc;
+use std::net::IpAddr;
 
 use ikura_shim_common_sovereign::{Block, SovereignRPCServer};
 use jsonrpsee::{server::Server, types::ErrorObjectOwned};
-use tokio::sync::Mutex;
 use tracing::info;
 
-use super::rpc_error as err;
-use crate::{ikura_rpc, key::Keypair};
+use super::{
+    envelope::EnvelopeConfig,
+    rate_limit::{RateLimiter, RateLimits},
+    rpc_error as err,
+    transport::{self, ApiTokenLayer, TlsConfig},
+    NamespacePolicy, SubmissionQueue,
+};
+use crate::{ikura_rpc, metrics::Metrics};
 
 pub struct Config {
     /// The RPC client handle to the ikura node.
     pub client: ikura_rpc::Client,
 
-    /// The optional key used for signing when submitting blobs.
-    pub submit_key: Option<Keypair>,
+    /// The handle to the shim metrics.
+    pub metrics: Metrics,
+
+    /// The queue the blobs are submitted through. `None` if there are no keys for signing.
+    pub submit_queue: Option<SubmissionQueue>,
+
+    /// The namespaces the dock accepts submissions into.
+    pub policy: NamespacePolicy,
+
+    /// The limits on the rate of submissions.
+    pub rate_limits: RateLimits,
+
+    /// How the submitted blobs are put into envelopes.
+    pub envelope: EnvelopeConfig,
+
+    /// If specified, the dock serves TLS connections only.
+    pub tls: Option<TlsConfig>,
+
+    /// If specified, the clients must present this token in the `x-api-token` header.
+    pub api_token: Option<String>,
 
     /// The address to listen on.
     pub address: String,
@@ -23,27 +46,83 @@ pub struct Config {
 }
 
 /// Registers the sovereign dock in the given methods.
+///
+/// The jsonrpsee server cannot serve TLS and cannot tell the methods which client a request came
+/// from. Therefore, if either TLS or per-client rate limits are configured, the dock is served by
+/// [`transport::serve_rpc`], which creates the methods for every connection.
 pub async fn run(config: Config) -> anyhow::Result<()> {
-    let listen_on = (config.address.as_str(), config.port);
-    let server = Server::builder().build(listen_on).await?;
-    let dock = SovereignDock::new(config.client.clone(), config.submit_key.clone()).into_rpc();
-    let handle = server.start(dock);
-    handle.stopped().await;
-    Ok(())
+    let limiter = RateLimiter::new(config.rate_limits, "sovereign", config.metrics.clone());
+    let dock = SovereignDock::new(
+        config.client,
+        config.metrics,
+        config.submit_queue,
+        config.policy,
+        limiter,
+        config.envelope,
+    );
+    if config.tls.is_none() && !config.rate_limits.limits_clients() {
+        let middleware = tower::ServiceBuilder::new().layer(ApiTokenLayer::new(config.api_token));
+        let server = Server::builder()
+            .set_middleware(middleware)
+            .build((config.address.as_str(), config.port))
+            .await?;
+        let handle = server.start(dock.into_rpc());
+        handle.stopped().await;
+        return Ok(());
+    }
+
+    let Some(listen_on) = tokio::net::lookup_host((config.address.as_str(), config.port))
+        .await?
+        .next()
+    else {
+        anyhow::bail!(
+            "failed to resolve address: {}:{}",
+            config.address,
+            config.port
+        )
+    };
+    let acceptor = config.tls.as_ref().map(TlsConfig::acceptor).transpose()?;
+    let (_, server) =
+        transport::serve_rpc(listen_on, acceptor, config.api_token, move |client_ip| {
+            SovereignDock {
+                client_ip: Some(client_ip),
+                ..dock.clone()
+            }
+            .into_rpc()
+        })
+        .await?;
+    server.await
 }
 
+#[derive(Clone)]
 struct SovereignDock {
     client: ikura_rpc::Client,
-    submit_key: Option<Keypair>,
-    cur_nonce: Arc<Mutex<Option<u64>>>,
+    metrics: Metrics,
+    submit_queue: Option<SubmissionQueue>,
+    policy: NamespacePolicy,
+    limiter: RateLimiter,
+    envelope: EnvelopeConfig,
+    /// The IP address of the client served, if known.
+    client_ip: Option<IpAddr>,
 }
 
 impl SovereignDock {
-    fn new(client: ikura_rpc::Client, submit_key: Option<Keypair>) -> Self {
+    fn new(
+        client: ikura_rpc::Client,
+        metrics: Metrics,
+        submit_queue: Option<SubmissionQueue>,
+        policy: NamespacePolicy,
+        limiter: RateLimiter,
+        envelope: EnvelopeConfig,
+    ) -> Self {
         Self {
             client,
-            submit_key,
-            cur_nonce: Arc::new(Mutex::new(None)),
+            metrics,
+            submit_queue,
+            policy,
+            limiter,
+            envelope,
+            client_ip: None,
         }
     }
 }
@@ -56,9 +135,13 @@ impl SovereignRPCServer for SovereignDock {
         namespace: ikura_nmt::Namespace,
     ) -> Result<Block, ErrorObjectOwned> {
         info!("get_block({})", height);
+        self.metrics.on_rpc_request("sovereign", "get_block");
         let block_hash = self.client.await_finalized_height(height).await;
         let block = self.client.await_block_at(Some(block_hash)).await.unwrap();
-        let proof = make_namespace_proof(&block, namespace);
+        let proof = block.namespace_proof(namespace);
+        // The blobs are served as stored on chain, since the adapter verifies them against the
+        // proof. The adapter takes them out of their envelopes itself, but it cannot decrypt them:
+        // an encrypted blob reaches the rollup as ciphertext.
         let blobs = block
             .blobs
             .into_iter()
@@ -82,60 +165,28 @@ impl SovereignRPCServer for SovereignDock {
         &self,
         blob: Vec<u8>,
         namespace: ikura_nmt::Namespace,
+        auth_token: Option<String>,
     ) -> Result<(), ErrorObjectOwned> {
         info!("submit_blob({}, {:?})", blob.len(), namespace);
-        let submit_key = self
-            .submit_key
-            .as_ref()
-            .cloned()
-            .ok_or_else(err::no_signing_key)?;
-        let nonce = self.gen_nonce().await.map_err(err::nonce_obtain_error)?;
-        let blob_extrinsic = self
-            .client
-            .make_blob_extrinsic(blob, namespace, &submit_key, nonce)
+        self.metrics.on_rpc_request("sovereign", "submit_blob");
+        self.policy
+            .check(namespace, auth_token.as_deref())
+            .map_err(err::policy_violation)?;
+        let submit_queue = self.submit_queue.as_ref().ok_or_else(err::no_signing_key)?;
+        let blob = self
+            .envelope
+            .seal(blob, namespace)
+            .map_err(err::envelope_error)?;
+        let block = self.client.finalized_height().await;
+        self.limiter
+            .check(namespace, self.client_ip, block, blob.len() as u64, 1)
+            .map_err(err::rate_limited)?;
+        // The Sovereign SDK adapter does not express a preference, so all of its blobs are
+        // submitted with the same priority.
+        submit_queue
+            .submit(blob, namespace, 0.0)
             .await
-            .map_err(err::submit_extrinsic_error)?;
-        self.client
-            .submit_blob(&blob_extrinsic)
-            .await
-            .map_err(err::submission_error)?;
+            .map_err(err::submit_error)?;
         Ok(())
     }
 }
-
-impl SovereignDock {
-    async fn gen_nonce(&self) -> anyhow::Result<u64> {
-        let submit_key = self
-            .submit_key
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("no key for signing blobs"))?; // should be unreachable
-        let mut cur_nonce = self.cur_nonce.lock().await;
-        let nonce = match *cur_nonce {
-            Some(nonce) => nonce,
-            None => self.client.get_last_nonce(&submit_key).await?,
-        };
-        cur_nonce.replace(nonce + 1);
-        Ok(nonce)
-    }
-}
-
-/// Creates a namespace proof for the given namespace in the given block.
-fn make_namespace_proof(
-    block: &ikura_rpc::Block,
-    namespace: ikura_nmt::Namespace,
-) -> ikura_nmt::NamespaceProof {
-    let mut blob_metadata = vec![];
-    for blob in &block.blobs {
-        blob_metadata.push(ikura_nmt::BlobMetadata {
-            namespace: blob.namespace,
-            leaf: ikura_nmt::NmtLeaf {
-                extrinsic_index: blob.extrinsic_index,
-                who: blob.sender,
-                blob_hash: blob.sha2_hash(),
-            },
-        });
-    }
-    let mut tree = ikura_nmt::tree_from_blobs(blob_metadata);
-    let blob_proof = tree.proof(namespace);
-    blob_proof
-}
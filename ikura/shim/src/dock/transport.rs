@@ -0,0 +1,665 @@
+//! Transport security of the docks: TLS, optionally with client certificate verification, and a
+//! static API token.
+//!
+//! The Rollkit dock relies on the TLS support of tonic. The jsonrpsee server used by the Sovereign
+//! dock cannot serve TLS, nor tell the methods which client a request came from, so in those cases
+//! the dock is served by [`serve_rpc`] instead.
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use futures::{
+    future::{self, BoxFuture, Either, Ready},
+    FutureExt as _, StreamExt as _,
+};
+use jsonrpsee::RpcModule;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+        RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+use tower::Layer as _;
+
+use super::policy::constant_time_eq;
+
+/// The name of the header (or gRPC metadata key) carrying the API token.
+pub const API_TOKEN_HEADER: &str = "x-api-token";
+
+/// The TLS configuration of a dock.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    /// The path to the PEM-encoded certificate chain of the dock.
+    pub cert: PathBuf,
+    /// The path to the PEM-encoded private key of the dock.
+    pub key: PathBuf,
+    /// The path to the PEM-encoded CA certificates. If specified, the clients must present
+    /// a certificate signed by one of them.
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Creates the TLS configuration for tonic.
+    pub fn tonic(&self) -> anyhow::Result<tonic::transport::ServerTlsConfig> {
+        let cert = read(&self.cert)?;
+        let key = read(&self.key)?;
+        let mut tls_config = tonic::transport::ServerTlsConfig::new()
+            .identity(tonic::transport::Identity::from_pem(cert, key));
+        if let Some(client_ca) = &self.client_ca {
+            let client_ca = read(client_ca)?;
+            tls_config =
+                tls_config.client_ca_root(tonic::transport::Certificate::from_pem(client_ca));
+        }
+        Ok(tls_config)
+    }
+
+    /// Creates the acceptor of the TLS connections.
+    pub fn acceptor(&self) -> anyhow::Result<TlsAcceptor> {
+        let certs = load_certs(&self.cert)?;
+        let key = rustls_pemfile::private_key(&mut read(&self.key)?.as_slice())
+            .with_context(|| format!("malformed private key '{}'", self.key.display()))?
+            .ok_or_else(|| anyhow::anyhow!("no private key found in '{}'", self.key.display()))?;
+        let builder = ServerConfig::builder();
+        let builder = match &self.client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(client_ca)? {
+                    roots.add(cert)?;
+                }
+                builder.with_client_cert_verifier(
+                    WebPkiClientVerifier::builder(Arc::new(roots)).build()?,
+                )
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let server_config = builder
+            .with_single_cert(certs, key)
+            .context("invalid certificate or key")?;
+        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    }
+}
+
+fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("cannot read '{}'", path.display()))
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut read(path)?.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("malformed certificates '{}'", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates found in '{}'", path.display());
+    }
+    Ok(certs)
+}
+
+/// The maximum size of a JSON-RPC request, the same as the default of the jsonrpsee server.
+const MAX_REQUEST_SIZE: usize = 10 * 1024 * 1024;
+
+/// The maximum number of connections served at once, the same as the default of the jsonrpsee
+/// server. The connections above the limit are closed right away.
+const MAX_CONNECTIONS: usize = 100;
+
+/// The maximum number of requests of a single WebSocket connection handled at once. The following
+/// requests are not read until one of them completes. The calls of a batch are handled at most this
+/// many at once as well.
+const MAX_PENDING_CALLS: usize = 64;
+
+/// How long to wait before accepting connections again after a failure, e.g. if the process ran
+/// out of file descriptors. The same as hyper.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The time a client has to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The time a client has to send the headers of an HTTP request, once it started sending them, and
+/// then its body.
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The time after which an HTTP connection with no request in flight is closed. WebSocket
+/// connections are kept open, since the rollups keep theirs for as long as they run.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The response to a request that is not valid JSON.
+const PARSE_ERROR: &str =
+    r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#;
+
+/// The response to a request that is valid JSON but not a JSON-RPC call, or an empty batch.
+const INVALID_REQUEST: &str =
+    r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"#;
+
+/// Serves JSON-RPC over HTTP and WebSocket on `listen_on`. If `acceptor` is specified, the
+/// connections are TLS.
+///
+/// Unlike with the jsonrpsee server, the methods are created for every connection by `methods`
+/// from the IP address of the client, so that they can tell the clients apart. All of the
+/// connections are served by the same listener. Subscriptions are not supported.
+///
+/// A connection counts towards [`MAX_CONNECTIONS`] from the moment it is accepted, so the slow TLS
+/// handshakes and requests time out and the idle HTTP connections are closed.
+///
+/// Binding happens eagerly, so that a misconfiguration is reported before the server is spawned.
+/// Returns the address the server is bound to along with a future that serves the connections.
+pub async fn serve_rpc<C, F>(
+    listen_on: SocketAddr,
+    acceptor: Option<TlsAcceptor>,
+    api_token: Option<String>,
+    methods: F,
+) -> anyhow::Result<(SocketAddr, impl Future<Output = anyhow::Result<()>>)>
+where
+    C: Send + Sync + 'static,
+    F: Fn(IpAddr) -> RpcModule<C> + Send + 'static,
+{
+    let listener = TcpListener::bind(listen_on).await?;
+    let local_addr = listener.local_addr()?;
+    let api_token = ApiTokenLayer::new(api_token);
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    Ok((local_addr, async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(err) if is_connection_error(&err) => {
+                    tracing::debug!(?err, "failed to accept a connection");
+                    continue;
+                }
+                Err(err) => {
+                    tracing::warn!(?err, "failed to accept a connection, backing off");
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            };
+            let Ok(permit) = connections.clone().try_acquire_owned() else {
+                tracing::debug!(%peer, "too many connections, closing");
+                continue;
+            };
+            let idle = Arc::new(Idle::new());
+            let service = api_token.layer(RpcService {
+                methods: Arc::new(methods(peer.ip())),
+                idle: idle.clone(),
+                _connection: Arc::new(permit),
+            });
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let res = match acceptor {
+                    Some(acceptor) => {
+                        match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+                            .await
+                        {
+                            Ok(Ok(tls_stream)) => serve_connection(tls_stream, service, idle).await,
+                            Ok(Err(err)) => Err(err.into()),
+                            Err(_) => Err(anyhow::anyhow!("TLS handshake timed out")),
+                        }
+                    }
+                    None => serve_connection(stream, service, idle).await,
+                };
+                if let Err(err) = res {
+                    tracing::debug!(?err, %peer, "connection terminated");
+                }
+            });
+        }
+    }))
+}
+
+/// Returns true if the error concerns only the connection being accepted, which can be skipped
+/// right away. Other errors, such as running out of file descriptors, affect the listener.
+fn is_connection_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::ConnectionReset
+    )
+}
+
+/// Serves the HTTP requests of a connection until it is closed, upgraded to WebSocket or idle.
+async fn serve_connection<I, C>(
+    io: I,
+    service: ApiTokenService<RpcService<C>>,
+    idle: Arc<Idle>,
+) -> anyhow::Result<()>
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    C: Send + Sync + 'static,
+{
+    let connection = hyper::server::conn::Http::new()
+        .http1_header_read_timeout(REQUEST_READ_TIMEOUT)
+        .serve_connection(io, service)
+        .with_upgrades();
+    tokio::select! {
+        res = connection => res?,
+        () = idle.timeout() => tracing::debug!("closing an idle connection"),
+    }
+    Ok(())
+}
+
+/// Tracks the HTTP requests of a connection, to close it once it is idle.
+struct Idle(Mutex<IdleState>);
+
+struct IdleState {
+    /// The number of requests being handled.
+    requests: usize,
+    /// When the last request was handled, or the connection accepted.
+    since: Instant,
+}
+
+impl Idle {
+    fn new() -> Self {
+        Self(Mutex::new(IdleState {
+            requests: 0,
+            since: Instant::now(),
+        }))
+    }
+
+    /// Marks a request as being handled until the returned guard is dropped.
+    fn request(self: &Arc<Self>) -> IdleGuard {
+        self.0.lock().unwrap().requests += 1;
+        IdleGuard(self.clone())
+    }
+
+    fn request_done(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.requests -= 1;
+        state.since = Instant::now();
+    }
+
+    /// Completes once the connection has not had any request in flight for [`IDLE_TIMEOUT`].
+    async fn timeout(&self) {
+        loop {
+            let (requests, deadline) = {
+                let state = self.0.lock().unwrap();
+                (state.requests, state.since + IDLE_TIMEOUT)
+            };
+            let now = Instant::now();
+            if now < deadline {
+                tokio::time::sleep_until(deadline).await;
+            } else if requests == 0 {
+                return;
+            } else {
+                tokio::time::sleep(IDLE_TIMEOUT).await;
+            }
+        }
+    }
+}
+
+struct IdleGuard(Arc<Idle>);
+
+impl Drop for IdleGuard {
+    fn drop(&mut self) {
+        self.0.request_done();
+    }
+}
+
+/// Serves the JSON-RPC methods of a single connection.
+struct RpcService<C> {
+    methods: Arc<RpcModule<C>>,
+    idle: Arc<Idle>,
+    /// Held until both the connection and the WebSocket it was upgraded to, if any, are closed.
+    _connection: Arc<OwnedSemaphorePermit>,
+}
+
+impl<C> Clone for RpcService<C> {
+    fn clone(&self) -> Self {
+        Self {
+            methods: self.methods.clone(),
+            idle: self.idle.clone(),
+            _connection: self._connection.clone(),
+        }
+    }
+}
+
+impl<C: Send + Sync + 'static> RpcService<C> {
+    async fn handle(self, request: hyper::Request<hyper::Body>) -> hyper::Response<hyper::Body> {
+        let _request = self.idle.request();
+        if soketto::handshake::http::is_upgrade_request(&request) {
+            let mut server = soketto::handshake::http::Server::new();
+            return match server.receive_request(&request) {
+                Ok(response) => {
+                    tokio::spawn(async move {
+                        if let Err(err) = self.serve_ws(server, request).await {
+                            tracing::debug!(?err, "WebSocket connection terminated");
+                        }
+                    });
+                    response.map(|()| hyper::Body::empty())
+                }
+                Err(err) => {
+                    tracing::debug!(?err, "WebSocket handshake failed");
+                    status(hyper::StatusCode::BAD_REQUEST)
+                }
+            };
+        }
+        if request.method() != hyper::Method::POST {
+            return status(hyper::StatusCode::METHOD_NOT_ALLOWED);
+        }
+        let body = tokio::time::timeout(REQUEST_READ_TIMEOUT, read_body(request.into_body()));
+        let body = match body.await {
+            Ok(Some(body)) => body,
+            Ok(None) => return status(hyper::StatusCode::PAYLOAD_TOO_LARGE),
+            Err(_) => return status(hyper::StatusCode::REQUEST_TIMEOUT),
+        };
+        match self.call(&body).await {
+            Some(response) => hyper::Response::builder()
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .body(hyper::Body::from(response))
+                .expect("the response is well-formed; qed"),
+            None => status(hyper::StatusCode::NO_CONTENT),
+        }
+    }
+
+    async fn serve_ws(
+        self,
+        server: soketto::handshake::http::Server,
+        request: hyper::Request<hyper::Body>,
+    ) -> anyhow::Result<()> {
+        let stream = hyper::upgrade::on(request).await?;
+        let mut builder = server.into_builder(stream.compat());
+        builder.set_max_message_size(MAX_REQUEST_SIZE);
+        let (sender, mut receiver) = builder.finish();
+        let sender = Arc::new(tokio::sync::Mutex::new(sender));
+        // The calls are handled concurrently, since some of them wait for future blocks.
+        let pending_calls = Arc::new(Semaphore::new(MAX_PENDING_CALLS));
+        loop {
+            let mut message = Vec::new();
+            match receiver.receive_data(&mut message).await {
+                Ok(_) => {}
+                Err(soketto::connection::Error::Closed) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+            let permit = pending_calls.clone().acquire_owned().await?;
+            let (this, sender) = (self.clone(), sender.clone());
+            tokio::spawn(async move {
+                let Some(response) = this.call(&message).await else {
+                    return;
+                };
+                let mut sender = sender.lock().await;
+                if let Err(err) = sender.send_text_owned(response).await {
+                    tracing::debug!(?err, "failed to send a WebSocket response");
+                } else if let Err(err) = sender.flush().await {
+                    tracing::debug!(?err, "failed to send a WebSocket response");
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    /// Calls the methods requested by the given JSON-RPC request, either a single call or a batch,
+    /// and returns the response. Returns `None` if there is nothing to respond, i.e. if the request
+    /// is made of notifications only.
+    async fn call(&self, request: &[u8]) -> Option<String> {
+        let Ok(request) = serde_json::from_slice::<serde_json::Value>(request) else {
+            return Some(PARSE_ERROR.to_string());
+        };
+        let serde_json::Value::Array(batch) = request else {
+            return self.call_one(request).await;
+        };
+        if batch.is_empty() {
+            return Some(INVALID_REQUEST.to_string());
+        }
+        let responses = futures::stream::iter(batch)
+            .map(|call| self.call_one(call))
+            .buffered(MAX_PENDING_CALLS)
+            .filter_map(future::ready)
+            .collect::<Vec<_>>()
+            .await;
+        if responses.is_empty() {
+            None
+        } else {
+            Some(format!("[{}]", responses.join(",")))
+        }
+    }
+
+    /// Calls the method requested by a single JSON-RPC call and returns the response, or `None`
+    /// for a notification, which is not answered.
+    async fn call_one(&self, call: serde_json::Value) -> Option<String> {
+        if call.get("method").is_some() && call.get("id").is_none() {
+            return None;
+        }
+        match self.methods.raw_json_request(&call.to_string(), 1).await {
+            Ok((response, _)) => Some(response.result),
+            Err(_) => Some(INVALID_REQUEST.to_string()),
+        }
+    }
+}
+
+impl<C: Send + Sync + 'static> tower::Service<hyper::Request<hyper::Body>> for RpcService<C> {
+    type Response = hyper::Response<hyper::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: hyper::Request<hyper::Body>) -> Self::Future {
+        self.clone().handle(request).map(Ok).boxed()
+    }
+}
+
+/// Reads the body of an HTTP request, unless it exceeds [`MAX_REQUEST_SIZE`].
+async fn read_body(mut body: hyper::Body) -> Option<Vec<u8>> {
+    use hyper::body::HttpBody as _;
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.ok()?;
+        if buf.len() + chunk.len() > MAX_REQUEST_SIZE {
+            return None;
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Some(buf)
+}
+
+fn status(status: hyper::StatusCode) -> hyper::Response<hyper::Body> {
+    hyper::Response::builder()
+        .status(status)
+        .body(hyper::Body::empty())
+        .expect("the response is well-formed; qed")
+}
+
+/// Checks the API token presented by the client against the expected one, if any.
+pub fn check_api_token(expected: Option<&str>, presented: Option<&str>) -> bool {
+    match (expected, presented) {
+        (None, _) => true,
+        (Some(expected), Some(presented)) => {
+            constant_time_eq(expected.as_bytes(), presented.as_bytes())
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// Creates a tonic interceptor rejecting the requests without the expected API token.
+pub fn api_token_interceptor(
+    api_token: Option<String>,
+) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> + Clone {
+    move |request: tonic::Request<()>| {
+        let presented = request
+            .metadata()
+            .get(API_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if check_api_token(api_token.as_deref(), presented) {
+            Ok(request)
+        } else {
+            Err(tonic::Status::unauthenticated("invalid API token"))
+        }
+    }
+}
+
+/// An HTTP middleware rejecting the requests without the expected API token.
+///
+/// The token is checked once per HTTP request, which for WebSocket means once per connection.
+#[derive(Clone)]
+pub struct ApiTokenLayer {
+    api_token: Option<Arc<str>>,
+}
+
+impl ApiTokenLayer {
+    pub fn new(api_token: Option<String>) -> Self {
+        Self {
+            api_token: api_token.map(Into::into),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for ApiTokenLayer {
+    type Service = ApiTokenService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiTokenService {
+            inner,
+            api_token: self.api_token.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiTokenService<S> {
+    inner: S,
+    api_token: Option<Arc<str>>,
+}
+
+impl<S> tower::Service<hyper::Request<hyper::Body>> for ApiTokenService<S>
+where
+    S: tower::Service<hyper::Request<hyper::Body>, Response = hyper::Response<hyper::Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<S::Response, S::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: hyper::Request<hyper::Body>) -> Self::Future {
+        let presented = request
+            .headers()
+            .get(API_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if check_api_token(self.api_token.as_deref(), presented) {
+            Either::Right(self.inner.call(request))
+        } else {
+            let response = hyper::Response::builder()
+                .status(hyper::StatusCode::UNAUTHORIZED)
+                .body(hyper::Body::from("invalid API token"))
+                .expect("the response is well-formed; qed");
+            Either::Left(future::ready(Ok(response)))
+        }
+    }
+}
+
+#[test]
+fn api_token() {
+    assert!(check_api_token(None, None));
+    assert!(check_api_token(None, Some("anything")));
+    assert!(check_api_token(Some("secret"), Some("secret")));
+    assert!(!check_api_token(Some("secret"), Some("guess")));
+    assert!(!check_api_token(Some("secret"), None));
+}
+
+#[tokio::test]
+async fn serve_rpc_per_connection_methods() {
+    use jsonrpsee::{proc_macros::rpc, types::ErrorObjectOwned};
+
+    #[rpc(client, server)]
+    trait ClientIpRpc {
+        #[method(name = "test_clientIp")]
+        async fn client_ip(&self) -> Result<IpAddr, ErrorObjectOwned>;
+    }
+
+    struct ClientIp(IpAddr);
+
+    #[async_trait::async_trait]
+    impl ClientIpRpcServer for ClientIp {
+        async fn client_ip(&self) -> Result<IpAddr, ErrorObjectOwned> {
+            Ok(self.0)
+        }
+    }
+
+    let (addr, server) = serve_rpc(
+        "127.0.0.1:0".parse().unwrap(),
+        None,
+        Some("secret".to_string()),
+        |client_ip| ClientIp(client_ip).into_rpc(),
+    )
+    .await
+    .unwrap();
+    tokio::spawn(server);
+
+    let mut headers = jsonrpsee::http_client::HeaderMap::new();
+    headers.insert(API_TOKEN_HEADER, "secret".parse().unwrap());
+    let localhost: IpAddr = "127.0.0.1".parse().unwrap();
+
+    let ws_client = jsonrpsee::ws_client::WsClientBuilder::default()
+        .set_headers(headers.clone())
+        .build(format!("ws://{}", addr))
+        .await
+        .unwrap();
+    assert_eq!(ws_client.client_ip().await.unwrap(), localhost);
+
+    let http_client = jsonrpsee::http_client::HttpClientBuilder::default()
+        .set_headers(headers)
+        .build(format!("http://{}", addr))
+        .unwrap();
+    assert_eq!(http_client.client_ip().await.unwrap(), localhost);
+
+    // The API token is still required.
+    let http_client = jsonrpsee::http_client::HttpClientBuilder::default()
+        .build(format!("http://{}", addr))
+        .unwrap();
+    assert!(http_client.client_ip().await.is_err());
+}
+
+#[tokio::test]
+async fn rpc_service_batches() {
+    let mut methods = RpcModule::new(());
+    methods
+        .register_method("test_echo", |params, _| params.one::<u32>())
+        .unwrap();
+    let connections = Arc::new(Semaphore::new(1));
+    let service = RpcService {
+        methods: Arc::new(methods),
+        idle: Arc::new(Idle::new()),
+        _connection: Arc::new(connections.try_acquire_owned().unwrap()),
+    };
+    let call = |request: &'static str| service.call(request.as_bytes());
+
+    assert_eq!(
+        call(r#"{"jsonrpc":"2.0","method":"test_echo","params":[1],"id":1}"#)
+            .await
+            .as_deref(),
+        Some(r#"{"jsonrpc":"2.0","result":1,"id":1}"#)
+    );
+    // The calls of a batch are answered in order, except for the notifications.
+    assert_eq!(
+        call(concat!(
+            r#"[{"jsonrpc":"2.0","method":"test_echo","params":[1],"id":1},"#,
+            r#"{"jsonrpc":"2.0","method":"test_echo","params":[2]},"#,
+            r#"{"jsonrpc":"2.0","method":"test_echo","params":[3],"id":3}]"#,
+        ))
+        .await
+        .as_deref(),
+        Some(r#"[{"jsonrpc":"2.0","result":1,"id":1},{"jsonrpc":"2.0","result":3,"id":3}]"#)
+    );
+    assert_eq!(
+        call(r#"[{"jsonrpc":"2.0","method":"test_echo","params":[2]}]"#).await,
+        None
+    );
+    assert_eq!(call("[]").await.as_deref(), Some(INVALID_REQUEST));
+    assert_eq!(call("[1]").await, Some(format!("[{}]", INVALID_REQUEST)));
+    assert_eq!(call("{").await.as_deref(), Some(PARSE_ERROR));
+}
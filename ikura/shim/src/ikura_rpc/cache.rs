@@ -0,0 +1,60 @@
+//! A cache of the recently retrieved blocks.
+
+use super::Block;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// Keeps up to a fixed number of the most recently inserted blocks, indexed by their hashes.
+///
+/// A block hash commits to the whole contents of the block, so the cached entries never become
+/// stale. The docks typically ask for the same recent blocks over and over again, e.g. a rollup
+/// node fetching a block by its height followed by the same block for another namespace.
+pub struct BlockCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    blocks: HashMap<[u8; 32], Block>,
+    /// The hashes of the cached blocks in the order of insertion. Used for eviction.
+    order: VecDeque<[u8; 32]>,
+}
+
+impl BlockCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner {
+                blocks: HashMap::with_capacity(capacity),
+                order: VecDeque::with_capacity(capacity),
+            }),
+        }
+    }
+
+    /// Returns a copy of the block with the given hash, if it is in the cache.
+    pub fn get(&self, block_hash: &[u8; 32]) -> Option<Block> {
+        let inner = self.inner.lock().unwrap();
+        inner.blocks.get(block_hash).cloned()
+    }
+
+    /// Puts a copy of the given block into the cache, evicting the oldest entry if the cache is
+    /// full.
+    pub fn insert(&self, block: &Block) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.blocks.contains_key(&block.hash) {
+            return;
+        }
+        if inner.order.len() >= self.capacity {
+            if let Some(evicted) = inner.order.pop_front() {
+                inner.blocks.remove(&evicted);
+            }
+        }
+        inner.order.push_back(block.hash);
+        inner.blocks.insert(block.hash, block.clone());
+    }
+}
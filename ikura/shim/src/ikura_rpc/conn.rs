@@ -1,35 +1,46 @@
 //! Connection management module.
 
 use super::FinalizedHeadWatcher;
+use crate::metrics::Metrics;
 use ikura_subxt::ikura::is_codegen_valid_for;
+use ikura_subxt::Header;
 use std::{
     mem,
     sync::{atomic::AtomicU64, Arc},
 };
-use subxt::backend::rpc::RpcClient;
+use subxt::{backend::rpc::RpcClient, rpc_params, utils::H256};
 use tokio::sync::{oneshot, Mutex};
 
 // Contains the RPC client structures that are assumed to be connected.
 pub struct Conn {
     /// Connection id. For diagnostics purposes only.
     pub conn_id: u64,
+    /// The URL of the endpoint this connection was established with. For diagnostics purposes
+    /// only.
+    pub rpc_url: String,
     pub raw: RpcClient,
     pub subxt: ikura_subxt::Client,
     pub finalized: FinalizedHeadWatcher,
 }
 
 impl Conn {
-    async fn connect(conn_id: u64, rpc_url: &str) -> anyhow::Result<Arc<Self>> {
-        let raw = RpcClient::from_url(rpc_url).await?;
+    async fn connect(
+        conn_id: u64,
+        endpoints: &Endpoints,
+        metrics: &Metrics,
+    ) -> anyhow::Result<Arc<Self>> {
+        let (rpc_url, raw) = select_endpoint(endpoints).await?;
+        tracing::debug!(?conn_id, %rpc_url, "selected ikura node endpoint");
         let subxt = ikura_subxt::Client::from_rpc_client(raw.clone()).await?;
         check_if_compatible(&subxt)?;
         if !is_codegen_valid_for(&subxt.metadata()) {
             const WARN_WRONG_VERSION: &str = "connected to a ikura node with a newer runtime than the one this shim was compiled against. Update the shim lest you run into problems. https://github.com/thrumdev/blobs";
             tracing::warn!("{}", WARN_WRONG_VERSION);
         }
-        let finalized = FinalizedHeadWatcher::spawn(subxt.clone()).await;
+        let finalized = FinalizedHeadWatcher::spawn(subxt.clone(), metrics.clone()).await;
         Ok(Arc::new(Self {
             conn_id,
+            rpc_url,
             raw,
             subxt,
             finalized,
@@ -37,6 +48,104 @@ impl Conn {
     }
 }
 
+/// The set of ikura node endpoints the connector can use.
+pub struct Endpoints {
+    /// The URLs of the endpoints, in the order of preference.
+    pub urls: Vec<String>,
+    /// If set, before connecting, the selected endpoint is checked to agree with all other
+    /// reachable endpoints on their finalized blocks.
+    pub cross_check: bool,
+}
+
+/// The result of probing a single endpoint.
+struct Probe {
+    url: String,
+    raw: RpcClient,
+    finalized_number: u64,
+    finalized_hash: H256,
+}
+
+/// Picks the endpoint to connect to and returns its URL along with an RPC client connected to it.
+///
+/// All endpoints are probed and the one with the highest finalized block wins. Endpoints that
+/// cannot be reached are skipped. Ties are broken by the order in which the endpoints were
+/// specified.
+///
+/// If cross-checking is enabled, the winner must agree with every other reachable endpoint on the
+/// block hash at the height finalized by that endpoint. A disagreement means that at least one
+/// of the nodes is on a different chain and that is reported as an error.
+async fn select_endpoint(endpoints: &Endpoints) -> anyhow::Result<(String, RpcClient)> {
+    if let [rpc_url] = &endpoints.urls[..] {
+        // The common case: there is nothing to choose from.
+        let raw = RpcClient::from_url(rpc_url).await?;
+        return Ok((rpc_url.clone(), raw));
+    }
+
+    let probes = futures::future::join_all(endpoints.urls.iter().map(|url| probe(url))).await;
+    let mut candidates = vec![];
+    for (url, probe) in endpoints.urls.iter().zip(probes) {
+        match probe {
+            Ok(probe) => candidates.push(probe),
+            Err(e) => tracing::warn!(%url, "skipping unavailable ikura node endpoint: {}", e),
+        }
+    }
+
+    // The sort is stable, so the order of the endpoints is preserved among equals.
+    candidates.sort_by_key(|probe| std::cmp::Reverse(probe.finalized_number));
+    let mut candidates = candidates.into_iter();
+    let Some(best) = candidates.next() else {
+        anyhow::bail!("none of the ikura node endpoints are reachable");
+    };
+
+    if endpoints.cross_check {
+        for other in candidates {
+            cross_check(&best, &other).await?;
+        }
+    }
+
+    Ok((best.url, best.raw))
+}
+
+/// Connects to the given endpoint and queries its finalized head.
+async fn probe(url: &str) -> anyhow::Result<Probe> {
+    let raw = RpcClient::from_url(url).await?;
+    let finalized_hash: H256 = raw.request("chain_getFinalizedHead", rpc_params![]).await?;
+    let header: Option<Header> = raw
+        .request("chain_getHeader", rpc_params![finalized_hash])
+        .await?;
+    let Some(header) = header else {
+        anyhow::bail!(
+            "the finalized header 0x{} is not available",
+            hex::encode(finalized_hash)
+        );
+    };
+    Ok(Probe {
+        url: url.to_string(),
+        raw,
+        finalized_number: header.number as u64,
+        finalized_hash,
+    })
+}
+
+/// Checks that `best` has the same block at the height `other` has finalized.
+///
+/// `best` is expected to have finalized at least as many blocks as `other`.
+async fn cross_check(best: &Probe, other: &Probe) -> anyhow::Result<()> {
+    let block_hash: Option<H256> = best
+        .raw
+        .request("chain_getBlockHash", rpc_params![other.finalized_number])
+        .await?;
+    if block_hash != Some(other.finalized_hash) {
+        anyhow::bail!(
+            "ikura nodes {} and {} disagree on the finalized block at height {}",
+            best.url,
+            other.url,
+            other.finalized_number,
+        );
+    }
+    Ok(())
+}
+
 /// Tries to find the `Blob` pallet in the runtime metadata. If it's not there, then we are not
 /// connected to a Ikura node.
 fn check_if_compatible(client: &ikura_subxt::Client) -> anyhow::Result<()> {
@@ -72,18 +181,23 @@ enum State {
 ///
 /// Allows to wait for a connection to be established and to reset the connection if we detect
 /// that it's broken.
+///
+/// Every new connection selects among all the known endpoints, so a reset fails over to another
+/// node in case the current one becomes unavailable.
 pub struct Connector {
     state: Arc<Mutex<State>>,
     next_conn_id: AtomicU64,
-    rpc_url: Arc<String>,
+    endpoints: Arc<Endpoints>,
+    metrics: Metrics,
 }
 
 impl Connector {
-    pub fn new(rpc_url: Arc<String>) -> Self {
+    pub fn new(endpoints: Arc<Endpoints>, metrics: Metrics) -> Self {
         Self {
             state: Arc::new(Mutex::new(State::Disconnected)),
             next_conn_id: AtomicU64::new(0),
-            rpc_url,
+            endpoints,
+            metrics,
         }
     }
 
@@ -97,8 +211,7 @@ impl Connector {
             )),
             State::Disconnected => {
                 let conn_id = self.gen_conn_id();
-                let rpc_url = self.rpc_url.clone();
-                match Conn::connect(conn_id, &rpc_url).await {
+                match Conn::connect(conn_id, &self.endpoints, &self.metrics).await {
                     Ok(conn) => {
                         *state = State::Connected(conn.clone());
                         Ok(conn)
@@ -173,6 +286,7 @@ impl Connector {
             }
             State::Disconnected => (),
         }
+        self.metrics.on_node_reconnect();
         let conn_id = self.gen_conn_id();
         tracing::debug!(?conn_id, "reset: initiating new connection");
         *state = State::Connecting {
@@ -192,11 +306,12 @@ impl Connector {
     /// Spawns a task that will connect to the ikura node and notify all waiters.
     fn spawn_connection_task(&self, conn_id: u64) {
         let state = self.state.clone();
-        let rpc_url = self.rpc_url.clone();
+        let endpoints = self.endpoints.clone();
+        let metrics = self.metrics.clone();
         let _ = tokio::spawn(async move {
-            tracing::debug!(?conn_id, ?rpc_url, "connecting to ikura node");
+            tracing::debug!(?conn_id, rpc_urls = ?endpoints.urls, "connecting to ikura node");
             let conn = loop {
-                match Conn::connect(conn_id, &rpc_url).await {
+                match Conn::connect(conn_id, &endpoints, &metrics).await {
                     Ok(conn) => break conn,
                     Err(e) => {
                         tracing::error!(?conn_id, "failed to connect to ikura node: {}\n", e);
@@ -226,13 +341,14 @@ impl Connector {
 
             // Finally, set the state to `Connected`, notify all waiters and explicitly
             // release the mutex.
+            let rpc_url = conn.rpc_url.clone();
             for tx in waiters {
                 let _ = tx.send(conn.clone());
             }
             *state = State::Connected(conn);
             drop(state);
 
-            tracing::info!(?conn_id, "connected to ikura node");
+            tracing::info!(?conn_id, %rpc_url, "connected to ikura node");
         });
     }
 }
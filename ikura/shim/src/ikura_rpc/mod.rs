@@ -1,6 +1,6 @@
 use std::{fmt, sync::Arc};
 
-use crate::key::Keypair;
+use crate::{metrics::Metrics, signer::BlobSigner};
 use anyhow::Context;
 use ikura_nmt::Namespace;
 use ikura_subxt::{
@@ -11,8 +11,8 @@ use subxt::{
     config::Header as _,
     error::BlockError,
     rpc_params,
-    tx::{Signer, SubmittableExtrinsic},
-    utils::H256,
+    tx::SubmittableExtrinsic,
+    utils::{AccountId32, MultiAddress, MultiSignature, H256},
     OnlineClient,
 };
 use tokio::sync::watch;
@@ -21,8 +21,12 @@ use tracing::Level;
 // NOTE: we specifically avoid prolifiration of subxt types around the codebase. To that end, we
 //       avoid returning H256 and instead return [u8; 32] directly.
 
+mod cache;
 mod conn;
 
+/// The number of the most recently retrieved blocks kept in memory.
+const BLOCK_CACHE_CAPACITY: usize = 64;
+
 /// A high-level abstraction over a ikura RPC client.
 ///
 /// This client abstracts over the connection concerns and will perform automatic reconnections in
@@ -33,30 +37,49 @@ mod conn;
 ///
 /// # Clone
 ///
-/// This is a thin wrapper that can be cloned cheaply.
+/// This is a thin wrapper that can be cloned cheaply. The clones share the connection and the
+/// block cache.
 #[derive(Clone)]
 pub struct Client {
     connector: Arc<conn::Connector>,
+    cache: Arc<cache::BlockCache>,
+    metrics: Metrics,
 }
 
 impl Client {
     /// Creates a new instance of the client. This immediately tries to connect to the ikura
     /// node. It will *retry indefinitely* until it succeeds.
     ///
-    /// The RPC URL must be a valid URL pointing to a ikura node. If it's not a malformed URL,
-    /// returns an error.
-    #[tracing::instrument(level = Level::DEBUG)]
-    pub async fn new(rpc_url: String, no_retry: bool) -> anyhow::Result<Self> {
-        anyhow::ensure!(
-            url::Url::parse(&rpc_url).is_ok(),
-            "invalid RPC URL: {}",
-            rpc_url
-        );
+    /// Each of the RPC URLs must be a valid URL pointing to a ikura node. If any of them is
+    /// malformed, returns an error. When several URLs are given, the client connects to the node
+    /// with the highest finalized block and fails over to the others on connection errors. If
+    /// `cross_check` is set, the nodes are additionally required to agree on their finalized
+    /// blocks.
+    #[tracing::instrument(level = Level::DEBUG, skip(metrics))]
+    pub async fn new(
+        rpc_urls: Vec<String>,
+        cross_check: bool,
+        no_retry: bool,
+        metrics: Metrics,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(!rpc_urls.is_empty(), "no RPC URL provided");
+        for rpc_url in &rpc_urls {
+            anyhow::ensure!(
+                url::Url::parse(rpc_url).is_ok(),
+                "invalid RPC URL: {}",
+                rpc_url
+            );
+        }
 
-        tracing::info!("connecting to ikura node: {}", rpc_url);
-        let rpc_url = Arc::new(rpc_url);
+        tracing::info!("connecting to ikura node: {}", rpc_urls.join(", "));
+        let endpoints = Arc::new(conn::Endpoints {
+            urls: rpc_urls,
+            cross_check,
+        });
         let me = Self {
-            connector: Arc::new(conn::Connector::new(rpc_url)),
+            connector: Arc::new(conn::Connector::new(endpoints, metrics.clone())),
+            cache: Arc::new(cache::BlockCache::new(BLOCK_CACHE_CAPACITY)),
+            metrics,
         };
 
         match no_retry {
@@ -84,6 +107,48 @@ impl Client {
         }
     }
 
+    /// Returns the height of the last finalized block observed, or 0 if none has been observed
+    /// yet.
+    pub async fn finalized_height(&self) -> u64 {
+        let conn = self.connector.ensure_connected().await;
+        conn.finalized.height()
+    }
+
+    /// Blocks until the ikura node reports a best block other than `known`. Returns the hash of
+    /// the new best block.
+    pub async fn await_best_block_change(&self, known: Option<[u8; 32]>) -> [u8; 32] {
+        loop {
+            let conn = self.connector.ensure_connected().await;
+            let mut rx = conn.finalized.best_rx.clone();
+            loop {
+                let (_, best_hash) = *rx.borrow_and_update();
+                if best_hash != [0; 32] && Some(best_hash) != known {
+                    return best_hash;
+                }
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+            // The watcher task has terminated. Reset the connection and retry.
+            self.connector.reset().await;
+        }
+    }
+
+    /// Returns the limits on the blobs in a single block, as defined by the runtime.
+    pub async fn block_limits(&self) -> anyhow::Result<BlockLimits> {
+        let conn = self.connector.ensure_connected().await;
+        let constants = ikura_subxt::ikura::constants().blobs();
+        let max_blobs = conn.subxt.constants().at(&constants.max_blobs())?;
+        let max_total_blob_size = conn
+            .subxt
+            .constants()
+            .at(&constants.max_total_blob_size())?;
+        Ok(BlockLimits {
+            max_blobs,
+            max_total_blob_size,
+        })
+    }
+
     /// Returns the block hash of the block at the given height.
     ///
     /// If there is no block at the given height, returns `None`.
@@ -189,7 +254,13 @@ impl Client {
     /// `None` indicates that the best block should be used.
     #[tracing::instrument(level = Level::DEBUG, skip(self))]
     pub async fn get_block_at(&self, block_hash: Option<[u8; 32]>) -> anyhow::Result<Block> {
-        Block::from_header_and_extrinsics(self.get_header_and_extrinsics(block_hash).await?)
+        if let Some(block) = block_hash.and_then(|h| self.cache.get(&h)) {
+            return Ok(block);
+        }
+        let block =
+            Block::from_header_and_extrinsics(self.get_header_and_extrinsics(block_hash).await?)?;
+        self.cache.insert(&block);
+        Ok(block)
     }
 
     /// Returns the data of the block identified by the given block hash.
@@ -198,28 +269,44 @@ impl Client {
     /// `None` indicates that the best block should be used.
     #[tracing::instrument(level = Level::DEBUG, skip(self))]
     pub async fn await_block_at(&self, block_hash: Option<[u8; 32]>) -> anyhow::Result<Block> {
-        Block::from_header_and_extrinsics(self.await_header_and_extrinsics(block_hash).await)
+        if let Some(block) = block_hash.and_then(|h| self.cache.get(&h)) {
+            return Ok(block);
+        }
+        let block =
+            Block::from_header_and_extrinsics(self.await_header_and_extrinsics(block_hash).await)?;
+        self.cache.insert(&block);
+        Ok(block)
     }
 
-    /// Creates a submit blob extrinsic with the given data, namespace and signed with the given key
-    /// and nonce.
+    /// Creates a submit blob extrinsic with the given data, namespace and signed by the given
+    /// signer with the given nonce.
     pub async fn make_blob_extrinsic(
         &self,
         blob: Vec<u8>,
         namespace: ikura_nmt::Namespace,
-        key: &Keypair,
+        signer: &dyn BlobSigner,
         nonce: u64,
     ) -> anyhow::Result<BlobExtrinsic> {
         let conn = self.connector.ensure_connected().await;
         let extrinsic = ikura_subxt::ikura::tx()
             .blobs()
             .submit_blob(UnvalidatedNamespace(namespace.to_raw_bytes()), blob);
-        let signed = conn
+        let account_id = AccountId32(signer.public_key());
+        let partial = conn
             .subxt
             .tx()
-            .create_signed_with_nonce(&extrinsic, key, nonce, Default::default())
-            .with_context(|| format!("failed to validate or sign extrinsic"))?;
-        Ok(BlobExtrinsic(signed))
+            .create_partial_signed_with_nonce(&extrinsic, &account_id, nonce, Default::default())
+            .with_context(|| format!("failed to validate extrinsic"))?;
+        // The signer may be remote, so it only gets to see the payload.
+        let signature = signer
+            .sign(&partial.signer_payload())
+            .await
+            .with_context(|| format!("failed to sign extrinsic"))?;
+        let signed = partial.sign_with_address_and_signature(
+            &MultiAddress::Id(account_id),
+            &MultiSignature::Sr25519(signature),
+        );
+        Ok(BlobExtrinsic { signed, namespace })
     }
 
     /// Submit a blob with the given namespace and signed with the given key. The block is submitted
@@ -232,43 +319,105 @@ impl Client {
         &self,
         blob_extrinsic: &BlobExtrinsic,
     ) -> anyhow::Result<([u8; 32], u32)> {
-        let BlobExtrinsic(signed) = blob_extrinsic;
-        let events = signed
-            .submit_and_watch()
-            .await
-            .with_context(|| format!("failed to submit extrinsic"))?
-            .wait_for_finalized_success()
-            .await?;
+        let BlobExtrinsic { signed, namespace } = blob_extrinsic;
+        let start = std::time::Instant::now();
+        let res = async {
+            let events = signed
+                .submit_and_watch()
+                .await
+                .with_context(|| format!("failed to submit extrinsic"))?
+                .wait_for_finalized_success()
+                .await?;
+            anyhow::Ok(events)
+        }
+        .await;
+        let events = match res {
+            Ok(events) => {
+                self.metrics.on_blob_submitted(*namespace, start.elapsed());
+                events
+            }
+            Err(err) => {
+                self.metrics.on_blob_failed(*namespace);
+                return Err(err);
+            }
+        };
         let block_hash = events.block_hash();
         let extrinsic_index = events.extrinsic_index();
         Ok((block_hash.0, extrinsic_index))
     }
 
+    /// Returns the share at the given coordinates of the extended data square of the given block,
+    /// along with its proof. The proof is not verified.
+    #[tracing::instrument(level = Level::DEBUG, skip(self))]
+    pub async fn sample(
+        &self,
+        block_hash: [u8; 32],
+        row: u32,
+        col: u32,
+    ) -> anyhow::Result<ikura_nmt::Sample> {
+        let conn = self.connector.ensure_connected().await;
+        let sample = conn
+            .raw
+            .request(
+                "blobs_sample",
+                rpc_params![H256::from(block_hash), row, col],
+            )
+            .await
+            .with_context(|| format!("failed to sample ({}, {})", row, col))?;
+        Ok(sample)
+    }
+
     /// Returns the last nonce observed on the account of the signer.
-    pub async fn get_last_nonce(&self, key: &Keypair) -> anyhow::Result<u64> {
+    pub async fn get_last_nonce(&self, signer: &dyn BlobSigner) -> anyhow::Result<u64> {
         let conn = self.connector.ensure_connected().await;
         let nonce = conn
             .subxt
             .tx()
-            .account_nonce(
-                &<subxt_signer::sr25519::Keypair as Signer<IkuraConfig>>::account_id(key),
-            )
+            .account_nonce(&AccountId32(signer.public_key()))
             .await?;
         Ok(nonce)
     }
+
+    /// Returns the free balance of the account of the signer as of the latest block.
+    pub async fn get_free_balance(&self, signer: &dyn BlobSigner) -> anyhow::Result<u128> {
+        let conn = self.connector.ensure_connected().await;
+        let account_id = AccountId32(signer.public_key());
+        let account_info = conn
+            .subxt
+            .storage()
+            .at_latest()
+            .await?
+            .fetch_or_default(&ikura_subxt::ikura::storage().system().account(account_id))
+            .await?;
+        Ok(account_info.data.free)
+    }
+}
+
+/// The limits on the blobs in a single block.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockLimits {
+    /// The maximum number of blobs.
+    pub max_blobs: u32,
+    /// The maximum total size of the blobs, in bytes.
+    pub max_total_blob_size: u32,
 }
 
 /// Signed blob extrinsic. The extirnsic is signed against a certain nonce value.
 /// The extrinsic is ready to be submitted to the network.
-pub struct BlobExtrinsic(SubmittableExtrinsic<IkuraConfig, OnlineClient<IkuraConfig>>);
+pub struct BlobExtrinsic {
+    signed: SubmittableExtrinsic<IkuraConfig, OnlineClient<IkuraConfig>>,
+    /// The namespace the blob is submitted into. Used for the metrics.
+    namespace: Namespace,
+}
 
 impl fmt::Debug for BlobExtrinsic {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let hash = self.0.hash();
-        let len = self.0.encoded().len();
+        let hash = self.signed.hash();
+        let len = self.signed.encoded().len();
         f.debug_struct("BlobExtrinsic")
             .field("hash", &hash)
             .field("len", &len)
+            .field("namespace", &self.namespace)
             .finish()
     }
 }
@@ -325,15 +474,29 @@ fn extract_blobs(extrinsics: Vec<ikura_subxt::ExtrinsicDetails>) -> Vec<Blob> {
 
 /// Examines the header and extracts the tree root committed as one of the logs.
 ///
+/// The tag of the log tells the version of the leaves of the tree, so that the blocks with the
+/// original 68-byte leaves are still understood.
+///
 /// Returns None if no tree root was found or if the tree root was malformed.
-fn tree_root(header: &Header) -> Option<ikura_nmt::TreeRoot> {
+pub(crate) fn tree_root(header: &Header) -> Option<ikura_nmt::TreeRoot> {
     use subxt::config::substrate::DigestItem;
-    let nmt_digest_bytes = header.digest.logs.iter().find_map(|log| match log {
-        DigestItem::Other(ref bytes) if bytes.starts_with(b"snmt") => Some(&bytes[4..]),
+    header.digest.logs.iter().find_map(|log| match log {
+        DigestItem::Other(ref bytes) => ikura_nmt::TreeRoot::from_digest(bytes),
+        _ => None,
+    })
+}
+
+/// Examines the header and extracts the data square root committed as one of the logs.
+///
+/// Returns None if no data square root was found or if it was malformed.
+fn data_square_root(header: &Header) -> Option<ikura_nmt::DataSquareRoot> {
+    use subxt::config::substrate::DigestItem;
+    let digest_bytes = header.digest.logs.iter().find_map(|log| match log {
+        DigestItem::Other(ref bytes) if bytes.starts_with(b"sdsq") => Some(&bytes[4..]),
         _ => None,
     })?;
-    let nmt_root: [u8; 68] = nmt_digest_bytes.try_into().ok()?;
-    Some(ikura_nmt::TreeRoot::from_raw_bytes(&nmt_root))
+    let root: [u8; 36] = digest_bytes.try_into().ok()?;
+    Some(ikura_nmt::DataSquareRoot::from_raw_bytes(&root))
 }
 
 /// A small gadget that watches the finalized block headers and remembers the last one.
@@ -342,30 +505,66 @@ struct FinalizedHeadWatcher {
     ///
     /// Initialized with 0 as a dummy value.
     rx: watch::Receiver<(u64, [u8; 32])>,
+    /// The last best block header watch value.
+    ///
+    /// Initialized with 0 as a dummy value.
+    best_rx: watch::Receiver<(u64, [u8; 32])>,
     /// The join handle of the task that watches the finalized block headers.
     handle: tokio::task::JoinHandle<()>,
 }
 
 impl FinalizedHeadWatcher {
     /// Spawns the watch task.
-    async fn spawn(subxt: ikura_subxt::Client) -> Self {
+    ///
+    /// The task also follows the best block headers in order to report how far the finalized
+    /// block lags behind.
+    async fn spawn(subxt: ikura_subxt::Client, metrics: Metrics) -> Self {
         let (tx, rx) = watch::channel((0, [0; 32]));
+        let (best_tx, best_rx) = watch::channel((0, [0; 32]));
         let handle = tokio::spawn({
             async move {
                 // In case of an error, the subxt client becomes unusable. The task will be
                 // terminated in case of an error.
-                let Ok(mut stream) = subxt.backend().stream_finalized_block_headers().await else {
+                let Ok(mut finalized_stream) =
+                    subxt.backend().stream_finalized_block_headers().await
+                else {
                     return;
                 };
-                while let Some(header) = stream.next().await {
-                    let Ok((header, block_ref)) = header else {
-                        return;
-                    };
-                    let _ = tx.send((header.number as u64, block_ref.hash().0));
+                let Ok(mut best_stream) = subxt.backend().stream_best_block_headers().await else {
+                    return;
+                };
+                let (mut best_height, mut finalized_height) = (0, 0);
+                loop {
+                    tokio::select! {
+                        header = finalized_stream.next() => {
+                            let Some(Ok((header, block_ref))) = header else {
+                                return;
+                            };
+                            finalized_height = header.number as u64;
+                            let _ = tx.send((finalized_height, block_ref.hash().0));
+                        }
+                        header = best_stream.next() => {
+                            let Some(Ok((header, block_ref))) = header else {
+                                return;
+                            };
+                            best_height = header.number as u64;
+                            let _ = best_tx.send((best_height, block_ref.hash().0));
+                        }
+                    }
+                    metrics.on_chain_heads(best_height.max(finalized_height), finalized_height);
                 }
             }
         });
-        Self { rx, handle }
+        Self {
+            rx,
+            best_rx,
+            handle,
+        }
+    }
+
+    /// Returns the height of the last finalized block observed.
+    fn height(&self) -> u64 {
+        self.rx.borrow().0
     }
 
     /// Wait until the ikura node has finalized a block at the given height. Returns the block
@@ -413,7 +612,7 @@ mod err {
 }
 
 /// Represents a ikura block.
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Block {
     pub number: u64,
     #[serde(with = "ikura_serde_util::bytes32_hex")]
@@ -421,8 +620,14 @@ pub struct Block {
     #[serde(with = "ikura_serde_util::bytes32_hex")]
     pub parent_hash: [u8; 32],
     pub tree_root: ikura_nmt::TreeRoot,
+    /// The root of the erasure-coded data square. Absent in the blocks predating it.
+    #[serde(default)]
+    pub data_square_root: Option<ikura_nmt::DataSquareRoot>,
     pub timestamp: u64,
     pub blobs: Vec<Blob>,
+    /// The SCALE-encoded header of the block, which hashes to `hash`.
+    #[serde(default, with = "ikura_serde_util::bytes_hex")]
+    pub header: Vec<u8>,
 }
 
 impl Block {
@@ -431,6 +636,7 @@ impl Block {
     ) -> anyhow::Result<Self> {
         let (header, extrinsics) = value;
         let tree_root = tree_root(&header).ok_or_else(err::no_tree_root)?;
+        let data_square_root = data_square_root(&header);
         let timestamp = extract_timestamp(&extrinsics)?;
         let blobs = extract_blobs(extrinsics);
         tracing::debug!(?blobs, "found {} blobs in block", blobs.len());
@@ -439,14 +645,33 @@ impl Block {
             hash: header.hash().0,
             parent_hash: header.parent_hash.0,
             tree_root,
+            data_square_root,
             timestamp,
             blobs,
+            header: subxt::ext::codec::Encode::encode(&header),
         })
     }
+
+    /// Builds the proof of the blobs of the given namespace against the `tree_root` of the block.
+    ///
+    /// The proof covers all of the blobs of the namespace in the block, or proves that there are
+    /// none.
+    pub fn namespace_proof(&self, namespace: Namespace) -> ikura_nmt::NamespaceProof {
+        let leaf_version = self.tree_root.leaf_version;
+        let blob_metadata = self
+            .blobs
+            .iter()
+            .map(|blob| ikura_nmt::BlobMetadata {
+                namespace: blob.namespace,
+                leaf: blob.nmt_leaf(leaf_version),
+            })
+            .collect();
+        ikura_nmt::tree_from_blobs(blob_metadata, leaf_version).proof(namespace)
+    }
 }
 
 /// Represents a blob in a ikura block.
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Blob {
     pub extrinsic_index: u32,
     pub namespace: Namespace,
@@ -461,6 +686,20 @@ impl Blob {
         use sha2::Digest;
         sha2::Sha256::digest(&self.data).into()
     }
+
+    /// The leaf of the blob in the namespaced merkle tree with leaves of the given version.
+    pub fn nmt_leaf(&self, leaf_version: ikura_nmt::LeafVersion) -> ikura_nmt::NmtLeaf {
+        let shares = match leaf_version {
+            ikura_nmt::LeafVersion::V0 => None,
+            ikura_nmt::LeafVersion::V1 => Some(ikura_nmt::ShareCommitment::new(&self.data)),
+        };
+        ikura_nmt::NmtLeaf {
+            extrinsic_index: self.extrinsic_index,
+            who: self.sender,
+            blob_hash: self.sha2_hash(),
+            shares,
+        }
+    }
 }
 
 impl fmt::Debug for Blob {
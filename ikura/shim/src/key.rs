@@ -1,10 +1,110 @@
 //! Key management: sr25519 account key used for signing blob submission
 //! transactions.
+//!
+//! A key can be obtained from:
+//!
+//! - a file with a raw hex-encoded 32-byte seed,
+//! - a secret URI, i.e. a BIP-39 mnemonic phrase or a hex-encoded seed optionally followed by a
+//!   derivation path and a password (`<phrase>//rollup1///password`),
+//! - an encrypted JSON keystore as exported by polkadot-js.
+//!
+//! Several keys can be combined into a pool, so that the submissions are spread across multiple
+//! accounts.
+//!
+//! The derivation and the signing are done by `subxt_signer`, so the keys are compatible with the
+//! ones produced by `subkey` and polkadot-js. The keystores are decrypted with `scrypt` and
+//! `crypto_secretbox` and hold the expanded schnorrkel secret, which is used as is.
 
 use std::path::Path;
-use subxt_signer::sr25519::Seed;
 
-pub use subxt_signer::sr25519::Keypair;
+use anyhow::Context as _;
+use subxt::utils::AccountId32;
+use subxt_signer::{
+    bip39::Mnemonic,
+    sr25519::{self, Seed},
+    SecretUri,
+};
+
+/// The signing context used by Substrate for sr25519 signatures.
+const SIGNING_CTX: &[u8] = b"substrate";
+
+/// The name of the environment variable holding the password of the JSON keystore.
+pub const ENV_IKURA_KEYSTORE_PASSWORD: &str = "IKURA_KEYSTORE_PASSWORD";
+
+/// An sr25519 keypair.
+#[derive(Clone)]
+pub struct Keypair(Inner);
+
+#[derive(Clone)]
+enum Inner {
+    /// A key derived from a seed or a secret URI.
+    Signer(sr25519::Keypair),
+    /// A key loaded from a keystore, which only holds the expanded secret key.
+    Schnorrkel(schnorrkel::Keypair),
+}
+
+impl Keypair {
+    /// Creates a keypair from a 32-byte seed, also known as the mini secret key.
+    pub fn from_seed(seed: Seed) -> anyhow::Result<Self> {
+        Ok(Self(Inner::Signer(sr25519::Keypair::from_seed(seed)?)))
+    }
+
+    /// Creates a keypair from a secret URI, such as `<mnemonic>//rollup1` or `0x<seed>//rollup1`.
+    ///
+    /// If the phrase is omitted, the well-known development phrase is used, e.g. `//Alice`.
+    pub fn from_uri(uri: &str) -> anyhow::Result<Self> {
+        let uri = uri
+            .parse::<SecretUri>()
+            .map_err(|e| anyhow::anyhow!("invalid secret URI: {}", e))?;
+        let keypair = sr25519::Keypair::from_uri(&uri)
+            .map_err(|e| anyhow::anyhow!("invalid secret URI: {}", e))?;
+        Ok(Self(Inner::Signer(keypair)))
+    }
+
+    /// Returns the public key, which is also the account ID.
+    pub fn public_key(&self) -> [u8; 32] {
+        match &self.0 {
+            Inner::Signer(keypair) => keypair.public_key().0,
+            Inner::Schnorrkel(keypair) => keypair.public.to_bytes(),
+        }
+    }
+
+    /// Returns the SS58 address of the account, using the generic Substrate prefix.
+    pub fn address(&self) -> String {
+        AccountId32(self.public_key()).to_string()
+    }
+
+    /// Signs the given message producing a signature compatible with Substrate.
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        match &self.0 {
+            Inner::Signer(keypair) => keypair.sign(message).0,
+            Inner::Schnorrkel(keypair) => {
+                let context = schnorrkel::signing_context(SIGNING_CTX);
+                keypair.sign(context.bytes(message)).to_bytes()
+            }
+        }
+    }
+}
+
+/// Checks the sr25519 signature of the message made by the given public key.
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let (Ok(public_key), Ok(signature)) = (
+        schnorrkel::PublicKey::from_bytes(public_key),
+        schnorrkel::Signature::from_bytes(signature),
+    ) else {
+        return false;
+    };
+    public_key
+        .verify_simple(SIGNING_CTX, message, &signature)
+        .is_ok()
+}
+
+impl std::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret.
+        f.debug_tuple("Keypair").field(&self.address()).finish()
+    }
+}
 
 /// Load a key from the provided file path.
 ///
@@ -21,12 +121,182 @@ pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Keypair> {
         );
     }
     seed.copy_from_slice(&raw[..]);
-    Ok(Keypair::from_seed(seed)?)
+    Keypair::from_seed(seed)
+}
+
+/// Load an XChaCha20-Poly1305 key for the encryption of the blobs from a file containing 32
+/// hex-encoded bytes.
+pub fn load_encryption_key<P: AsRef<Path>>(path: P) -> anyhow::Result<[u8; 32]> {
+    let raw = hex::decode(std::fs::read_to_string(path)?.trim())?;
+    raw.try_into().map_err(|raw: Vec<u8>| {
+        anyhow::anyhow!(
+            "Encryption key length invalid, expected {} bytes, got {} bytes",
+            32,
+            raw.len()
+        )
+    })
+}
+
+/// Load a key from a file containing a secret URI, such as a mnemonic phrase optionally followed
+/// by a derivation path.
+pub fn load_uri<P: AsRef<Path>>(path: P) -> anyhow::Result<Keypair> {
+    let uri = std::fs::read_to_string(path)?;
+    Keypair::from_uri(uri.trim())
+}
+
+/// Load a pool of keys from a file containing one secret URI per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn load_pool<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<Keypair>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut keys = vec![];
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let key = Keypair::from_uri(line).with_context(|| format!("line {}", line_no + 1))?;
+        keys.push(key);
+    }
+    if keys.is_empty() {
+        anyhow::bail!("the key pool is empty");
+    }
+    Ok(keys)
+}
+
+/// Load a key from a JSON keystore file exported from polkadot-js, decrypting it with the given
+/// password.
+///
+/// Only the sr25519 keys encrypted with scrypt and xsalsa20-poly1305 (the format version 3)
+/// are supported.
+pub fn load_keystore<P: AsRef<Path>>(path: P, password: &str) -> anyhow::Result<Keypair> {
+    let json = std::fs::read_to_string(path)?;
+    let keystore: keystore::Keystore =
+        serde_json::from_str(&json).context("malformed JSON keystore")?;
+    keystore.decrypt(password)
 }
 
 /// The default dev key.
 pub fn alice() -> Keypair {
-    subxt_signer::sr25519::dev::alice()
+    Keypair(Inner::Signer(sr25519::dev::alice()))
+}
+
+/// Generates a new random 24-word mnemonic.
+pub fn generate_mnemonic() -> anyhow::Result<Mnemonic> {
+    let mut entropy = [0u8; 32];
+    getrandom::getrandom(&mut entropy)
+        .map_err(|e| anyhow::anyhow!("failed to obtain randomness: {}", e))?;
+    Mnemonic::from_entropy(&entropy).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+mod keystore {
+    //! The polkadot-js JSON keystore format.
+
+    use super::{Inner, Keypair};
+    use base64::Engine as _;
+    use crypto_secretbox::{
+        aead::{Aead as _, KeyInit as _},
+        XSalsa20Poly1305,
+    };
+
+    const SCRYPT_SALT_LEN: usize = 32;
+    const SCRYPT_PARAMS_LEN: usize = SCRYPT_SALT_LEN + 3 * 4;
+    const NONCE_LEN: usize = 24;
+    const PKCS8_HEADER: &[u8] = &[48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+    const PKCS8_DIVIDER: &[u8] = &[161, 35, 3, 33, 0];
+    const SECRET_KEY_LEN: usize = 64;
+    const PUBLIC_KEY_LEN: usize = 32;
+
+    #[derive(serde::Deserialize)]
+    pub struct Keystore {
+        encoded: String,
+        encoding: Encoding,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Encoding {
+        content: Vec<String>,
+        #[serde(rename = "type")]
+        ty: Vec<String>,
+        version: String,
+    }
+
+    impl Keystore {
+        pub fn decrypt(&self, password: &str) -> anyhow::Result<Keypair> {
+            let Encoding {
+                content,
+                ty,
+                version,
+            } = &self.encoding;
+            if version != "3" || ty.as_slice() != ["scrypt", "xsalsa20-poly1305"] {
+                anyhow::bail!(
+                    "unsupported keystore encoding: version {}, type {:?}",
+                    version,
+                    ty
+                );
+            }
+            if !content.iter().any(|c| c == "sr25519") {
+                anyhow::bail!(
+                    "unsupported key type {:?}, only sr25519 is supported",
+                    content
+                );
+            }
+
+            let encoded = base64::engine::general_purpose::STANDARD
+                .decode(&self.encoded)
+                .map_err(|e| anyhow::anyhow!("keystore is not valid base64: {}", e))?;
+            if encoded.len() < SCRYPT_PARAMS_LEN + NONCE_LEN {
+                anyhow::bail!("keystore is too short");
+            }
+
+            // The encoded data is: scrypt salt and params | nonce | ciphertext.
+            let (scrypt_params, rest) = encoded.split_at(SCRYPT_PARAMS_LEN);
+            let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+            let key = scrypt_key(password, scrypt_params)?;
+            let plaintext = XSalsa20Poly1305::new(&key.into())
+                .decrypt(nonce.into(), ciphertext)
+                .map_err(|_| anyhow::anyhow!("failed to decrypt the keystore, wrong password?"))?;
+
+            decode_pkcs8(&plaintext)
+        }
+    }
+
+    /// Derives the encryption key from the password with the scrypt parameters prepended to the
+    /// encoded keystore.
+    fn scrypt_key(password: &str, scrypt_params: &[u8]) -> anyhow::Result<[u8; 32]> {
+        let (salt, params) = scrypt_params.split_at(SCRYPT_SALT_LEN);
+        let read_u32 =
+            |i: usize| u32::from_le_bytes(params[i * 4..(i + 1) * 4].try_into().unwrap());
+        let (n, p, r) = (read_u32(0), read_u32(1), read_u32(2));
+        if !n.is_power_of_two() {
+            anyhow::bail!("invalid scrypt parameter N = {}", n);
+        }
+        let params = scrypt::Params::new(n.trailing_zeros() as u8, r, p, 32)
+            .map_err(|e| anyhow::anyhow!("invalid scrypt parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+            .map_err(|e| anyhow::anyhow!("scrypt failed: {}", e))?;
+        Ok(key)
+    }
+
+    /// Decodes the PKCS#8-like structure used by polkadot-js: header | secret key | divider |
+    /// public key. The secret key is in the ed25519-expanded format.
+    fn decode_pkcs8(plaintext: &[u8]) -> anyhow::Result<Keypair> {
+        let malformed = || anyhow::anyhow!("malformed keystore contents");
+        let rest = plaintext.strip_prefix(PKCS8_HEADER).ok_or_else(malformed)?;
+        if rest.len() != SECRET_KEY_LEN + PKCS8_DIVIDER.len() + PUBLIC_KEY_LEN {
+            return Err(malformed());
+        }
+        let (secret, rest) = rest.split_at(SECRET_KEY_LEN);
+        let public = rest.strip_prefix(PKCS8_DIVIDER).ok_or_else(malformed)?;
+
+        let secret = schnorrkel::SecretKey::from_ed25519_bytes(secret).map_err(|_| malformed())?;
+        let keypair = Keypair(Inner::Schnorrkel(secret.to_keypair()));
+        if keypair.public_key()[..] != public[..] {
+            anyhow::bail!("the public key in the keystore does not match the secret key");
+        }
+        Ok(keypair)
+    }
 }
 
 #[test]
@@ -46,3 +316,46 @@ fn load_alice_key() {
         hex::encode(expected_alice_pubk.as_ref()),
     );
 }
+
+#[test]
+fn derive_from_mnemonic() {
+    // `subkey inspect "bottom drive obey lake curtain smoke basket hold race lonely fit walk//Alice"`
+    // is the same as `subkey inspect //Alice`.
+    let uri = format!("{}//Alice", subxt_signer::DEV_PHRASE);
+    let key = Keypair::from_uri(&uri).unwrap();
+    assert_eq!(
+        key.address(),
+        "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+    );
+}
+
+#[test]
+fn load_key_pool() {
+    use std::fs;
+    use temp_dir::TempDir;
+    let dir = TempDir::new().unwrap();
+    let pool = dir.child("pool");
+    fs::write(&pool, "# rollup keys\n//Alice\n\n//Bob\n").unwrap();
+    let keys = load_pool(&pool).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0].public_key(), alice().public_key());
+    assert_eq!(
+        keys[1].address(),
+        "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"
+    );
+}
+
+#[test]
+fn load_polkadot_js_keystore() {
+    // An export of `//Alice` in the polkadot-js keystore format, with the password below.
+    const KEYSTORE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/alice-keystore.json");
+    const PASSWORD: &str = "ikura-test-password";
+
+    let key = load_keystore(KEYSTORE, PASSWORD).unwrap();
+    assert_eq!(key.public_key(), alice().public_key());
+    let signature = key.sign(b"blob");
+    assert!(verify(&alice().public_key(), b"blob", &signature));
+
+    let err = load_keystore(KEYSTORE, "wrong password").unwrap_err();
+    assert!(err.to_string().contains("wrong password"));
+}
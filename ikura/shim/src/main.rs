@@ -1,8 +1,11 @@
 mod cli;
 mod cmd;
+mod config;
 mod dock;
 mod ikura_rpc;
 mod key;
+mod metrics;
+mod signer;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -0,0 +1,293 @@
+//! Prometheus metrics of the shim.
+//!
+//! The metrics are always collected. They are only exposed to the outside world if the metrics
+//! server is started with [`serve`].
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use prometheus::{
+    Encoder as _, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// The buckets for the submission latency histogram, in seconds.
+///
+/// A submission is measured until the block is finalized, so it normally takes at least a couple
+/// of parachain blocks.
+const SUBMISSION_LATENCY_BUCKETS: &[f64] = &[
+    6.0, 12.0, 18.0, 24.0, 30.0, 45.0, 60.0, 90.0, 120.0, 180.0, 300.0,
+];
+
+/// A handle to the shim metrics.
+///
+/// # Clone
+///
+/// This is a thin wrapper that can be cloned cheaply.
+#[derive(Clone)]
+pub struct Metrics(Arc<Inner>);
+
+struct Inner {
+    registry: Registry,
+    blobs_submitted: IntCounterVec,
+    blobs_failed: IntCounterVec,
+    submission_latency: HistogramVec,
+    node_reconnects: IntCounter,
+    finalized_height: IntGauge,
+    finalized_height_lag: IntGauge,
+    rpc_requests: IntCounterVec,
+    submit_key_balance: GaugeVec,
+    rate_limit: IntGaugeVec,
+    rate_limited: IntCounterVec,
+    submission_queue_len: IntGauge,
+}
+
+impl Metrics {
+    /// Creates a new set of metrics registered within a fresh registry.
+    pub fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new_custom(Some("ikura_shim".to_string()), None)?;
+        let blobs_submitted = register(
+            &registry,
+            IntCounterVec::new(
+                Opts::new(
+                    "blobs_submitted_total",
+                    "Number of blobs submitted and finalized, per namespace",
+                ),
+                &["namespace"],
+            )?,
+        )?;
+        let blobs_failed = register(
+            &registry,
+            IntCounterVec::new(
+                Opts::new(
+                    "blobs_failed_total",
+                    "Number of blobs that failed to be submitted, per namespace",
+                ),
+                &["namespace"],
+            )?,
+        )?;
+        let submission_latency = register(
+            &registry,
+            HistogramVec::new(
+                HistogramOpts::new(
+                    "submission_latency_seconds",
+                    "Time from submitting a blob until the block containing it is finalized",
+                )
+                .buckets(SUBMISSION_LATENCY_BUCKETS.to_vec()),
+                &["namespace"],
+            )?,
+        )?;
+        let node_reconnects = register(
+            &registry,
+            IntCounter::new(
+                "node_reconnects_total",
+                "Number of times the connection to the ikura node was reset",
+            )?,
+        )?;
+        let finalized_height = register(
+            &registry,
+            IntGauge::new(
+                "finalized_height",
+                "The height of the last finalized block observed",
+            )?,
+        )?;
+        let finalized_height_lag = register(
+            &registry,
+            IntGauge::new(
+                "finalized_height_lag",
+                "The number of blocks the finalized block lags behind the best block",
+            )?,
+        )?;
+        let rpc_requests = register(
+            &registry,
+            IntCounterVec::new(
+                Opts::new(
+                    "rpc_requests_total",
+                    "Number of requests served by the docks, per dock and method",
+                ),
+                &["dock", "method"],
+            )?,
+        )?;
+        let submit_key_balance = register(
+            &registry,
+            GaugeVec::new(
+                Opts::new(
+                    "submit_key_balance",
+                    "The free balance of the submission key accounts, in the smallest units",
+                ),
+                &["account"],
+            )?,
+        )?;
+        let rate_limit = register(
+            &registry,
+            IntGaugeVec::new(
+                Opts::new(
+                    "rate_limit",
+                    "The configured submission rate limits, per dock, scope and kind",
+                ),
+                &["dock", "scope", "kind"],
+            )?,
+        )?;
+        let rate_limited = register(
+            &registry,
+            IntCounterVec::new(
+                Opts::new(
+                    "rate_limited_total",
+                    "Number of submissions rejected due to a rate limit, per dock, scope and kind",
+                ),
+                &["dock", "scope", "kind"],
+            )?,
+        )?;
+        let submission_queue_len = register(
+            &registry,
+            IntGauge::new(
+                "submission_queue_len",
+                "The number of blobs waiting in the submission queue",
+            )?,
+        )?;
+        Ok(Self(Arc::new(Inner {
+            registry,
+            blobs_submitted,
+            blobs_failed,
+            submission_latency,
+            node_reconnects,
+            finalized_height,
+            finalized_height_lag,
+            rpc_requests,
+            submit_key_balance,
+            rate_limit,
+            rate_limited,
+            submission_queue_len,
+        })))
+    }
+
+    /// Records a blob that was successfully submitted into the given namespace and took `latency`
+    /// to get finalized.
+    pub fn on_blob_submitted(&self, namespace: ikura_nmt::Namespace, latency: Duration) {
+        let namespace = namespace.to_string();
+        self.0
+            .blobs_submitted
+            .with_label_values(&[&namespace])
+            .inc();
+        self.0
+            .submission_latency
+            .with_label_values(&[&namespace])
+            .observe(latency.as_secs_f64());
+    }
+
+    /// Records a blob that failed to be submitted into the given namespace.
+    pub fn on_blob_failed(&self, namespace: ikura_nmt::Namespace) {
+        self.0
+            .blobs_failed
+            .with_label_values(&[&namespace.to_string()])
+            .inc();
+    }
+
+    /// Records a reset of the connection to the ikura node.
+    pub fn on_node_reconnect(&self) {
+        self.0.node_reconnects.inc();
+    }
+
+    /// Records the last observed best and finalized block heights.
+    pub fn on_chain_heads(&self, best: u64, finalized: u64) {
+        self.0.finalized_height.set(finalized as i64);
+        self.0
+            .finalized_height_lag
+            .set(best.saturating_sub(finalized) as i64);
+    }
+
+    /// Records a request served by the given dock.
+    pub fn on_rpc_request(&self, dock: &str, method: &str) {
+        self.0.rpc_requests.with_label_values(&[dock, method]).inc();
+    }
+
+    /// Records the last observed free balance of the given submission key account.
+    pub fn on_key_balance(&self, account: &str, free: u128) {
+        self.0
+            .submit_key_balance
+            .with_label_values(&[account])
+            .set(free as f64);
+    }
+
+    /// Records a rate limit configured for the given dock. The scope is either `namespace` or
+    /// `client`.
+    pub fn on_rate_limit(&self, dock: &str, scope: &str, kind: &str, limit: u64) {
+        self.0
+            .rate_limit
+            .with_label_values(&[dock, scope, kind])
+            .set(limit as i64);
+    }
+
+    /// Records a submission rejected by the given dock due to a rate limit.
+    pub fn on_rate_limited(&self, dock: &str, scope: &str, kind: &str) {
+        self.0
+            .rate_limited
+            .with_label_values(&[dock, scope, kind])
+            .inc();
+    }
+
+    /// Records the number of blobs waiting in the submission queue.
+    pub fn on_submission_queue_len(&self, len: usize) {
+        self.0.submission_queue_len.set(len as i64);
+    }
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(
+    registry: &Registry,
+    metric: T,
+) -> anyhow::Result<T> {
+    registry.register(Box::new(metric.clone()))?;
+    Ok(metric)
+}
+
+/// Binds the metrics server to the given address and returns a future that serves the metrics
+/// under `/metrics` in the Prometheus text format.
+///
+/// Binding happens eagerly, so that a misconfiguration is reported before the server is spawned.
+pub fn serve(
+    metrics: Metrics,
+    listen_on: SocketAddr,
+) -> anyhow::Result<impl std::future::Future<Output = anyhow::Result<()>>> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = metrics.clone();
+                async move { Ok::<_, Infallible>(handle_request(&metrics, request)) }
+            }))
+        }
+    });
+    let server = hyper::Server::try_bind(&listen_on)?.serve(make_service);
+    Ok(async move {
+        server.await?;
+        Ok(())
+    })
+}
+
+fn handle_request(metrics: &Metrics, request: Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("not found"))
+            .expect("the response is well-formed; qed");
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(err) = encoder.encode(&metrics.0.registry.gather(), &mut buffer) {
+        tracing::error!(?err, "failed to encode metrics");
+        return Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::empty())
+            .expect("the response is well-formed; qed");
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, encoder.format_type())
+        .body(Body::from(buffer))
+        .expect("the response is well-formed; qed")
+}
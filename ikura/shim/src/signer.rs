@@ -0,0 +1,209 @@
+//! Signers of the blob submission transactions.
+//!
+//! A signer is either a [`Keypair`] held by the shim itself or a [`RemoteSigner`], which asks an
+//! external process over JSON-RPC to sign the transactions. The latter allows running the shim
+//! without any secrets on the host.
+//!
+//! The remote signer protocol consists of two methods:
+//!
+//! - `signer_publicKeys` returns the hex-encoded sr25519 public keys the signer is willing to sign
+//!   with.
+//! - `signer_signPayload` takes the public key and the hex-encoded signer payload of a transaction
+//!   and returns the hex-encoded 64-byte sr25519 signature of the payload, made with the signing
+//!   context `substrate`.
+//!
+//! [`serve_local`] implements the protocol with local keys. It is meant as a stand-in for an
+//! external signer in tests and during development.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Context as _;
+use jsonrpsee::{
+    http_client::{HttpClient, HttpClientBuilder},
+    proc_macros::rpc,
+    server::Server,
+    types::ErrorObjectOwned,
+};
+use subxt::utils::AccountId32;
+
+use crate::key::{self, Keypair};
+
+/// Produces the sr25519 signatures of the blob submission transactions.
+#[async_trait::async_trait]
+pub trait BlobSigner: Send + Sync {
+    /// Returns the public key, which is also the account ID.
+    fn public_key(&self) -> [u8; 32];
+
+    /// Signs the given signer payload of a transaction.
+    async fn sign(&self, payload: &[u8]) -> anyhow::Result<[u8; 64]>;
+
+    /// Returns the SS58 address of the account, using the generic Substrate prefix.
+    fn address(&self) -> String {
+        AccountId32(self.public_key()).to_string()
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobSigner for Keypair {
+    fn public_key(&self) -> [u8; 32] {
+        Keypair::public_key(self)
+    }
+
+    async fn sign(&self, payload: &[u8]) -> anyhow::Result<[u8; 64]> {
+        Ok(Keypair::sign(self, payload))
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SignRequest {
+    #[serde(with = "ikura_serde_util::bytes32_hex")]
+    pub public_key: [u8; 32],
+    #[serde(with = "ikura_serde_util::bytes_hex")]
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SignResponse {
+    #[serde(with = "ikura_serde_util::bytes_hex")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct PublicKey(#[serde(with = "ikura_serde_util::bytes32_hex")] pub [u8; 32]);
+
+#[rpc(client, server)]
+pub trait RemoteSignerRpc {
+    #[method(name = "signer_publicKeys")]
+    async fn public_keys(&self) -> Result<Vec<PublicKey>, ErrorObjectOwned>;
+
+    #[method(name = "signer_signPayload")]
+    async fn sign_payload(&self, request: SignRequest) -> Result<SignResponse, ErrorObjectOwned>;
+}
+
+/// A signer that delegates the signing to an external process over JSON-RPC via HTTP.
+pub struct RemoteSigner {
+    client: Arc<HttpClient>,
+    public_key: [u8; 32],
+}
+
+impl RemoteSigner {
+    /// Connects to the remote signer at the given URL and returns a signer for each of the keys
+    /// it holds.
+    pub async fn connect(url: &str) -> anyhow::Result<Vec<RemoteSigner>> {
+        let client = Arc::new(
+            HttpClientBuilder::default()
+                .build(url)
+                .with_context(|| format!("invalid remote signer URL '{}'", url))?,
+        );
+        let public_keys = client
+            .public_keys()
+            .await
+            .with_context(|| format!("failed to obtain the public keys from '{}'", url))?;
+        if public_keys.is_empty() {
+            anyhow::bail!("the remote signer at '{}' holds no keys", url);
+        }
+        Ok(public_keys
+            .into_iter()
+            .map(|PublicKey(public_key)| RemoteSigner {
+                client: client.clone(),
+                public_key,
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobSigner for RemoteSigner {
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    async fn sign(&self, payload: &[u8]) -> anyhow::Result<[u8; 64]> {
+        let request = SignRequest {
+            public_key: self.public_key,
+            payload: payload.to_vec(),
+        };
+        let SignResponse { signature } = self
+            .client
+            .sign_payload(request)
+            .await
+            .context("remote signer request failed")?;
+        let signature: [u8; 64] = signature.try_into().map_err(|s: Vec<u8>| {
+            anyhow::anyhow!("signature must be 64 bytes long, but was {}", s.len())
+        })?;
+        // Don't trust the signer blindly, a bad signature would only be detected by the node.
+        if !key::verify(&self.public_key, payload, &signature) {
+            anyhow::bail!("the remote signer returned an invalid signature");
+        }
+        Ok(signature)
+    }
+}
+
+struct LocalSigner {
+    keypairs: Vec<Keypair>,
+}
+
+#[async_trait::async_trait]
+impl RemoteSignerRpcServer for LocalSigner {
+    async fn public_keys(&self) -> Result<Vec<PublicKey>, ErrorObjectOwned> {
+        Ok(self
+            .keypairs
+            .iter()
+            .map(|keypair| PublicKey(keypair.public_key()))
+            .collect())
+    }
+
+    async fn sign_payload(&self, request: SignRequest) -> Result<SignResponse, ErrorObjectOwned> {
+        let keypair = self
+            .keypairs
+            .iter()
+            .find(|keypair| keypair.public_key() == request.public_key)
+            .ok_or_else(|| {
+                ErrorObjectOwned::owned(
+                    jsonrpsee::types::error::INVALID_PARAMS_CODE,
+                    "unknown public key",
+                    None::<()>,
+                )
+            })?;
+        Ok(SignResponse {
+            signature: keypair.sign(&request.payload).to_vec(),
+        })
+    }
+}
+
+/// Binds a remote signer server holding the given keys to the given address and returns the
+/// address it is bound to along with a future that serves the requests.
+pub async fn serve_local(
+    keypairs: Vec<Keypair>,
+    listen_on: SocketAddr,
+) -> anyhow::Result<(
+    SocketAddr,
+    impl std::future::Future<Output = anyhow::Result<()>>,
+)> {
+    let server = Server::builder().build(listen_on).await?;
+    let local_addr = server.local_addr()?;
+    let handle = server.start(LocalSigner { keypairs }.into_rpc());
+    Ok((local_addr, async move {
+        handle.stopped().await;
+        Ok(())
+    }))
+}
+
+#[tokio::test]
+async fn remote_signer_round_trip() {
+    let (addr, server) = serve_local(vec![key::alice()], "127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+    tokio::spawn(server);
+
+    let signers = RemoteSigner::connect(&format!("http://{}", addr))
+        .await
+        .unwrap();
+    assert_eq!(signers.len(), 1);
+    assert_eq!(signers[0].public_key(), key::alice().public_key());
+
+    let payload = b"blob submission payload";
+    let signature = signers[0].sign(payload).await.unwrap();
+    assert!(key::verify(&key::alice().public_key(), payload, &signature));
+}